@@ -1,8 +1,11 @@
 use std::{
-    cmp, fmt,
+    cmp,
+    collections::VecDeque,
+    fmt,
     fs::File,
     future::Future,
     io::{self, Read, Seek},
+    mem,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -14,6 +17,8 @@ use actix_web::{
 use bytes::Bytes;
 use futures_core::{ready, Stream};
 
+use crate::range::HttpRange;
+
 #[doc(hidden)]
 /// A helper created from a `std::fs::File` which reads the file
 /// chunk-by-chunk on a `ThreadPool`.
@@ -38,6 +43,14 @@ impl ChunkedReadFile {
             counter: 0,
         }
     }
+
+    /// Returns the underlying file if no read is currently in flight.
+    fn into_file(self) -> Option<File> {
+        match self.state {
+            ChunkedReadFileState::File(file) => file,
+            ChunkedReadFileState::Future(_) => None,
+        }
+    }
 }
 
 impl fmt::Debug for ChunkedReadFile {
@@ -96,3 +109,120 @@ impl Stream for ChunkedReadFile {
         }
     }
 }
+
+/// Streams several byte ranges of a file as a `multipart/byteranges` body.
+///
+/// Each part is read through a [`ChunkedReadFile`] which hands the file back once its range
+/// has been fully read, so a single file handle is reused for all parts.
+pub(crate) struct ChunkedMultipartFile {
+    parts: VecDeque<(Bytes, HttpRange)>,
+    trailer: Option<Bytes>,
+    state: ChunkedMultipartFileState,
+}
+
+enum ChunkedMultipartFileState {
+    Idle(Option<File>),
+    Reading(ChunkedReadFile),
+}
+
+impl ChunkedMultipartFile {
+    pub(crate) fn new(
+        file: File,
+        ranges: &[HttpRange],
+        total_size: u64,
+        content_type: &str,
+        boundary: &str,
+    ) -> Self {
+        let parts = ranges
+            .iter()
+            .map(|range| {
+                let head = format!(
+                    "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                    boundary,
+                    content_type,
+                    range.start,
+                    range.start + range.length - 1,
+                    total_size
+                );
+
+                (Bytes::from(head), *range)
+            })
+            .collect();
+
+        let trailer = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+
+        Self {
+            parts,
+            trailer: Some(trailer),
+            state: ChunkedMultipartFileState::Idle(Some(file)),
+        }
+    }
+
+    /// Total number of bytes this stream will yield.
+    pub(crate) fn size(&self) -> u64 {
+        let parts = self
+            .parts
+            .iter()
+            .map(|(head, range)| head.len() as u64 + range.length)
+            .sum::<u64>();
+
+        parts
+            + self
+                .trailer
+                .as_ref()
+                .map_or(0, |trailer| trailer.len() as u64)
+    }
+}
+
+impl fmt::Debug for ChunkedMultipartFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChunkedMultipartFile")
+    }
+}
+
+impl Stream for ChunkedMultipartFile {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        match this.state {
+            ChunkedMultipartFileState::Idle(ref mut file) => match this.parts.pop_front() {
+                Some((head, range)) => {
+                    let file = file
+                        .take()
+                        .expect("ChunkedMultipartFile polled after completion");
+
+                    this.state = ChunkedMultipartFileState::Reading(ChunkedReadFile::new(
+                        range.length,
+                        range.start,
+                        file,
+                    ));
+
+                    Poll::Ready(Some(Ok(head)))
+                }
+
+                None => Poll::Ready(this.trailer.take().map(Ok)),
+            },
+
+            ChunkedMultipartFileState::Reading(ref mut reader) => {
+                match ready!(Pin::new(reader).poll_next(cx)) {
+                    Some(res) => Poll::Ready(Some(res)),
+
+                    None => {
+                        let reader = match mem::replace(
+                            &mut this.state,
+                            ChunkedMultipartFileState::Idle(None),
+                        ) {
+                            ChunkedMultipartFileState::Reading(reader) => reader,
+                            ChunkedMultipartFileState::Idle(_) => unreachable!(),
+                        };
+
+                        this.state = ChunkedMultipartFileState::Idle(reader.into_file());
+                        self.poll_next(cx)
+                    }
+                }
+            }
+        }
+    }
+}
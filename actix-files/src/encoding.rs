@@ -1,3 +1,4 @@
+use actix_web::http::header::{q, ContentEncoding, QualityItem};
 use mime::Mime;
 
 /// Transforms MIME `text/*` types into their UTF-8 equivalent, if supported.
@@ -39,6 +40,51 @@ pub(crate) fn equiv_utf8_text(ct: Mime) -> Mime {
     ct
 }
 
+/// Returns the file extension used for pre-compressed variants of `encoding`, if it has one.
+pub(crate) fn precompressed_ext(encoding: ContentEncoding) -> Option<&'static str> {
+    match encoding {
+        ContentEncoding::Br => Some("br"),
+        ContentEncoding::Gzip => Some("gz"),
+        ContentEncoding::Zstd => Some("zst"),
+        _ => None,
+    }
+}
+
+/// Orders `available` encodings by preference according to an `Accept-Encoding` header value.
+///
+/// Encodings the client did not accept (explicitly or through `*`) or gave `q=0` are dropped.
+/// Encodings with equal quality keep the relative (server preferred) order of `available`.
+pub(crate) fn negotiate_encodings(
+    accept_encoding: &str,
+    available: &[ContentEncoding],
+) -> Vec<ContentEncoding> {
+    let items = accept_encoding
+        .split(',')
+        .filter_map(|item| item.trim().parse::<QualityItem<String>>().ok())
+        .collect::<Vec<_>>();
+
+    let quality_of = |encoding: ContentEncoding| {
+        items
+            .iter()
+            .find(|item| item.item.eq_ignore_ascii_case(encoding.as_str()))
+            .or_else(|| items.iter().find(|item| item.item == "*"))
+            .map(|item| item.quality)
+    };
+
+    let mut accepted = available
+        .iter()
+        .filter_map(|&encoding| match quality_of(encoding) {
+            Some(quality) if quality > q(0) => Some((encoding, quality)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // stable sort keeps server preference between equal q-values
+    accepted.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    accepted.into_iter().map(|(encoding, _)| encoding).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +95,20 @@ mod tests {
         assert_eq!(equiv_utf8_text(mime::TEXT_XML), mime::TEXT_XML);
         assert_eq!(equiv_utf8_text(mime::IMAGE_PNG), mime::IMAGE_PNG);
     }
+
+    #[test]
+    fn test_negotiate_encodings() {
+        use ContentEncoding::{Br, Gzip};
+
+        assert_eq!(negotiate_encodings("gzip, br", &[Br, Gzip]), vec![Br, Gzip]);
+        assert_eq!(negotiate_encodings("gzip, br", &[Gzip, Br]), vec![Gzip, Br]);
+        assert_eq!(
+            negotiate_encodings("gzip;q=1, br;q=0.5", &[Br, Gzip]),
+            vec![Gzip, Br]
+        );
+        assert_eq!(negotiate_encodings("gzip", &[Br, Gzip]), vec![Gzip]);
+        assert_eq!(negotiate_encodings("*, gzip;q=0", &[Br, Gzip]), vec![Br]);
+        assert_eq!(negotiate_encodings("identity", &[Br, Gzip]), vec![]);
+        assert_eq!(negotiate_encodings("", &[Br, Gzip]), vec![]);
+    }
 }
@@ -5,7 +5,7 @@ use actix_web::{
     dev::{AppService, HttpServiceFactory, ResourceDef, ServiceRequest, ServiceResponse},
     error::Error,
     guard::Guard,
-    http::header::DispositionType,
+    http::header::{ContentEncoding, DispositionType},
     HttpRequest,
 };
 use futures_util::future::{ok, FutureExt, LocalBoxFuture};
@@ -38,6 +38,8 @@ pub struct Files {
     file_flags: named::Flags,
     guards: Option<Rc<dyn Guard>>,
     hidden_files: bool,
+    max_ranges: usize,
+    precompressed: Vec<ContentEncoding>,
 }
 
 impl fmt::Debug for Files {
@@ -60,6 +62,8 @@ impl Clone for Files {
             mime_override: self.mime_override.clone(),
             guards: self.guards.clone(),
             hidden_files: self.hidden_files,
+            max_ranges: self.max_ranges,
+            precompressed: self.precompressed.clone(),
         }
     }
 }
@@ -105,6 +109,8 @@ impl Files {
             file_flags: named::Flags::default(),
             guards: None,
             hidden_files: false,
+            max_ranges: named::DEFAULT_MAX_RANGES,
+            precompressed: Vec::new(),
         }
     }
 
@@ -179,6 +185,44 @@ impl Files {
         self
     }
 
+    /// Sets the maximum number of byte ranges served for a single multi-range request.
+    ///
+    /// See [`NamedFile::set_max_ranges`](crate::NamedFile::set_max_ranges) for details.
+    ///
+    /// Default is 16.
+    #[inline]
+    pub fn max_ranges(mut self, max_ranges: usize) -> Self {
+        self.max_ranges = max_ranges;
+        self
+    }
+
+    /// Enables serving pre-compressed variants of files.
+    ///
+    /// For a request to `app.js`, the sibling files `app.js.br`, `app.js.gz` and `app.js.zst` are
+    /// considered for the `Br`, `Gzip` and `Zstd` encodings respectively; other encodings are
+    /// ignored. The variant that exists on disk and is most preferred by the request's
+    /// `Accept-Encoding` header is served with a matching `Content-Encoding` header. When the
+    /// client ranks several encodings equally, the order given here decides. Content type and
+    /// disposition are always derived from the original file name and responses carry
+    /// `Vary: accept-encoding`.
+    ///
+    /// By default pre-compressed files are not used.
+    ///
+    /// ```rust
+    /// use actix_files::Files;
+    /// use actix_web::http::ContentEncoding;
+    ///
+    /// let files = Files::new("/static", ".")
+    ///     .use_precompressed(vec![ContentEncoding::Br, ContentEncoding::Gzip]);
+    /// ```
+    pub fn use_precompressed<I>(mut self, encodings: I) -> Self
+    where
+        I: IntoIterator<Item = ContentEncoding>,
+    {
+        self.precompressed = encodings.into_iter().collect();
+        self
+    }
+
     /// Specifies custom guards to use for directory listings and files.
     ///
     /// Default behaviour allows GET and HEAD.
@@ -260,6 +304,8 @@ impl ServiceFactory<ServiceRequest> for Files {
             file_flags: self.file_flags,
             guards: self.guards.clone(),
             hidden_files: self.hidden_files,
+            max_ranges: self.max_ranges,
+            precompressed: self.precompressed.clone(),
         };
 
         if let Some(ref default) = *self.default.borrow() {
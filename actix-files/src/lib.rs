@@ -436,6 +436,88 @@ mod tests {
         assert_eq!(content_range.to_str().unwrap(), "bytes */100");
     }
 
+    #[actix_rt::test]
+    async fn test_named_file_multiple_ranges() {
+        let srv = test::start(|| App::new().service(Files::new("/", ".")));
+
+        let mut response = srv
+            .get("/tests/test.binary")
+            .insert_header((header::RANGE, "bytes=0-4,90-99"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(response.headers().get(header::CONTENT_RANGE).is_none());
+
+        let content_type = response.headers().get(header::CONTENT_TYPE).unwrap();
+        let content_type = content_type.to_str().unwrap().to_owned();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+
+        let content_length = response.headers().get(header::CONTENT_LENGTH).unwrap();
+        let content_length = content_length.to_str().unwrap().parse::<usize>().unwrap();
+
+        let body = response.body().await.unwrap();
+        assert_eq!(body.len(), content_length);
+
+        let data = fs::read("tests/test.binary").unwrap();
+        let mut expected = Vec::new();
+        expected.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: application/octet-stream\r\n\
+                 Content-Range: bytes 0-4/100\r\n\r\n",
+                boundary
+            )
+            .as_bytes(),
+        );
+        expected.extend_from_slice(&data[0..5]);
+        expected.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: application/octet-stream\r\n\
+                 Content-Range: bytes 90-99/100\r\n\r\n",
+                boundary
+            )
+            .as_bytes(),
+        );
+        expected.extend_from_slice(&data[90..100]);
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        assert_eq!(body, Bytes::from(expected));
+
+        // overlapping ranges are coalesced into a single part
+        let response = srv
+            .get("/tests/test.binary")
+            .insert_header((header::RANGE, "bytes=10-20,15-30"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_range = response.headers().get(header::CONTENT_RANGE).unwrap();
+        assert_eq!(content_range.to_str().unwrap(), "bytes 10-30/100");
+    }
+
+    #[actix_rt::test]
+    async fn test_named_file_max_ranges() {
+        let srv =
+            test::init_service(App::new().service(Files::new("/", ".").max_ranges(2))).await;
+
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .insert_header((header::RANGE, "bytes=0-1,10-11"))
+            .to_request();
+        let response = test::call_service(&srv, request).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .insert_header((header::RANGE, "bytes=0-1,10-11,20-21"))
+            .to_request();
+        let response = test::call_service(&srv, request).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        let content_range = response.headers().get(header::CONTENT_RANGE).unwrap();
+        assert_eq!(content_range.to_str().unwrap(), "bytes */100");
+    }
+
     #[actix_rt::test]
     async fn test_named_file_content_length_headers() {
         let srv = test::start(|| App::new().service(Files::new("/", ".")));
@@ -19,9 +19,14 @@ use actix_web::{
 };
 use bitflags::bitflags;
 use mime_guess::from_path;
+use rand::{distributions::Alphanumeric, Rng};
 
-use crate::ChunkedReadFile;
-use crate::{encoding::equiv_utf8_text, range::HttpRange};
+use crate::{
+    chunked::ChunkedMultipartFile,
+    encoding::equiv_utf8_text,
+    range::{coalesce, HttpRange},
+    ChunkedReadFile,
+};
 
 bitflags! {
     pub(crate) struct Flags: u8 {
@@ -38,6 +43,9 @@ impl Default for Flags {
     }
 }
 
+/// Default cap on the number of (coalesced) ranges served in a single response.
+pub(crate) const DEFAULT_MAX_RANGES: usize = 16;
+
 /// A file with an associated name.
 #[derive(Debug)]
 pub struct NamedFile {
@@ -50,6 +58,8 @@ pub struct NamedFile {
     pub(crate) content_type: mime::Mime,
     pub(crate) content_disposition: header::ContentDisposition,
     pub(crate) encoding: Option<ContentEncoding>,
+    pub(crate) precompressed: Option<ContentEncoding>,
+    pub(crate) max_ranges: usize,
 }
 
 impl NamedFile {
@@ -128,8 +138,10 @@ impl NamedFile {
             md,
             modified,
             encoding,
+            precompressed: None,
             status_code: StatusCode::OK,
             flags: Flags::default(),
+            max_ranges: DEFAULT_MAX_RANGES,
         })
     }
 
@@ -242,6 +254,18 @@ impl NamedFile {
         self
     }
 
+    /// Sets the maximum number of byte ranges served for a single multi-range request.
+    ///
+    /// Overlapping and adjacent ranges are merged before this limit is checked. Requests that
+    /// still ask for more ranges are rejected with `416 Range Not Satisfiable`.
+    ///
+    /// Default is 16.
+    #[inline]
+    pub fn set_max_ranges(mut self, max_ranges: usize) -> Self {
+        self.max_ranges = max_ranges;
+        self
+    }
+
     pub(crate) fn etag(&self) -> Option<header::EntityTag> {
         // This etag format is similar to Apache's.
         self.modified.as_ref().map(|mtime| {
@@ -297,6 +321,10 @@ impl NamedFile {
                 res.encoding(current_encoding);
             }
 
+            if let Some(precompressed) = self.precompressed {
+                res.insert_header(precompressed);
+            }
+
             let reader = ChunkedReadFile::new(self.md.len(), 0, self.file);
 
             return res.streaming(reader);
@@ -371,6 +399,11 @@ impl NamedFile {
             resp.encoding(current_encoding);
         }
 
+        // file contents are already encoded; also prevents compressing them again
+        if let Some(precompressed) = self.precompressed {
+            resp.insert_header(precompressed);
+        }
+
         if let Some(lm) = last_modified {
             resp.insert_header((header::LAST_MODIFIED, lm.to_string()));
         }
@@ -383,23 +416,49 @@ impl NamedFile {
 
         let mut length = self.md.len();
         let mut offset = 0;
+        let mut multi_ranges = None;
 
         // check for range header
         if let Some(ranges) = req.headers().get(header::RANGE) {
             if let Ok(ranges_header) = ranges.to_str() {
-                if let Ok(ranges) = HttpRange::parse(ranges_header, length) {
-                    length = ranges[0].length;
-                    offset = ranges[0].start;
-
-                    resp.encoding(ContentEncoding::Identity);
-                    resp.insert_header((
-                        header::CONTENT_RANGE,
-                        format!("bytes {}-{}/{}", offset, offset + length - 1, self.md.len()),
-                    ));
-                } else {
-                    resp.insert_header((header::CONTENT_RANGE, format!("bytes */{}", length)));
-                    return resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish();
-                };
+                match HttpRange::parse(ranges_header, length).map(coalesce) {
+                    Ok(ref ranges) if ranges.len() > self.max_ranges => {
+                        resp.insert_header((
+                            header::CONTENT_RANGE,
+                            format!("bytes */{}", length),
+                        ));
+                        return resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish();
+                    }
+
+                    Ok(ref ranges) if ranges.len() == 1 => {
+                        length = ranges[0].length;
+                        offset = ranges[0].start;
+
+                        resp.encoding(ContentEncoding::Identity);
+                        resp.insert_header((
+                            header::CONTENT_RANGE,
+                            format!(
+                                "bytes {}-{}/{}",
+                                offset,
+                                offset + length - 1,
+                                self.md.len()
+                            ),
+                        ));
+                    }
+
+                    Ok(ranges) if !ranges.is_empty() => {
+                        resp.encoding(ContentEncoding::Identity);
+                        multi_ranges = Some(ranges);
+                    }
+
+                    _ => {
+                        resp.insert_header((
+                            header::CONTENT_RANGE,
+                            format!("bytes */{}", length),
+                        ));
+                        return resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish();
+                    }
+                }
             } else {
                 return resp.status(StatusCode::BAD_REQUEST).finish();
             };
@@ -411,6 +470,37 @@ impl NamedFile {
             return resp.status(StatusCode::NOT_MODIFIED).finish();
         }
 
+        if let Some(ranges) = multi_ranges {
+            let boundary = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(32)
+                .map(char::from)
+                .collect::<String>();
+
+            let part_content_type = if self.flags.contains(Flags::PREFER_UTF8) {
+                equiv_utf8_text(self.content_type.clone())
+            } else {
+                self.content_type.clone()
+            };
+
+            let reader = ChunkedMultipartFile::new(
+                self.file,
+                &ranges,
+                self.md.len(),
+                part_content_type.as_ref(),
+                &boundary,
+            );
+
+            resp.insert_header((
+                header::CONTENT_TYPE,
+                format!("multipart/byteranges; boundary={}", boundary),
+            ));
+
+            return resp
+                .status(StatusCode::PARTIAL_CONTENT)
+                .body(SizedStream::new(reader.size(), reader));
+        }
+
         let reader = ChunkedReadFile::new(length, offset, self.file);
 
         if offset != 0 || length != self.md.len() {
@@ -33,6 +33,25 @@ impl HttpRange {
     }
 }
 
+/// Sorts ranges by start offset and merges any that overlap or are directly adjacent.
+pub(crate) fn coalesce(mut ranges: Vec<HttpRange>) -> Vec<HttpRange> {
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.start + last.length => {
+                let end = (last.start + last.length).max(range.start + range.length);
+                last.length = end - last.start;
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -307,4 +326,21 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_coalesce() {
+        let range = |start, length| HttpRange { start, length };
+
+        let merged = coalesce(vec![range(500, 201), range(601, 399)]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].start, merged[0].length), (500, 500));
+
+        let merged = coalesce(vec![range(20, 5), range(0, 10), range(10, 2)]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!((merged[0].start, merged[0].length), (0, 12));
+        assert_eq!((merged[1].start, merged[1].length), (20, 5));
+
+        let merged = coalesce(vec![range(0, 1), range(9999, 1)]);
+        assert_eq!(merged.len(), 2);
+    }
 }
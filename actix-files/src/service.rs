@@ -1,16 +1,20 @@
-use std::{fmt, io, path::PathBuf, rc::Rc, task::Poll};
+use std::{fmt, fs::File, io, path::PathBuf, rc::Rc, task::Poll};
 
 use actix_service::Service;
 use actix_web::{
     dev::{ServiceRequest, ServiceResponse},
     error::Error,
     guard::Guard,
-    http::{header, Method},
+    http::{
+        header::{self, ContentEncoding, HeaderValue},
+        Method,
+    },
     HttpResponse,
 };
 use futures_util::future::{ok, Either, LocalBoxFuture, Ready};
 
 use crate::{
+    encoding::{negotiate_encodings, precompressed_ext},
     named, Directory, DirectoryRenderer, FilesError, HttpService, MimeOverride, NamedFile,
     PathBufWrap,
 };
@@ -27,6 +31,8 @@ pub struct FilesService {
     pub(crate) file_flags: named::Flags,
     pub(crate) guards: Option<Rc<dyn Guard>>,
     pub(crate) hidden_files: bool,
+    pub(crate) max_ranges: usize,
+    pub(crate) precompressed: Vec<ContentEncoding>,
 }
 
 type FilesServiceFuture = Either<
@@ -44,6 +50,66 @@ impl FilesService {
             Either::Left(ok(req.error_response(e)))
         }
     }
+
+    fn serve_named_file(&self, path: PathBuf, req: ServiceRequest) -> FilesServiceFuture {
+        let mut named_file = match self.open_named_file(path, &req) {
+            Ok(named_file) => named_file,
+            Err(e) => return self.handle_err(e, req),
+        };
+
+        if let Some(ref mime_override) = self.mime_override {
+            let new_disposition = mime_override(&named_file.content_type.type_());
+            named_file.content_disposition.disposition = new_disposition;
+        }
+        named_file.flags = self.file_flags;
+        named_file.max_ranges = self.max_ranges;
+
+        let (req, _) = req.into_parts();
+        let mut res = named_file.into_response(&req);
+
+        if !self.precompressed.is_empty() {
+            res.headers_mut()
+                .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+        }
+
+        Either::Left(ok(ServiceResponse::new(req, res)))
+    }
+
+    /// Opens the file at `path`, or the best pre-compressed variant of it accepted by the client.
+    fn open_named_file(&self, path: PathBuf, req: &ServiceRequest) -> io::Result<NamedFile> {
+        if self.precompressed.is_empty() {
+            return NamedFile::open(path);
+        }
+
+        let accept_encoding = req
+            .headers()
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|val| val.to_str().ok());
+
+        if let Some(accept_encoding) = accept_encoding {
+            for encoding in negotiate_encodings(accept_encoding, &self.precompressed) {
+                let ext = match precompressed_ext(encoding) {
+                    Some(ext) => ext,
+                    None => continue,
+                };
+
+                let mut variant = path.clone().into_os_string();
+                variant.push(".");
+                variant.push(ext);
+
+                if let Ok(file) = File::open(&variant) {
+                    if file.metadata()?.is_file() {
+                        // content type and disposition are derived from the original path
+                        let mut named_file = NamedFile::from_file(file, &path)?;
+                        named_file.precompressed = Some(encoding);
+                        return Ok(named_file);
+                    }
+                }
+            }
+        }
+
+        NamedFile::open(path)
+    }
 }
 
 impl fmt::Debug for FilesService {
@@ -102,22 +168,7 @@ impl Service<ServiceRequest> for FilesService {
                 }
 
                 let path = path.join(redir_index);
-
-                match NamedFile::open(path) {
-                    Ok(mut named_file) => {
-                        if let Some(ref mime_override) = self.mime_override {
-                            let new_disposition =
-                                mime_override(&named_file.content_type.type_());
-                            named_file.content_disposition.disposition = new_disposition;
-                        }
-                        named_file.flags = self.file_flags;
-
-                        let (req, _) = req.into_parts();
-                        let res = named_file.into_response(&req);
-                        Either::Left(ok(ServiceResponse::new(req, res)))
-                    }
-                    Err(e) => self.handle_err(e, req),
-                }
+                self.serve_named_file(path, req)
             } else if self.show_index {
                 let dir = Directory::new(self.directory.clone(), path);
 
@@ -135,20 +186,7 @@ impl Service<ServiceRequest> for FilesService {
                 )))
             }
         } else {
-            match NamedFile::open(path) {
-                Ok(mut named_file) => {
-                    if let Some(ref mime_override) = self.mime_override {
-                        let new_disposition = mime_override(&named_file.content_type.type_());
-                        named_file.content_disposition.disposition = new_disposition;
-                    }
-                    named_file.flags = self.file_flags;
-
-                    let (req, _) = req.into_parts();
-                    let res = named_file.into_response(&req);
-                    Either::Left(ok(ServiceResponse::new(req, res)))
-                }
-                Err(e) => self.handle_err(e, req),
-            }
+            self.serve_named_file(path, req)
         }
     }
 }
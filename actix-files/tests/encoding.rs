@@ -1,7 +1,9 @@
+use std::fs;
+
 use actix_files::Files;
 use actix_web::{
     http::{
-        header::{self, HeaderValue},
+        header::{self, ContentEncoding, HeaderValue},
         StatusCode,
     },
     test::{self, TestRequest},
@@ -36,3 +38,70 @@ async fn test_utf8_file_contents() {
         Some(&HeaderValue::from_static("text/plain; charset=utf-8")),
     );
 }
+
+#[actix_rt::test]
+async fn test_precompressed_file_variants() {
+    let srv = test::init_service(
+        App::new().service(
+            Files::new("/", "./tests")
+                .use_precompressed(vec![ContentEncoding::Br, ContentEncoding::Gzip]),
+        ),
+    )
+    .await;
+
+    // server preference decides between equally accepted encodings
+    let req = TestRequest::with_uri("/utf8.txt")
+        .insert_header((header::ACCEPT_ENCODING, "gzip, br"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::CONTENT_ENCODING),
+        Some(&HeaderValue::from_static("br")),
+    );
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("text/plain")),
+    );
+    assert_eq!(
+        res.headers().get(header::VARY),
+        Some(&HeaderValue::from_static("accept-encoding")),
+    );
+    let body = test::read_body(res).await;
+    assert_eq!(body, fs::read("./tests/utf8.txt.br").unwrap());
+
+    // client q-values take precedence over server preference
+    let req = TestRequest::with_uri("/utf8.txt")
+        .insert_header((header::ACCEPT_ENCODING, "gzip, br;q=0.5"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+
+    assert_eq!(
+        res.headers().get(header::CONTENT_ENCODING),
+        Some(&HeaderValue::from_static("gzip")),
+    );
+    let gzip_etag = res.headers().get(header::ETAG).cloned();
+    let body = test::read_body(res).await;
+    assert_eq!(body, fs::read("./tests/utf8.txt.gz").unwrap());
+
+    // original file is served when no variant is acceptable
+    let req = TestRequest::with_uri("/utf8.txt")
+        .insert_header((header::ACCEPT_ENCODING, "deflate"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+
+    assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_ne!(res.headers().get(header::ETAG).cloned(), gzip_etag);
+    let body = test::read_body(res).await;
+    assert_eq!(body, fs::read("./tests/utf8.txt").unwrap());
+
+    // files without variants are served as-is
+    let req = TestRequest::with_uri("/test.binary")
+        .insert_header((header::ACCEPT_ENCODING, "br, gzip"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+}
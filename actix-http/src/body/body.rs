@@ -8,6 +8,7 @@ use bytes::{Bytes, BytesMut};
 use futures_core::Stream;
 
 use crate::error::Error;
+use crate::header::HeaderMap;
 
 use super::{BodySize, BodyStream, MessageBody, SizedStream};
 
@@ -63,6 +64,13 @@ impl MessageBody for Body {
             Body::Message(body) => Pin::new(&mut **body).poll_next(cx),
         }
     }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        match self.get_mut() {
+            Body::Message(body) => Pin::new(&mut **body).take_trailers(),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for Body {
@@ -9,6 +9,7 @@ use std::{
 use bytes::{Bytes, BytesMut};
 
 use crate::error::Error;
+use crate::header::HeaderMap;
 
 use super::BodySize;
 
@@ -21,6 +22,14 @@ pub trait MessageBody {
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Error>>>;
 
+    /// Take trailer fields to send after the body.
+    ///
+    /// Called once [`poll_next`](Self::poll_next) has returned `None`. Trailers are sent with
+    /// chunked HTTP/1.1 and with HTTP/2 responses, and discarded otherwise.
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        None
+    }
+
     downcast_get_type_id!();
 }
 
@@ -50,6 +59,10 @@ impl<T: MessageBody + Unpin> MessageBody for Box<T> {
     ) -> Poll<Option<Result<Bytes, Error>>> {
         Pin::new(self.get_mut().as_mut()).poll_next(cx)
     }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        Pin::new(self.get_mut().as_mut()).take_trailers()
+    }
 }
 
 impl MessageBody for Bytes {
@@ -9,6 +9,7 @@ use futures_core::Stream;
 use pin_project::pin_project;
 
 use crate::error::Error;
+use crate::header::HeaderMap;
 
 use super::{Body, BodySize, MessageBody};
 
@@ -60,6 +61,13 @@ impl<B: MessageBody> MessageBody for ResponseBody<B> {
             ResponseBodyProj::Other(body) => Pin::new(body).poll_next(cx),
         }
     }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        match self.project() {
+            ResponseBodyProj::Body(body) => body.take_trailers(),
+            ResponseBodyProj::Other(body) => Pin::new(body).take_trailers(),
+        }
+    }
 }
 
 impl<B: MessageBody> Stream for ResponseBody<B> {
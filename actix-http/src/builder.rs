@@ -6,7 +6,7 @@ use actix_codec::Framed;
 use actix_service::{IntoServiceFactory, Service, ServiceFactory};
 
 use crate::body::MessageBody;
-use crate::config::{KeepAlive, ServiceConfig};
+use crate::config::{H1Limits, H2Settings, KeepAlive, ServiceConfig, ShutdownSignal};
 use crate::error::Error;
 use crate::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::h2::H2Service;
@@ -15,6 +15,9 @@ use crate::response::Response;
 use crate::service::HttpService;
 use crate::{ConnectCallback, Extensions};
 
+/// Largest flow control window allowed by HTTP/2.
+const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
 /// A HTTP service builder
 ///
 /// This type can be used to construct an instance of [`HttpService`] through a
@@ -25,6 +28,11 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler> {
     client_disconnect: u64,
     secure: bool,
     local_addr: Option<net::SocketAddr>,
+    h2_ping_interval: u64,
+    h2_ping_timeout: u64,
+    h1_limits: H1Limits,
+    h2_settings: H2Settings,
+    shutdown_signal: Option<ShutdownSignal>,
     expect: X,
     upgrade: Option<U>,
     on_connect_ext: Option<Rc<ConnectCallback<T>>>,
@@ -46,6 +54,11 @@ where
             client_disconnect: 0,
             secure: false,
             local_addr: None,
+            h2_ping_interval: 0,
+            h2_ping_timeout: 20_000,
+            h1_limits: H1Limits::default(),
+            h2_settings: H2Settings::default(),
+            shutdown_signal: None,
             expect: ExpectHandler,
             upgrade: None,
             on_connect_ext: None,
@@ -116,6 +129,132 @@ where
         self
     }
 
+    /// Set interval in milliseconds between HTTP/2 keep-alive PING frames.
+    ///
+    /// Connections whose peer does not acknowledge a PING within the
+    /// [PING timeout](Self::h2_ping_timeout) are closed. This detects peers that disappeared
+    /// without closing the connection, e.g. behind a NAT or load balancer.
+    ///
+    /// To disable keep-alive PINGs set value to 0.
+    ///
+    /// By default keep-alive PINGs are disabled.
+    pub fn h2_ping_interval(mut self, val: u64) -> Self {
+        self.h2_ping_interval = val;
+        self
+    }
+
+    /// Set timeout in milliseconds for acknowledgement of HTTP/2 keep-alive PING frames.
+    ///
+    /// By default PING timeout is set to 20000 milliseconds.
+    pub fn h2_ping_timeout(mut self, val: u64) -> Self {
+        self.h2_ping_timeout = val;
+        self
+    }
+
+    /// Set the maximum number of headers allowed in an HTTP/1 request.
+    ///
+    /// Requests with more headers are responded to with `431 Request Header Fields Too Large`.
+    ///
+    /// By default 96 headers are allowed.
+    pub fn h1_max_headers(mut self, num: usize) -> Self {
+        self.h1_limits.max_headers = num;
+        self
+    }
+
+    /// Set the maximum size (in bytes) of an HTTP/1 request head, including request line and
+    /// headers.
+    ///
+    /// Requests with a larger head are responded to with `431 Request Header Fields Too Large`.
+    ///
+    /// By default the request head is limited to 128KiB.
+    pub fn h1_max_head_size(mut self, size: usize) -> Self {
+        self.h1_limits.max_head_size = size;
+        self
+    }
+
+    /// Set the maximum length (in bytes) of an HTTP/1 request URI.
+    ///
+    /// Requests with a longer URI are responded to with `414 URI Too Long`.
+    ///
+    /// By default the URI length is only limited by the maximum request head size.
+    pub fn h1_max_uri_length(mut self, len: usize) -> Self {
+        self.h1_limits.max_uri_length = len;
+        self
+    }
+
+    /// Set the maximum number of concurrent HTTP/2 streams the client is allowed to open.
+    ///
+    /// By default the number of concurrent streams is not limited.
+    pub fn h2_max_concurrent_streams(mut self, max: u32) -> Self {
+        self.h2_settings.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Indicates the initial window size (in octets) for
+    /// HTTP/2 stream-level flow control for received data.
+    ///
+    /// The default value is 65,535 and is good for APIs, but not for big objects.
+    ///
+    /// # Panics
+    /// Panics if `size` is larger than 2^31 - 1.
+    pub fn h2_initial_window_size(mut self, size: u32) -> Self {
+        assert!(
+            size <= MAX_WINDOW_SIZE,
+            "window size must not exceed 2^31 - 1"
+        );
+        self.h2_settings.initial_window_size = Some(size);
+        self
+    }
+
+    /// Indicates the initial window size (in octets) for
+    /// HTTP/2 connection-level flow control for received data.
+    ///
+    /// The default value is 65,535 and is good for APIs, but not for big objects.
+    ///
+    /// # Panics
+    /// Panics if `size` is larger than 2^31 - 1.
+    pub fn h2_initial_connection_window_size(mut self, size: u32) -> Self {
+        assert!(
+            size <= MAX_WINDOW_SIZE,
+            "window size must not exceed 2^31 - 1"
+        );
+        self.h2_settings.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Set the maximum HTTP/2 frame payload size (in octets) the server is willing to receive.
+    ///
+    /// The default value is 16,384.
+    ///
+    /// # Panics
+    /// Panics if `size` is not between 16,384 and 16,777,215.
+    pub fn h2_max_frame_size(mut self, size: u32) -> Self {
+        assert!(
+            (16_384..=16_777_215).contains(&size),
+            "max frame size must be between 16,384 and 16,777,215"
+        );
+        self.h2_settings.max_frame_size = Some(size);
+        self
+    }
+
+    /// Set the maximum size (in octets) of the HTTP/2 header list the server is willing to
+    /// accept, measured as the uncompressed size of all header fields.
+    ///
+    /// By default the header list size is limited to 16MiB.
+    pub fn h2_max_header_list_size(mut self, size: u32) -> Self {
+        self.h2_settings.max_header_list_size = Some(size);
+        self
+    }
+
+    /// Set signal for graceful shutdown of HTTP/2 connections.
+    ///
+    /// When the signal is triggered, HTTP/2 connections send a GOAWAY frame with the last
+    /// processed stream id, finish in-flight streams and then close.
+    pub fn shutdown_signal(mut self, signal: ShutdownSignal) -> Self {
+        self.shutdown_signal = Some(signal);
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -135,6 +274,11 @@ where
             client_disconnect: self.client_disconnect,
             secure: self.secure,
             local_addr: self.local_addr,
+            h2_ping_interval: self.h2_ping_interval,
+            h2_ping_timeout: self.h2_ping_timeout,
+            h1_limits: self.h1_limits,
+            h2_settings: self.h2_settings,
+            shutdown_signal: self.shutdown_signal,
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_connect_ext: self.on_connect_ext,
@@ -160,6 +304,11 @@ where
             client_disconnect: self.client_disconnect,
             secure: self.secure,
             local_addr: self.local_addr,
+            h2_ping_interval: self.h2_ping_interval,
+            h2_ping_timeout: self.h2_ping_timeout,
+            h1_limits: self.h1_limits,
+            h2_settings: self.h2_settings,
+            shutdown_signal: self.shutdown_signal,
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_connect_ext: self.on_connect_ext,
@@ -195,7 +344,11 @@ where
             self.client_disconnect,
             self.secure,
             self.local_addr,
-        );
+        )
+        .with_h2_ping(self.h2_ping_interval, self.h2_ping_timeout)
+        .with_h1_limits(self.h1_limits)
+        .with_h2_settings(self.h2_settings)
+        .with_shutdown_signal(self.shutdown_signal);
 
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
@@ -219,7 +372,11 @@ where
             self.client_disconnect,
             self.secure,
             self.local_addr,
-        );
+        )
+        .with_h2_ping(self.h2_ping_interval, self.h2_ping_timeout)
+        .with_h1_limits(self.h1_limits)
+        .with_h2_settings(self.h2_settings)
+        .with_shutdown_signal(self.shutdown_signal);
 
         H2Service::with_config(cfg, service.into_factory())
             .on_connect_ext(self.on_connect_ext)
@@ -241,7 +398,11 @@ where
             self.client_disconnect,
             self.secure,
             self.local_addr,
-        );
+        )
+        .with_h2_ping(self.h2_ping_interval, self.h2_ping_timeout)
+        .with_h1_limits(self.h1_limits)
+        .with_h2_settings(self.h2_settings)
+        .with_shutdown_signal(self.shutdown_signal);
 
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
@@ -1,6 +1,9 @@
 use std::net::IpAddr;
 use std::time::Duration;
 
+use super::pool::PoolStats;
+use super::proxy::Proxy;
+
 const DEFAULT_H2_CONN_WINDOW: u32 = 1024 * 1024 * 2; // 2MB
 const DEFAULT_H2_STREAM_WINDOW: u32 = 1024 * 1024; // 1MB
 
@@ -12,9 +15,14 @@ pub(crate) struct ConnectorConfig {
     pub(crate) conn_keep_alive: Duration,
     pub(crate) disconnect_timeout: Option<Duration>,
     pub(crate) limit: usize,
+    pub(crate) limit_per_host: usize,
+    pub(crate) max_idle_per_host: usize,
+    pub(crate) wait_timeout: Option<Duration>,
     pub(crate) conn_window_size: u32,
     pub(crate) stream_window_size: u32,
     pub(crate) local_address: Option<IpAddr>,
+    pub(crate) proxies: Vec<Proxy>,
+    pub(crate) stats: PoolStats,
 }
 
 impl Default for ConnectorConfig {
@@ -25,9 +33,14 @@ impl Default for ConnectorConfig {
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Some(Duration::from_millis(3000)),
             limit: 100,
+            limit_per_host: 0,
+            max_idle_per_host: usize::MAX,
+            wait_timeout: None,
             conn_window_size: DEFAULT_H2_CONN_WINDOW,
             stream_window_size: DEFAULT_H2_STREAM_WINDOW,
             local_address: None,
+            proxies: Vec::new(),
+            stats: PoolStats::default(),
         }
     }
 }
@@ -17,6 +17,7 @@ use crate::payload::Payload;
 
 use super::error::SendRequestError;
 use super::pool::Acquired;
+use super::proxy::ForwardProxy;
 use super::{h1proto, h2proto};
 
 pub(crate) enum ConnectionType<Io> {
@@ -111,6 +112,7 @@ where
     io: Option<ConnectionType<T>>,
     created: time::Instant,
     pool: Option<Acquired<T>>,
+    forward_proxy: Option<ForwardProxy>,
 }
 
 impl<T> fmt::Debug for IoConnection<T>
@@ -136,9 +138,16 @@ impl<T: AsyncRead + AsyncWrite + Unpin> IoConnection<T> {
             pool,
             created,
             io: Some(io),
+            forward_proxy: None,
         }
     }
 
+    /// Marks the connection as being to a forward proxy.
+    pub(crate) fn forward_proxy(mut self, proxy: Option<ForwardProxy>) -> Self {
+        self.forward_proxy = proxy;
+        self
+    }
+
     pub(crate) fn into_inner(self) -> (ConnectionType<T>, time::Instant) {
         (self.io.unwrap(), self.created)
     }
@@ -155,8 +164,15 @@ impl<T: AsyncRead + AsyncWrite + Unpin> IoConnection<T> {
     ) -> Result<(ResponseHead, Payload), SendRequestError> {
         match self.io.take().unwrap() {
             ConnectionType::H1(io) => {
-                h1proto::send_request(io, head.into(), body, self.created, self.pool)
-                    .await
+                h1proto::send_request(
+                    io,
+                    head.into(),
+                    body,
+                    self.created,
+                    self.pool,
+                    self.forward_proxy,
+                )
+                .await
             }
             ConnectionType::H2(io) => {
                 h2proto::send_request(io, head.into(), body, self.created, self.pool)
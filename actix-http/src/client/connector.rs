@@ -10,7 +10,7 @@ use std::{
 
 use actix_codec::{AsyncRead, AsyncWrite};
 use actix_rt::net::TcpStream;
-use actix_service::{apply_fn, Service, ServiceExt};
+use actix_service::{fn_service, Service, ServiceExt};
 use actix_tls::connect::{
     new_connector, Connect as TcpConnect, Connection as TcpConnection, Resolver,
 };
@@ -20,7 +20,8 @@ use http::Uri;
 use super::config::ConnectorConfig;
 use super::connection::{Connection, EitherIoConnection};
 use super::error::ConnectError;
-use super::pool::{ConnectionPool, Protocol};
+use super::pool::{ConnectionPool, PoolStats, Protocol};
+use super::proxy::{self, Proxy};
 use super::Connect;
 
 #[cfg(feature = "openssl")]
@@ -209,6 +210,42 @@ where
         self
     }
 
+    /// Set total number of simultaneous connections to a single host per type of scheme.
+    ///
+    /// Requests to a host at its limit wait without holding any of the slots of
+    /// [`limit`](Self::limit), so a slow host can not use up the connections of other hosts.
+    /// Connections through a forward proxy count as connections to the proxy.
+    ///
+    /// If limit is 0, the connector has no per-host limit. This is the default.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.config.limit_per_host = limit;
+        self
+    }
+
+    /// Set max number of idle connections kept for reuse per host.
+    ///
+    /// Connections released while a host has this many idle connections are closed. By default
+    /// the number of idle connections is not limited.
+    pub fn max_idle_per_host(mut self, max: usize) -> Self {
+        self.config.max_idle_per_host = max;
+        self
+    }
+
+    /// Set max time a request waits for a connection when a connection limit is reached.
+    ///
+    /// Requests that wait longer fail with [`ConnectError::PoolTimeout`]. This is separate from
+    /// the [connection timeout](Self::timeout), which starts once a connection is being
+    /// established. By default requests wait without a limit.
+    pub fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.config.wait_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns a handle for reading the statistics of the connection pools of this connector.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.config.stats.clone()
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -249,6 +286,15 @@ where
         self
     }
 
+    /// Route connections through a proxy.
+    ///
+    /// Can be called multiple times; each connection uses the first proxy that applies to its
+    /// host. Connections through a proxy are pooled separately from direct ones.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.config.proxies.push(proxy);
+        self
+    }
+
     /// Finish configuration process and create connector service.
     /// The Connector builder always concludes by calling `finish()` last in
     /// its combinator chain.
@@ -259,18 +305,14 @@ where
         let local_address = self.config.local_address;
         let timeout = self.config.timeout;
 
+        let connector = self.connector.clone();
+        let proxies = self.config.proxies.clone();
+
         let tcp_service = TimeoutService::new(
             timeout,
-            apply_fn(self.connector.clone(), move |msg: Connect, srv| {
-                let mut req = TcpConnect::new(msg.uri).set_addr(msg.addr);
-
-                if let Some(local_addr) = local_address {
-                    req = req.set_local_addr(local_addr);
-                }
-
-                srv.call(req)
+            fn_service(move |msg: Connect| {
+                connect(&connector, msg, &proxies, local_address)
             })
-            .map_err(ConnectError::from)
             .map(|stream| (stream.into_parts().0, Protocol::Http1)),
         )
         .map_err(|e| match e {
@@ -311,20 +353,14 @@ where
             #[cfg(feature = "rustls")]
             use actix_tls::connect::ssl::rustls::{RustlsConnector, Session};
 
+            let connector = self.connector.clone();
+            let proxies = self.config.proxies.clone();
+
             let ssl_service = TimeoutService::new(
                 timeout,
-                pipeline(
-                    apply_fn(self.connector.clone(), move |msg: Connect, srv| {
-                        let mut req = TcpConnect::new(msg.uri).set_addr(msg.addr);
-
-                        if let Some(local_addr) = local_address {
-                            req = req.set_local_addr(local_addr);
-                        }
-
-                        srv.call(req)
-                    })
-                    .map_err(ConnectError::from),
-                )
+                pipeline(fn_service(move |msg: Connect| {
+                    connect(&connector, msg, &proxies, local_address)
+                }))
                 .and_then(match self.ssl {
                     #[cfg(feature = "openssl")]
                     SslConnector::Openssl(ssl) => service(
@@ -381,6 +417,48 @@ where
     }
 }
 
+/// Connect to the host of `msg`, or to the proxy its connections are routed through.
+fn connect<S, Io>(
+    srv: &S,
+    msg: Connect,
+    proxies: &[Proxy],
+    local_address: Option<IpAddr>,
+) -> impl Future<Output = Result<TcpConnection<Uri, Io>, ConnectError>>
+where
+    S: Service<
+        TcpConnect<Uri>,
+        Response = TcpConnection<Uri, Io>,
+        Error = actix_tls::connect::ConnectError,
+    >,
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    let proxy = proxy::find(proxies, &msg.uri).cloned();
+
+    let mut req = match proxy {
+        Some(ref proxy) => TcpConnect::new(proxy.uri().clone()),
+        None => TcpConnect::new(msg.uri.clone()).set_addr(msg.addr),
+    };
+
+    if let Some(local_addr) = local_address {
+        req = req.set_local_addr(local_addr);
+    }
+
+    let fut = srv.call(req);
+
+    async move {
+        let conn = fut.await?;
+
+        match proxy {
+            Some(proxy) if !proxy.is_forward(&msg.uri) => {
+                let (mut io, _) = conn.into_parts();
+                proxy.tunnel(&mut io, &msg.uri).await?;
+                Ok(TcpConnection::from_parts(io, msg.uri))
+            }
+            _ => Ok(conn),
+        }
+    }
+}
+
 struct InnerConnector<S1, S2, Io1, Io2>
 where
     S1: Service<Connect, Response = (Io1, Protocol), Error = ConnectError> + 'static,
@@ -36,6 +36,10 @@ pub enum ConnectError {
     #[display(fmt = "Timeout while establishing connection")]
     Timeout,
 
+    /// Waiting for a free connection of the pool took too long
+    #[display(fmt = "Timeout while waiting for a free connection")]
+    PoolTimeout,
+
     /// Connector has been disconnected
     #[display(fmt = "Internal error: connector has been disconnected")]
     Disconnected,
@@ -47,6 +51,11 @@ pub enum ConnectError {
     /// Connection io error
     #[display(fmt = "{}", _0)]
     Io(io::Error),
+
+    /// Proxy refused or failed to open a tunnel
+    #[display(fmt = "Proxy error: {}", _0)]
+    #[from(ignore)]
+    Proxy(String),
 }
 
 impl std::error::Error for ConnectError {}
@@ -13,13 +13,16 @@ use futures_util::{SinkExt, StreamExt};
 use crate::error::PayloadError;
 use crate::h1;
 use crate::header::HeaderMap;
-use crate::http::header::{IntoHeaderValue, HOST};
+use crate::http::header::{
+    HeaderName, HeaderValue, IntoHeaderValue, HOST, PROXY_AUTHORIZATION,
+};
 use crate::message::{RequestHeadType, ResponseHead};
 use crate::payload::{Payload, PayloadStream};
 
 use super::connection::{ConnectionLifetime, ConnectionType, IoConnection};
 use super::error::{ConnectError, SendRequestError};
 use super::pool::Acquired;
+use super::proxy::ForwardProxy;
 use crate::body::{BodySize, MessageBody};
 
 pub(crate) async fn send_request<T, B>(
@@ -28,15 +31,14 @@ pub(crate) async fn send_request<T, B>(
     body: B,
     created: time::Instant,
     pool: Option<Acquired<T>>,
+    forward_proxy: Option<ForwardProxy>,
 ) -> Result<(ResponseHead, Payload), SendRequestError>
 where
     T: AsyncRead + AsyncWrite + Unpin + 'static,
     B: MessageBody,
 {
     // set request host header
-    if !head.as_ref().headers.contains_key(HOST)
-        && !head.extra_headers().iter().any(|h| h.contains_key(HOST))
-    {
+    if !contains_header(&head, &HOST) {
         if let Some(host) = head.as_ref().uri.host() {
             let mut wrt = BytesMut::with_capacity(host.len() + 5).writer();
 
@@ -46,20 +48,24 @@ where
             };
 
             match wrt.get_mut().split().freeze().try_into_value() {
-                Ok(value) => match head {
-                    RequestHeadType::Owned(ref mut head) => {
-                        head.headers.insert(HOST, value);
-                    }
-                    RequestHeadType::Rc(_, ref mut extra_headers) => {
-                        let headers = extra_headers.get_or_insert(HeaderMap::new());
-                        headers.insert(HOST, value);
-                    }
-                },
+                Ok(value) => insert_header(&mut head, HOST, value),
                 Err(e) => log::error!("Can not set HOST header {}", e),
             }
         }
     }
 
+    let mut codec = h1::ClientCodec::default();
+
+    if let Some(proxy) = forward_proxy {
+        codec = codec.absolute_form();
+
+        if let Some(value) = proxy.authorization {
+            if !contains_header(&head, &PROXY_AUTHORIZATION) {
+                insert_header(&mut head, PROXY_AUTHORIZATION, value);
+            }
+        }
+    }
+
     let io = H1Connection {
         created,
         pool,
@@ -67,7 +73,7 @@ where
     };
 
     // create Framed and send request
-    let mut framed_inner = Framed::new(io, h1::ClientCodec::default());
+    let mut framed_inner = Framed::new(io, codec);
     framed_inner.send((head, body.size()).into()).await?;
 
     // send request body
@@ -98,6 +104,23 @@ where
     }
 }
 
+fn contains_header(head: &RequestHeadType, name: &HeaderName) -> bool {
+    head.as_ref().headers.contains_key(name)
+        || head.extra_headers().iter().any(|h| h.contains_key(name))
+}
+
+fn insert_header(head: &mut RequestHeadType, name: HeaderName, value: HeaderValue) {
+    match head {
+        RequestHeadType::Owned(ref mut head) => {
+            head.headers.insert(name, value);
+        }
+        RequestHeadType::Rc(_, ref mut extra_headers) => {
+            let headers = extra_headers.get_or_insert(HeaderMap::new());
+            headers.insert(name, value);
+        }
+    }
+}
+
 pub(crate) async fn open_tunnel<T>(
     io: T,
     head: RequestHeadType,
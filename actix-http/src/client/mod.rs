@@ -9,6 +9,7 @@ mod error;
 mod h1proto;
 mod h2proto;
 mod pool;
+mod proxy;
 
 pub use actix_tls::connect::{
     Connect as TcpConnect, ConnectError as TcpConnectError, Connection as TcpConnection,
@@ -17,7 +18,8 @@ pub use actix_tls::connect::{
 pub use self::connection::Connection;
 pub use self::connector::Connector;
 pub use self::error::{ConnectError, FreezeRequestError, InvalidUrl, SendRequestError};
-pub use self::pool::Protocol;
+pub use self::pool::{HostStats, PoolKey, PoolStats, Protocol};
+pub use self::proxy::Proxy;
 
 #[derive(Clone)]
 pub struct Connect {
@@ -1,17 +1,18 @@
-//! Client connection pooling keyed on the authority part of the connection URI.
+//! Client connection pooling keyed on the authority part of the connection URI and the proxy
+//! used for the connection.
 
 use std::collections::VecDeque;
 use std::future::Future;
 use std::ops::Deref;
 use std::pin::Pin;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
-use std::{cell::RefCell, io};
+use std::{cell::RefCell, fmt, io};
 
 use actix_codec::{AsyncRead, AsyncWrite};
-use actix_rt::time::{sleep, Sleep};
+use actix_rt::time::{sleep, timeout, Sleep};
 use actix_service::Service;
 use ahash::AHashMap;
 use futures_core::future::LocalBoxFuture;
@@ -24,6 +25,7 @@ use super::config::ConnectorConfig;
 use super::connection::{ConnectionType, H2Connection, IoConnection};
 use super::error::ConnectError;
 use super::h2proto::handshake;
+use super::proxy::{self, ForwardProxy};
 use super::Connect;
 
 #[derive(Clone, Copy, PartialEq)]
@@ -33,17 +35,109 @@ pub enum Protocol {
     Http2,
 }
 
+/// Key of the connections of a pool.
+///
+/// Connections are pooled by the authority of the connection URI and the proxy they are routed
+/// through. Connections to a forward proxy are shared by all target hosts and are keyed by the
+/// authority of the proxy.
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
-pub(crate) struct Key {
+pub struct PoolKey {
     authority: Authority,
+    proxy: Option<Authority>,
 }
 
-impl From<Authority> for Key {
-    fn from(authority: Authority) -> Key {
-        Key { authority }
+impl PoolKey {
+    /// Returns the authority the connections are made to.
+    pub fn authority(&self) -> &Authority {
+        &self.authority
+    }
+
+    /// Returns the authority of the proxy the connections are routed through.
+    pub fn proxy(&self) -> Option<&Authority> {
+        self.proxy.as_ref()
+    }
+}
+
+impl From<Authority> for PoolKey {
+    fn from(authority: Authority) -> PoolKey {
+        PoolKey {
+            authority,
+            proxy: None,
+        }
+    }
+}
+
+/// Connection statistics of a single [`PoolKey`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostStats {
+    key: PoolKey,
+    active: usize,
+    idle: usize,
+    waiting: usize,
+}
+
+impl HostStats {
+    /// Returns the key of the connections.
+    pub fn key(&self) -> &PoolKey {
+        &self.key
+    }
+
+    /// Returns the number of connections that are in use or being established.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Returns the number of idle connections kept for reuse.
+    pub fn idle(&self) -> usize {
+        self.idle
+    }
+
+    /// Returns the number of requests waiting for a connection because a limit is reached.
+    pub fn waiting(&self) -> usize {
+        self.waiting
     }
 }
 
+/// Handle for reading the statistics of the connection pools of a
+/// [`Connector`](super::Connector).
+///
+/// Obtained with [`Connector::pool_stats`](super::Connector::pool_stats) before the connector is
+/// finished; the handle stays valid for the lifetime of the connector service.
+#[derive(Clone, Default)]
+pub struct PoolStats(Rc<RefCell<Vec<Weak<dyn StatsSource>>>>);
+
+impl PoolStats {
+    /// Returns the statistics of every key with active, idle or waiting connections.
+    pub fn hosts(&self) -> Vec<HostStats> {
+        let mut stats = Vec::new();
+
+        self.0.borrow_mut().retain(|pool| match pool.upgrade() {
+            Some(pool) => {
+                pool.host_stats(&mut stats);
+                true
+            }
+            None => false,
+        });
+
+        stats
+    }
+
+    fn register(&self, pool: Weak<dyn StatsSource>) {
+        self.0.borrow_mut().push(pool);
+    }
+}
+
+impl fmt::Debug for PoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.hosts()).finish()
+    }
+}
+
+/// Type erased access to the statistics of a pool.
+trait StatsSource {
+    fn host_stats(&self, stats: &mut Vec<HostStats>);
+}
+
 /// Connections pool for reuse Io type for certain [`http::uri::Authority`] as key.
 pub(crate) struct ConnectionPool<S, Io>
 where
@@ -115,10 +209,144 @@ where
     Io: AsyncWrite + Unpin + 'static,
 {
     config: ConnectorConfig,
-    available: RefCell<AHashMap<Key, VecDeque<PooledConnection<Io>>>>,
+    available: RefCell<AHashMap<PoolKey, VecDeque<PooledConnection<Io>>>>,
+    hosts: RefCell<AHashMap<PoolKey, HostState>>,
     permits: Arc<Semaphore>,
 }
 
+/// Limit and counters of the connections of a single key.
+struct HostState {
+    permits: Option<Arc<Semaphore>>,
+    active: usize,
+    waiting: usize,
+}
+
+impl<Io> ConnectionPoolInnerPriv<Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    /// Wait for the permits of the per-key and global limits and count the key as active.
+    ///
+    /// The per-key permit is acquired first, so that requests to a host that is at its limit
+    /// do not hold any of the global permits.
+    async fn acquire_permits(
+        &self,
+        key: &PoolKey,
+    ) -> Result<(Option<OwnedSemaphorePermit>, OwnedSemaphorePermit), ConnectError> {
+        let host_permits = {
+            let limit = self.config.limit_per_host;
+            let mut hosts = self.hosts.borrow_mut();
+            let host = hosts.entry(key.clone()).or_insert_with(|| HostState {
+                permits: if limit > 0 {
+                    Some(Arc::new(Semaphore::new(limit)))
+                } else {
+                    None
+                },
+                active: 0,
+                waiting: 0,
+            });
+            host.waiting += 1;
+            host.permits.clone()
+        };
+
+        // decrements the waiting count when done or cancelled
+        let _waiting = Waiting { inner: self, key };
+
+        let acquire = async {
+            let host_permit = match host_permits {
+                Some(permits) => Some(permits.acquire_owned().await.map_err(closed)?),
+                None => None,
+            };
+            let permit = self.permits.clone().acquire_owned().await.map_err(closed)?;
+
+            Ok::<_, ConnectError>((host_permit, permit))
+        };
+
+        let permits = match self.config.wait_timeout {
+            Some(dur) => timeout(dur, acquire)
+                .await
+                .map_err(|_| ConnectError::PoolTimeout)??,
+            None => acquire.await?,
+        };
+
+        // count as active before the state of the key can be removed by the waiting guard
+        self.update_host(key, |host| host.active += 1);
+
+        Ok(permits)
+    }
+
+    /// Update the counters of a key and remove its state when it is unused.
+    fn update_host(&self, key: &PoolKey, f: impl FnOnce(&mut HostState)) {
+        let mut hosts = self.hosts.borrow_mut();
+
+        if let Some(host) = hosts.get_mut(key) {
+            f(host);
+
+            if host.active == 0 && host.waiting == 0 {
+                hosts.remove(key);
+            }
+        }
+    }
+}
+
+impl<Io> StatsSource for ConnectionPoolInnerPriv<Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    fn host_stats(&self, stats: &mut Vec<HostStats>) {
+        let start = stats.len();
+
+        for (key, host) in self.hosts.borrow().iter() {
+            stats.push(HostStats {
+                key: key.clone(),
+                active: host.active,
+                idle: 0,
+                waiting: host.waiting,
+            });
+        }
+
+        for (key, conns) in self.available.borrow().iter() {
+            if conns.is_empty() {
+                continue;
+            }
+
+            match stats[start..].iter_mut().find(|stats| stats.key == *key) {
+                Some(stats) => stats.idle = conns.len(),
+                None => stats.push(HostStats {
+                    key: key.clone(),
+                    active: 0,
+                    idle: conns.len(),
+                    waiting: 0,
+                }),
+            }
+        }
+    }
+}
+
+struct Waiting<'a, Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    inner: &'a ConnectionPoolInnerPriv<Io>,
+    key: &'a PoolKey,
+}
+
+impl<Io> Drop for Waiting<'_, Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    fn drop(&mut self) {
+        self.inner.update_host(self.key, |host| host.waiting -= 1);
+    }
+}
+
+fn closed<E>(_: E) -> ConnectError {
+    ConnectError::Io(io::Error::new(
+        io::ErrorKind::Other,
+        "failed to acquire semaphore on client connection pool",
+    ))
+}
+
 impl<S, Io> ConnectionPool<S, Io>
 where
     Io: AsyncWrite + Unpin + 'static,
@@ -126,23 +354,32 @@ where
     /// Construct a new connection pool.
     ///
     /// [`super::config::ConnectorConfig`]'s `limit` is used as the max permits allowed for
-    /// in-flight connections.
+    /// in-flight connections, and `limit_per_host` as the max permits for each [`PoolKey`].
     ///
     /// The pool can only have equal to `limit` amount of requests spawning/using Io type
     /// concurrently.
     ///
     /// Any requests beyond limit would be wait in fifo order and get notified in async manner
     /// by [`tokio::sync::Semaphore`]
+    ///
+    /// The pool registers itself with the config's [`PoolStats`] handle.
     pub(crate) fn new(connector: S, config: ConnectorConfig) -> Self {
         let permits = Arc::new(Semaphore::new(config.limit));
         let available = RefCell::new(AHashMap::default());
+        let hosts = RefCell::new(AHashMap::default());
         let connector = Rc::new(connector);
 
-        let inner = ConnectionPoolInner(Rc::new(ConnectionPoolInnerPriv {
+        let inner = Rc::new(ConnectionPoolInnerPriv {
             config,
             available,
+            hosts,
             permits,
-        }));
+        });
+
+        let stats: Rc<dyn StatsSource> = inner.clone();
+        inner.config.stats.register(Rc::downgrade(&stats));
+
+        let inner = ConnectionPoolInner(inner);
 
         Self { connector, inner }
     }
@@ -176,27 +413,54 @@ where
         let inner = self.inner.clone();
 
         Box::pin(async move {
-            let key = if let Some(authority) = req.uri.authority() {
-                authority.clone().into()
-            } else {
-                return Err(ConnectError::Unresolved);
+            let authority = match req.uri.authority() {
+                Some(authority) => authority.clone(),
+                None => return Err(ConnectError::Unresolved),
             };
 
-            // acquire an owned permit and carry it with connection
-            let permit = inner.permits.clone().acquire_owned().await.map_err(|_| {
-                ConnectError::Io(io::Error::new(
-                    io::ErrorKind::Other,
-                    "failed to acquire semaphore on client connection pool",
-                ))
-            })?;
+            let (key, forward_proxy) = match proxy::find(&inner.config.proxies, &req.uri)
+            {
+                // connections to forward proxies are shared by all target hosts
+                Some(proxy) if proxy.is_forward(&req.uri) => {
+                    let key = PoolKey {
+                        authority: proxy.authority().clone(),
+                        proxy: Some(proxy.authority().clone()),
+                    };
+                    let forward_proxy = ForwardProxy {
+                        authorization: proxy.authorization(),
+                    };
+                    (key, Some(forward_proxy))
+                }
+                Some(proxy) => {
+                    let key = PoolKey {
+                        authority,
+                        proxy: Some(proxy.authority().clone()),
+                    };
+                    (key, None)
+                }
+                None => (authority.into(), None),
+            };
+
+            // acquire owned permits and carry them with connection
+            let (host_permit, permit) = inner.acquire_permits(&key).await?;
+
+            // construct acquired. It's used to put Io type back to pool/ close the Io type.
+            // permits are carried with the whole lifecycle of Acquired.
+            let acquired = Acquired {
+                key,
+                inner,
+                permit,
+                host_permit,
+            };
 
             let conn = {
                 let mut conn = None;
+                let inner = &acquired.inner;
 
                 // check if there is idle connection for given key.
                 let mut map = inner.available.borrow_mut();
 
-                if let Some(conns) = map.get_mut(&key) {
+                if let Some(conns) = map.get_mut(&acquired.key) {
                     let now = Instant::now();
 
                     while let Some(mut c) = conns.pop_front() {
@@ -233,33 +497,33 @@ where
                 conn
             };
 
-            // construct acquired. It's used to put Io type back to pool/ close the Io type.
-            // permit is carried with the whole lifecycle of Acquired.
-            let acquired = Some(Acquired { key, inner, permit });
+            let acquired = Some(acquired);
 
             // match the connection and spawn new one if did not get anything.
-            match conn {
-                Some(conn) => Ok(IoConnection::new(conn.conn, conn.created, acquired)),
+            let conn = match conn {
+                Some(conn) => IoConnection::new(conn.conn, conn.created, acquired),
                 None => {
                     let (io, proto) = connector.call(req).await?;
 
                     if proto == Protocol::Http1 {
-                        Ok(IoConnection::new(
+                        IoConnection::new(
                             ConnectionType::H1(io),
                             Instant::now(),
                             acquired,
-                        ))
+                        )
                     } else {
                         let config = &acquired.as_ref().unwrap().inner.config;
                         let (sender, connection) = handshake(io, config).await?;
-                        Ok(IoConnection::new(
+                        IoConnection::new(
                             ConnectionType::H2(H2Connection::new(sender, connection)),
                             Instant::now(),
                             acquired,
-                        ))
+                        )
                     }
                 }
-            }
+            };
+
+            Ok(conn.forward_proxy(forward_proxy))
         })
     }
 }
@@ -351,9 +615,10 @@ pub(crate) struct Acquired<Io>
 where
     Io: AsyncWrite + Unpin + 'static,
 {
-    key: Key,
+    key: PoolKey,
     inner: ConnectionPoolInner<Io>,
     permit: OwnedSemaphorePermit,
+    host_permit: Option<OwnedSemaphorePermit>,
 }
 
 impl<Io> Acquired<Io>
@@ -371,18 +636,29 @@ where
         let (io, created) = conn.into_inner();
         let Acquired { key, inner, .. } = self;
 
-        inner
-            .available
-            .borrow_mut()
-            .entry(key.clone())
-            .or_insert_with(VecDeque::new)
-            .push_back(PooledConnection {
+        let mut available = inner.available.borrow_mut();
+        let conns = available.entry(key.clone()).or_insert_with(VecDeque::new);
+
+        if conns.len() >= inner.config.max_idle_per_host {
+            inner.close(io);
+        } else {
+            conns.push_back(PooledConnection {
                 conn: io,
                 created,
                 used: Instant::now(),
             });
+        }
 
-        let _ = &mut self.permit;
+        let _ = (&mut self.permit, &mut self.host_permit);
+    }
+}
+
+impl<Io> Drop for Acquired<Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    fn drop(&mut self) {
+        self.inner.update_host(&self.key, |host| host.active -= 1);
     }
 }
 
@@ -505,6 +781,130 @@ mod test {
         assert!(now.elapsed() >= Duration::from_millis(100));
     }
 
+    #[actix_rt::test]
+    async fn test_pool_limit_per_host() {
+        let connector = TestPoolConnector {
+            generated: Rc::new(Cell::new(0)),
+        };
+
+        let config = ConnectorConfig {
+            limit_per_host: 1,
+            ..Default::default()
+        };
+        let stats = config.stats.clone();
+
+        let pool = super::ConnectionPool::new(connector, config);
+
+        let req = |uri| Connect {
+            uri: Uri::from_static(uri),
+            addr: None,
+        };
+
+        let conn = pool.call(req("http://localhost")).await.unwrap();
+
+        // other hosts are not limited by the connections to localhost
+        let other = pool.call(req("http://127.0.0.1")).await.unwrap();
+        release(other);
+
+        let waiting = pool.call(req("http://localhost"));
+        let check = async {
+            actix_rt::task::yield_now().await;
+
+            let mut hosts = stats.hosts();
+            hosts.sort_by_key(|host| host.key().authority().to_string());
+
+            assert_eq!(hosts.len(), 2);
+            assert_eq!(hosts[0].key().authority(), "127.0.0.1");
+            assert_eq!((hosts[0].active(), hosts[0].idle()), (0, 1));
+            assert_eq!(hosts[1].key().authority(), "localhost");
+            assert_eq!((hosts[1].active(), hosts[1].waiting()), (1, 1));
+
+            release(conn);
+        };
+
+        let (conn, _) = futures_util::future::join(waiting, check).await;
+        release(conn.unwrap());
+
+        let hosts = stats.hosts();
+        assert!(hosts
+            .iter()
+            .all(|host| host.active() == 0 && host.waiting() == 0));
+        assert!(hosts.iter().all(|host| host.idle() == 1));
+    }
+
+    #[actix_rt::test]
+    async fn test_pool_wait_timeout() {
+        let connector = TestPoolConnector {
+            generated: Rc::new(Cell::new(0)),
+        };
+
+        let config = ConnectorConfig {
+            limit: 1,
+            wait_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let stats = config.stats.clone();
+
+        let pool = super::ConnectionPool::new(connector, config);
+
+        let req = Connect {
+            uri: Uri::from_static("http://localhost"),
+            addr: None,
+        };
+
+        let conn = pool.call(req.clone()).await.unwrap();
+
+        let now = Instant::now();
+        let err = pool.call(req.clone()).await.err().unwrap();
+        assert!(matches!(err, ConnectError::PoolTimeout));
+        assert!(now.elapsed() >= Duration::from_millis(50));
+
+        let hosts = stats.hosts();
+        assert_eq!((hosts[0].active(), hosts[0].waiting()), (1, 0));
+
+        release(conn);
+        let conn = pool.call(req).await.unwrap();
+        release(conn);
+    }
+
+    #[actix_rt::test]
+    async fn test_pool_max_idle_per_host() {
+        let generated = Rc::new(Cell::new(0));
+        let generated_clone = generated.clone();
+
+        let connector = TestPoolConnector { generated };
+
+        let config = ConnectorConfig {
+            max_idle_per_host: 1,
+            ..Default::default()
+        };
+        let stats = config.stats.clone();
+
+        let pool = super::ConnectionPool::new(connector, config);
+
+        let req = Connect {
+            uri: Uri::from_static("http://localhost"),
+            addr: None,
+        };
+
+        let conn1 = pool.call(req.clone()).await.unwrap();
+        let conn2 = pool.call(req).await.unwrap();
+        assert_eq!(2, generated_clone.get());
+        assert_eq!(stats.hosts()[0].active(), 2);
+
+        release(conn1);
+        release(conn2);
+
+        // yield task so the closed connection is properly dropped.
+        actix_rt::task::yield_now().await;
+        assert_eq!(1, generated_clone.get());
+        assert_eq!(stats.hosts()[0].idle(), 1);
+        assert_eq!(stats.hosts()[0].active(), 0);
+
+        drop(pool);
+        assert!(stats.hosts().is_empty());
+    }
+
     #[actix_rt::test]
     async fn test_pool_keep_alive() {
         let generated = Rc::new(Cell::new(0));
@@ -1,6 +1,8 @@
 use std::cell::Cell;
 use std::fmt::Write;
+use std::future::Future;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, net};
 
@@ -10,6 +12,9 @@ use actix_rt::{
 };
 use bytes::BytesMut;
 use time::OffsetDateTime;
+use tokio::sync::watch;
+
+use crate::h1;
 
 /// "Sun, 06 Nov 1994 08:49:37 GMT".len()
 const DATE_VALUE_LENGTH: usize = 29;
@@ -41,6 +46,89 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+/// Signal for graceful shutdown of HTTP/2 connections.
+///
+/// Once [triggered](Self::shutdown), connections of services configured with this signal send a
+/// GOAWAY frame, let in-flight streams finish and then close. Clones share the same state and
+/// can be sent to other threads.
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal {
+    tx: Arc<watch::Sender<bool>>,
+    rx: watch::Receiver<bool>,
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownSignal {
+    /// Create new shutdown signal.
+    pub fn new() -> Self {
+        let (tx, rx) = watch::channel(false);
+        ShutdownSignal {
+            tx: Arc::new(tx),
+            rx,
+        }
+    }
+
+    /// Start graceful shutdown of all connections using this signal.
+    pub fn shutdown(&self) {
+        // a receiver is always held by the signal itself
+        let _ = self.tx.send(true);
+    }
+
+    /// Returns true if shutdown was triggered.
+    pub fn is_shutdown(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Future that resolves once shutdown is triggered.
+    pub(crate) fn wait(&self) -> impl Future<Output = ()> + 'static {
+        // holding the sender means that waiting for a change can not fail
+        let tx = self.tx.clone();
+        let mut rx = self.rx.clone();
+
+        async move {
+            while !*rx.borrow() {
+                let _ = rx.changed().await;
+            }
+            drop(tx);
+        }
+    }
+}
+
+/// Limits applied when decoding HTTP/1 request heads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct H1Limits {
+    pub(crate) max_headers: usize,
+    pub(crate) max_head_size: usize,
+    pub(crate) max_uri_length: usize,
+}
+
+impl Default for H1Limits {
+    fn default() -> Self {
+        H1Limits {
+            max_headers: h1::MAX_HEADERS,
+            max_head_size: h1::MAX_BUFFER_SIZE,
+            max_uri_length: usize::MAX,
+        }
+    }
+}
+
+/// HTTP/2 settings sent by the server during the handshake.
+///
+/// Unset values use the defaults of the protocol implementation.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct H2Settings {
+    pub(crate) max_concurrent_streams: Option<u32>,
+    pub(crate) initial_window_size: Option<u32>,
+    pub(crate) initial_connection_window_size: Option<u32>,
+    pub(crate) max_frame_size: Option<u32>,
+    pub(crate) max_header_list_size: Option<u32>,
+}
+
 /// Http service configuration
 pub struct ServiceConfig(Rc<Inner>);
 
@@ -51,6 +139,11 @@ struct Inner {
     ka_enabled: bool,
     secure: bool,
     local_addr: Option<std::net::SocketAddr>,
+    h2_ping_interval: u64,
+    h2_ping_timeout: u64,
+    h1_limits: H1Limits,
+    h2_settings: H2Settings,
+    shutdown_signal: Option<ShutdownSignal>,
     date_service: DateService,
 }
 
@@ -93,10 +186,49 @@ impl ServiceConfig {
             client_disconnect,
             secure,
             local_addr,
+            h2_ping_interval: 0,
+            h2_ping_timeout: 20_000,
+            h1_limits: H1Limits::default(),
+            h2_settings: H2Settings::default(),
+            shutdown_signal: None,
             date_service: DateService::new(),
         }))
     }
 
+    /// Set HTTP/2 keep-alive PING interval and timeout in milliseconds.
+    pub(crate) fn with_h2_ping(mut self, interval: u64, timeout: u64) -> Self {
+        let inner = self.inner_mut();
+        inner.h2_ping_interval = interval;
+        inner.h2_ping_timeout = timeout;
+        self
+    }
+
+    /// Set limits for decoding HTTP/1 request heads.
+    pub(crate) fn with_h1_limits(mut self, limits: H1Limits) -> Self {
+        self.inner_mut().h1_limits = limits;
+        self
+    }
+
+    /// Set HTTP/2 settings sent during the handshake.
+    pub(crate) fn with_h2_settings(mut self, settings: H2Settings) -> Self {
+        self.inner_mut().h2_settings = settings;
+        self
+    }
+
+    /// Set signal for graceful connection shutdown.
+    pub(crate) fn with_shutdown_signal(
+        mut self,
+        signal: Option<ShutdownSignal>,
+    ) -> Self {
+        self.inner_mut().shutdown_signal = signal;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.0)
+            .expect("ServiceConfig is not shared during construction")
+    }
+
     /// Returns true if connection is secure (HTTPS)
     #[inline]
     pub fn secure(&self) -> bool {
@@ -173,6 +305,34 @@ impl ServiceConfig {
         }
     }
 
+    /// HTTP/2 keep-alive PING interval, if enabled.
+    pub(crate) fn h2_ping_interval(&self) -> Option<Duration> {
+        match self.0.h2_ping_interval {
+            0 => None,
+            interval => Some(Duration::from_millis(interval)),
+        }
+    }
+
+    /// Time to wait for acknowledgement of a HTTP/2 keep-alive PING.
+    pub(crate) fn h2_ping_timeout(&self) -> Duration {
+        Duration::from_millis(self.0.h2_ping_timeout)
+    }
+
+    /// Limits for decoding HTTP/1 request heads.
+    pub(crate) fn h1_limits(&self) -> H1Limits {
+        self.0.h1_limits
+    }
+
+    /// HTTP/2 settings sent during the handshake.
+    pub(crate) fn h2_settings(&self) -> &H2Settings {
+        &self.0.h2_settings
+    }
+
+    /// Future that resolves once graceful shutdown of connections is requested.
+    pub(crate) fn shutdown_signal(&self) -> Option<impl Future<Output = ()> + 'static> {
+        self.0.shutdown_signal.as_ref().map(ShutdownSignal::wait)
+    }
+
     #[inline]
     pub(crate) fn now(&self) -> Instant {
         self.0.date_service.now()
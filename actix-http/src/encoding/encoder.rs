@@ -13,12 +13,14 @@ use bytes::Bytes;
 use flate2::write::{GzEncoder, ZlibEncoder};
 use futures_core::ready;
 use pin_project::pin_project;
+#[cfg(feature = "compress-zstd")]
+use zstd::stream::write::Encoder as ZstdEncoder;
 
 use crate::{
     body::{Body, BodySize, MessageBody, ResponseBody},
     http::{
         header::{ContentEncoding, CONTENT_ENCODING},
-        HeaderValue, StatusCode,
+        HeaderMap, HeaderValue, StatusCode,
     },
     Error, ResponseHead,
 };
@@ -42,6 +44,19 @@ impl<B: MessageBody> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<Encoder<B>> {
+        Self::response_with_level(encoding, None, head, body)
+    }
+
+    /// Same as [`response`](Self::response) but with an explicit compression level.
+    ///
+    /// The level is interpreted by the selected algorithm and clamped to its valid range
+    /// (0-9 for gzip and deflate, 0-11 for brotli, 1-22 for zstd). `None` selects a fast default.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<Encoder<B>> {
         let can_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
             || head.status == StatusCode::SWITCHING_PROTOCOLS
@@ -67,7 +82,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if can_encode {
             // Modify response body only if encoder is not None
-            if let Some(enc) = ContentEncoder::encoder(encoding) {
+            if let Some(enc) = ContentEncoder::encoder(encoding, level) {
                 update_head(encoding, head);
                 head.no_chunking(false);
                 return ResponseBody::Body(Encoder {
@@ -122,6 +137,16 @@ impl<B: MessageBody> MessageBody for EncoderBody<B> {
             }
         }
     }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        match self.project() {
+            EncoderBodyProj::Bytes(_) => None,
+            EncoderBodyProj::Stream(b) => b.take_trailers(),
+            EncoderBodyProj::BoxedStream(ref mut b) => {
+                Pin::new(b.as_mut()).take_trailers()
+            }
+        }
+    }
 }
 
 impl<B: MessageBody> MessageBody for Encoder<B> {
@@ -198,6 +223,10 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
             }
         }
     }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
 }
 
 fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
@@ -211,21 +240,35 @@ enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
     Br(BrotliEncoder<Writer>),
+    #[cfg(feature = "compress-zstd")]
+    Zstd(ZstdEncoder<'static, Writer>),
 }
 
 impl ContentEncoder {
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: Option<u32>) -> Option<Self> {
+        let flate_level = || match level {
+            Some(level) => flate2::Compression::new(level.min(9)),
+            None => flate2::Compression::fast(),
+        };
+
         match encoding {
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
+            ContentEncoding::Br => Some(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.map_or(3, |level| level.min(11)),
+            ))),
+            #[cfg(feature = "compress-zstd")]
+            ContentEncoding::Zstd => {
+                let level = level.map_or(3, |level| level.max(1).min(22) as i32);
+                let encoder = ZstdEncoder::new(Writer::new(), level).ok()?;
+                Some(ContentEncoder::Zstd(encoder))
             }
             _ => None,
         }
@@ -237,6 +280,8 @@ impl ContentEncoder {
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "compress-zstd")]
+            ContentEncoder::Zstd(ref mut encoder) => encoder.get_mut().take(),
         }
     }
 
@@ -254,6 +299,11 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            #[cfg(feature = "compress-zstd")]
+            ContentEncoder::Zstd(encoder) => match encoder.finish() {
+                Ok(writer) => Ok(writer.buf.freeze()),
+                Err(err) => Err(err),
+            },
         }
     }
 
@@ -280,6 +330,14 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            #[cfg(feature = "compress-zstd")]
+            ContentEncoder::Zstd(ref mut encoder) => match encoder.write_all(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    trace!("Error decoding zstd encoding: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
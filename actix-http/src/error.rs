@@ -235,6 +235,9 @@ pub enum ParseError {
     /// A message head is too large to be reasonable.
     #[display(fmt = "Message head is too large")]
     TooLarge,
+    /// A request URI is longer than allowed.
+    #[display(fmt = "Uri is too long")]
+    UriTooLong,
     /// A message reached EOF, but is not complete.
     #[display(fmt = "Message is incomplete")]
     Incomplete,
@@ -16,6 +16,7 @@ use crate::message::{ConnectionType, RequestHeadType, ResponseHead};
 bitflags! {
     struct Flags: u8 {
         const HEAD              = 0b0000_0001;
+        const ABSOLUTE_FORM     = 0b0000_0010;
         const KEEPALIVE_ENABLED = 0b0000_1000;
         const STREAM            = 0b0001_0000;
     }
@@ -73,6 +74,12 @@ impl ClientCodec {
         }
     }
 
+    /// Encode request targets in absolute form, as required by forward proxies.
+    pub(crate) fn absolute_form(mut self) -> Self {
+        self.inner.flags.insert(Flags::ABSOLUTE_FORM);
+        self
+    }
+
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
         self.inner.ctype == ConnectionType::Upgrade
@@ -159,17 +166,21 @@ impl Decoder for ClientPayloadCodec {
             "Payload decoder is not specified"
         );
 
-        Ok(match self.inner.payload.as_mut().unwrap().decode(src)? {
-            Some(PayloadItem::Chunk(chunk)) => {
-                reserve_readbuf(src);
-                Some(Some(chunk))
-            }
-            Some(PayloadItem::Eof) => {
-                self.inner.payload.take();
-                Some(None)
+        loop {
+            match self.inner.payload.as_mut().unwrap().decode(src)? {
+                Some(PayloadItem::Chunk(chunk)) => {
+                    reserve_readbuf(src);
+                    return Ok(Some(Some(chunk)));
+                }
+                // response trailers are not exposed, continue to payload eof
+                Some(PayloadItem::Trailers(_)) => {}
+                Some(PayloadItem::Eof) => {
+                    self.inner.payload.take();
+                    return Ok(Some(None));
+                }
+                None => return Ok(None),
             }
-            None => None,
-        })
+        }
     }
 }
 
@@ -202,16 +213,29 @@ impl Encoder<Message<(RequestHeadType, BodySize)>> for ClientCodec {
                     ConnectionType::Close => ConnectionType::Close,
                 };
 
-                inner.encoder.encode(
-                    dst,
-                    &mut head,
-                    false,
-                    false,
-                    inner.version,
-                    length,
-                    inner.ctype,
-                    &inner.config,
-                )?;
+                if inner.flags.contains(Flags::ABSOLUTE_FORM) {
+                    inner.encoder.encode(
+                        dst,
+                        &mut encoder::AbsoluteForm(&mut head),
+                        false,
+                        false,
+                        inner.version,
+                        length,
+                        inner.ctype,
+                        &inner.config,
+                    )?;
+                } else {
+                    inner.encoder.encode(
+                        dst,
+                        &mut head,
+                        false,
+                        false,
+                        inner.version,
+                        length,
+                        inner.ctype,
+                        &inner.config,
+                    )?;
+                }
             }
             Message::Chunk(Some(bytes)) => {
                 self.inner.encoder.encode_chunk(bytes.as_ref(), dst)?;
@@ -219,6 +243,9 @@ impl Encoder<Message<(RequestHeadType, BodySize)>> for ClientCodec {
             Message::Chunk(None) => {
                 self.inner.encoder.encode_eof(dst)?;
             }
+            Message::Trailers(trailers) => {
+                self.inner.encoder.encode_trailers(&trailers, dst)?;
+            }
         }
         Ok(())
     }
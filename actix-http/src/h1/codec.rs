@@ -58,11 +58,12 @@ impl Codec {
         } else {
             Flags::empty()
         };
+        let decoder = decoder::MessageDecoder::new(config.h1_limits());
 
         Codec {
             config,
             flags,
-            decoder: decoder::MessageDecoder::default(),
+            decoder,
             payload: None,
             version: Version::HTTP_11,
             ctype: ConnectionType::Close,
@@ -114,6 +115,9 @@ impl Decoder for Codec {
         if let Some(ref mut payload) = self.payload {
             Ok(match payload.decode(src)? {
                 Some(PayloadItem::Chunk(chunk)) => Some(Message::Chunk(Some(chunk))),
+                Some(PayloadItem::Trailers(trailers)) => {
+                    Some(Message::Trailers(trailers))
+                }
                 Some(PayloadItem::Eof) => {
                     self.payload.take();
                     Some(Message::Chunk(None))
@@ -188,6 +192,9 @@ impl Encoder<Message<(Response<()>, BodySize)>> for Codec {
             Message::Chunk(None) => {
                 self.encoder.encode_eof(dst)?;
             }
+            Message::Trailers(trailers) => {
+                self.encoder.encode_trailers(&trailers, dst)?;
+            }
         }
         Ok(())
     }
@@ -9,16 +9,20 @@ use http::header::{HeaderName, HeaderValue};
 use http::{header, Method, StatusCode, Uri, Version};
 use log::{debug, error, trace};
 
+use crate::config::H1Limits;
 use crate::error::ParseError;
 use crate::header::HeaderMap;
 use crate::message::{ConnectionType, ResponseHead};
 use crate::request::Request;
 
 pub(crate) const MAX_BUFFER_SIZE: usize = 131_072;
-const MAX_HEADERS: usize = 96;
+pub(crate) const MAX_HEADERS: usize = 96;
 
 /// Incoming message decoder
-pub(crate) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(crate) struct MessageDecoder<T: MessageType> {
+    limits: H1Limits,
+    _phantom: PhantomData<T>,
+}
 
 #[derive(Debug)]
 /// Incoming request type
@@ -30,7 +34,17 @@ pub(crate) enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(H1Limits::default())
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    /// Create decoder enforcing the given head limits.
+    pub(crate) fn new(limits: H1Limits) -> Self {
+        MessageDecoder {
+            limits,
+            _phantom: PhantomData,
+        }
     }
 }
 
@@ -39,7 +53,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, &self.limits)
     }
 }
 
@@ -56,12 +70,16 @@ pub(crate) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &H1Limits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
         slice: &Bytes,
         raw_headers: &[HeaderIndex],
+        limits: &H1Limits,
     ) -> Result<PayloadLength, ParseError> {
         let mut ka = None;
         let mut has_upgrade_websocket = false;
@@ -152,7 +170,7 @@ pub(crate) trait MessageType: Sized {
         if chunked {
             // Chunked encoding
             Ok(PayloadLength::Payload(PayloadType::Payload(
-                PayloadDecoder::chunked(),
+                PayloadDecoder::chunked(*limits),
             )))
         } else if has_upgrade_websocket {
             Ok(PayloadLength::UpgradeWebSocket)
@@ -182,30 +200,64 @@ impl MessageType for Request {
         &mut self.head_mut().headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
-        let mut headers: [HeaderIndex; MAX_HEADERS] = EMPTY_HEADER_INDEX_ARRAY;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &H1Limits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        let mut headers_stack = EMPTY_HEADER_INDEX_ARRAY;
+        let mut headers_heap = Vec::new();
+        let headers = header_slots(
+            &mut headers_stack,
+            &mut headers_heap,
+            limits.max_headers,
+            EMPTY_HEADER_INDEX,
+        );
 
         let (len, method, uri, ver, h_len) = {
-            let mut parsed: [httparse::Header<'_>; MAX_HEADERS] = EMPTY_HEADER_ARRAY;
-
-            let mut req = httparse::Request::new(&mut parsed);
+            let mut parsed_stack = EMPTY_HEADER_ARRAY;
+            let mut parsed_heap = Vec::new();
+            let parsed = header_slots(
+                &mut parsed_stack,
+                &mut parsed_heap,
+                limits.max_headers,
+                httparse::EMPTY_HEADER,
+            );
+
+            let mut req = httparse::Request::new(parsed);
             match req.parse(src)? {
                 httparse::Status::Complete(len) => {
+                    let path = req.path.unwrap();
+                    if path.len() > limits.max_uri_length {
+                        trace!("Request URI exceeds configured length limit");
+                        return Err(ParseError::UriTooLong);
+                    }
+
+                    if len > limits.max_head_size {
+                        trace!("Request head exceeds configured size limit");
+                        return Err(ParseError::TooLarge);
+                    }
+
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
-                    let uri = Uri::try_from(req.path.unwrap())?;
+                    let uri = Uri::try_from(path)?;
                     let version = if req.version.unwrap() == 1 {
                         Version::HTTP_11
                     } else {
                         Version::HTTP_10
                     };
-                    HeaderIndex::record(src, req.headers, &mut headers);
+                    HeaderIndex::record(src, req.headers, headers);
 
                     (len, method, uri, version, req.headers.len())
                 }
                 httparse::Status::Partial => {
-                    return if src.len() >= MAX_BUFFER_SIZE {
-                        trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    // the URI may be too long before the request line is even complete
+                    if request_target_len(src) > limits.max_uri_length {
+                        trace!("Request URI exceeds configured length limit");
+                        return Err(ParseError::UriTooLong);
+                    }
+
+                    return if src.len() >= limits.max_head_size {
+                        trace!("Request head size limit reached, closing");
                         Err(ParseError::TooLarge)
                     } else {
                         // Return None to notify more read are needed for parsing request
@@ -218,7 +270,8 @@ impl MessageType for Request {
         let mut msg = Request::new();
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length =
+            msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len], limits)?;
 
         // payload decoder
         let decoder = match length {
@@ -258,13 +311,30 @@ impl MessageType for ResponseHead {
         &mut self.headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
-        let mut headers: [HeaderIndex; MAX_HEADERS] = EMPTY_HEADER_INDEX_ARRAY;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &H1Limits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        let mut headers_stack = EMPTY_HEADER_INDEX_ARRAY;
+        let mut headers_heap = Vec::new();
+        let headers = header_slots(
+            &mut headers_stack,
+            &mut headers_heap,
+            limits.max_headers,
+            EMPTY_HEADER_INDEX,
+        );
 
         let (len, ver, status, h_len) = {
-            let mut parsed: [httparse::Header<'_>; MAX_HEADERS] = EMPTY_HEADER_ARRAY;
-
-            let mut res = httparse::Response::new(&mut parsed);
+            let mut parsed_stack = EMPTY_HEADER_ARRAY;
+            let mut parsed_heap = Vec::new();
+            let parsed = header_slots(
+                &mut parsed_stack,
+                &mut parsed_heap,
+                limits.max_headers,
+                httparse::EMPTY_HEADER,
+            );
+
+            let mut res = httparse::Response::new(parsed);
             match res.parse(src)? {
                 httparse::Status::Complete(len) => {
                     let version = if res.version.unwrap() == 1 {
@@ -274,13 +344,13 @@ impl MessageType for ResponseHead {
                     };
                     let status = StatusCode::from_u16(res.code.unwrap())
                         .map_err(|_| ParseError::Status)?;
-                    HeaderIndex::record(src, res.headers, &mut headers);
+                    HeaderIndex::record(src, res.headers, headers);
 
                     (len, version, status, res.headers.len())
                 }
                 httparse::Status::Partial => {
-                    return if src.len() >= MAX_BUFFER_SIZE {
-                        error!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    return if src.len() >= limits.max_head_size {
+                        error!("Response head size limit reached, closing");
                         Err(ParseError::TooLarge)
                     } else {
                         Ok(None)
@@ -293,7 +363,8 @@ impl MessageType for ResponseHead {
         msg.version = ver;
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length =
+            msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len], limits)?;
 
         // message payload
         let decoder = if let PayloadLength::Payload(pl) = length {
@@ -332,6 +403,27 @@ pub(crate) const EMPTY_HEADER_INDEX_ARRAY: [HeaderIndex; MAX_HEADERS] =
 pub(crate) const EMPTY_HEADER_ARRAY: [httparse::Header<'static>; MAX_HEADERS] =
     [httparse::EMPTY_HEADER; MAX_HEADERS];
 
+/// Length of the request target in the (possibly incomplete) request line at the start of `src`.
+fn request_target_len(src: &[u8]) -> usize {
+    let line = src.split(|b| *b == b'\r' || *b == b'\n').next().unwrap();
+    line.splitn(3, |b| *b == b' ').nth(1).map_or(0, <[u8]>::len)
+}
+
+/// Use stack storage for header slots unless more than `MAX_HEADERS` are allowed.
+fn header_slots<'a, T: Copy>(
+    stack: &'a mut [T; MAX_HEADERS],
+    heap: &'a mut Vec<T>,
+    len: usize,
+    empty: T,
+) -> &'a mut [T] {
+    if len <= MAX_HEADERS {
+        &mut stack[..len]
+    } else {
+        heap.resize(len, empty);
+        heap
+    }
+}
+
 impl HeaderIndex {
     pub(crate) fn record(
         bytes: &[u8],
@@ -350,10 +442,11 @@ impl HeaderIndex {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 /// Http payload item
 pub enum PayloadItem {
     Chunk(Bytes),
+    Trailers(HeaderMap),
     Eof,
 }
 
@@ -373,9 +466,10 @@ impl PayloadDecoder {
         }
     }
 
-    pub fn chunked() -> PayloadDecoder {
+    /// Create decoder for a chunked payload whose trailers are bound by `limits`.
+    pub(crate) fn chunked(limits: H1Limits) -> PayloadDecoder {
         PayloadDecoder {
-            kind: Kind::Chunked(ChunkedState::Size, 0),
+            kind: Kind::Chunked(ChunkedState::Size, 0, limits),
         }
     }
 
@@ -390,7 +484,7 @@ enum Kind {
     /// integer.
     Length(u64),
     /// A Reader used when Transfer-Encoding is `chunked`.
-    Chunked(ChunkedState, u64),
+    Chunked(ChunkedState, u64, H1Limits),
     /// A Reader used for responses that don't indicate a length or chunked.
     ///
     /// Note: This should only used for `Response`s. It is illegal for a
@@ -448,8 +542,21 @@ impl Decoder for PayloadDecoder {
                     Ok(Some(PayloadItem::Chunk(buf)))
                 }
             }
-            Kind::Chunked(ref mut state, ref mut size) => {
+            Kind::Chunked(ref mut state, ref mut size, ref limits) => {
                 loop {
+                    // trailer section follows the last chunk
+                    if *state == ChunkedState::EndCr
+                        && src.first().map_or(false, |b| *b != b'\r')
+                    {
+                        return match parse_trailers(src, limits)? {
+                            Some(trailers) => {
+                                *state = ChunkedState::End;
+                                Ok(Some(PayloadItem::Trailers(trailers)))
+                            }
+                            None => Ok(None),
+                        };
+                    }
+
                     let mut buf = None;
                     // advances the chunked state
                     *state = match state.step(src, size, &mut buf) {
@@ -480,6 +587,70 @@ impl Decoder for PayloadDecoder {
     }
 }
 
+/// Parse trailer fields of a chunked payload, including the terminating empty line.
+///
+/// Trailers are subject to the same field count and size limits as the message head.
+fn parse_trailers(
+    src: &mut BytesMut,
+    limits: &H1Limits,
+) -> io::Result<Option<HeaderMap>> {
+    let mut parsed_stack = EMPTY_HEADER_ARRAY;
+    let mut parsed_heap = Vec::new();
+    let parsed = header_slots(
+        &mut parsed_stack,
+        &mut parsed_heap,
+        limits.max_headers,
+        httparse::EMPTY_HEADER,
+    );
+
+    let (len, trailers) = match httparse::parse_headers(src, parsed) {
+        Ok(httparse::Status::Complete((len, _))) if len > limits.max_head_size => {
+            return Err(trailers_too_large());
+        }
+        Ok(httparse::Status::Complete((len, fields))) => {
+            let mut trailers = HeaderMap::with_capacity(fields.len());
+            for field in fields {
+                let name = HeaderName::from_bytes(field.name.as_bytes());
+                let value = HeaderValue::from_bytes(field.value);
+                match (name, value) {
+                    (Ok(name), Ok(value)) => trailers.append(name, value),
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "Invalid chunked trailer field",
+                        ))
+                    }
+                }
+            }
+            (len, trailers)
+        }
+        Ok(httparse::Status::Partial) => {
+            return if src.len() >= limits.max_head_size {
+                Err(trailers_too_large())
+            } else {
+                Ok(None)
+            };
+        }
+        Err(httparse::Error::TooManyHeaders) => return Err(trailers_too_large()),
+        Err(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid chunked trailers",
+            ))
+        }
+    };
+
+    src.advance(len);
+    Ok(Some(trailers))
+}
+
+fn trailers_too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Chunked trailers are too large",
+    )
+}
+
 macro_rules! byte (
     ($rdr:ident) => ({
         if $rdr.len() > 0 {
@@ -1210,6 +1381,88 @@ mod tests {
         assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_trailers() {
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.1\r\n\
+             transfer-encoding: chunked\r\n\r\n",
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"4\r\ndata\r\n0\r\ngrpc-status: 0\r\n");
+        let msg = pl.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.chunk().as_ref(), b"data");
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+
+        buf.extend(b"grpc-message: ok\r\n\r\nPOST /test2 HTTP/1.1\r\n\r\n");
+        match pl.decode(&mut buf).unwrap().unwrap() {
+            PayloadItem::Trailers(trailers) => {
+                assert_eq!(trailers.len(), 2);
+                assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+                assert_eq!(trailers.get("grpc-message").unwrap(), "ok");
+            }
+            _ => panic!("expected trailers"),
+        }
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.path(), "/test2");
+    }
+
+    #[test]
+    fn test_parse_chunked_payload_invalid_trailers() {
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.1\r\n\
+             transfer-encoding: chunked\r\n\r\n",
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"0\r\ninvalid trailer\r\n\r\n");
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_parse_chunked_payload_trailer_limits() {
+        let limits = H1Limits {
+            max_headers: 2,
+            max_head_size: 64,
+            ..H1Limits::default()
+        };
+        let head = "POST /test HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n";
+
+        let mut buf = BytesMut::from(head);
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+        buf.extend(b"0\r\na: 1\r\nb: 2\r\n\r\n");
+        match pl.decode(&mut buf).unwrap().unwrap() {
+            PayloadItem::Trailers(trailers) => assert_eq!(trailers.len(), 2),
+            _ => panic!("expected trailers"),
+        }
+
+        let mut buf = BytesMut::from(head);
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+        buf.extend(b"0\r\na: 1\r\nb: 2\r\nc: 3\r\n\r\n");
+        assert!(pl.decode(&mut buf).is_err());
+
+        let mut buf = BytesMut::from(head);
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+        buf.extend(b"0\r\na: 12345678901234567890123456789012345678901234567890\r\n");
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+        buf.extend(b"b: 12345678901234567890\r\n");
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
     #[test]
     fn test_parse_chunked_payload_chunk_extension() {
         let mut buf = BytesMut::from(
@@ -1231,6 +1484,80 @@ mod tests {
         assert!(msg.eof());
     }
 
+    #[test]
+    fn test_limits() {
+        let limits = H1Limits {
+            max_headers: 2,
+            max_head_size: 64,
+            max_uri_length: 8,
+        };
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n");
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), 2);
+
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\n\r\n");
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        let mut buf = BytesMut::from("GET /test/long HTTP/1.1\r\n\r\n");
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        assert!(matches!(
+            reader.decode(&mut buf),
+            Err(ParseError::UriTooLong)
+        ));
+
+        let mut buf = BytesMut::from(
+            "GET / HTTP/1.1\r\na: 12345678901234567890123456789012345678901234567890\r\n\r\n",
+        );
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        let mut buf = BytesMut::from(&[b'a'; 64][..]);
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        // URI length is checked before head size
+        let mut buf =
+            BytesMut::from(format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(80)).as_str());
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        assert!(matches!(
+            reader.decode(&mut buf),
+            Err(ParseError::UriTooLong)
+        ));
+
+        let mut buf = BytesMut::from(format!("GET /{}", "a".repeat(80)).as_str());
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        assert!(matches!(
+            reader.decode(&mut buf),
+            Err(ParseError::UriTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_many_headers() {
+        let mut head = String::from("GET /test HTTP/1.1\r\n");
+        for i in 0..200 {
+            head.push_str(&format!("x-header-{}: {}\r\n", i, i));
+        }
+        head.push_str("\r\n");
+
+        let mut buf = BytesMut::from(head.as_str());
+        let mut reader = MessageDecoder::<Request>::default();
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        let limits = H1Limits {
+            max_headers: 256,
+            ..H1Limits::default()
+        };
+        let mut buf = BytesMut::from(head.as_str());
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), 200);
+    }
+
     #[test]
     fn test_response_http10_read_until_eof() {
         let mut buf = BytesMut::from(&"HTTP/1.0 200 Ok\r\n\r\ntest data"[..]);
@@ -1240,6 +1567,6 @@ mod tests {
         let mut pl = pl.unwrap();
 
         let chunk = pl.decode(&mut buf).unwrap().unwrap();
-        assert_eq!(chunk, PayloadItem::Chunk(Bytes::from_static(b"test data")));
+        assert_eq!(chunk.chunk(), Bytes::from_static(b"test data"));
     }
 }
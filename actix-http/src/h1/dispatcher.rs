@@ -1,4 +1,5 @@
 use std::{
+    cmp,
     collections::VecDeque,
     fmt,
     future::Future,
@@ -376,6 +377,12 @@ where
                             }
 
                             Poll::Ready(None) => {
+                                if let Some(trailers) = stream.as_mut().take_trailers() {
+                                    this.codec.encode(
+                                        Message::Trailers(trailers),
+                                        this.write_buf,
+                                    )?;
+                                }
                                 this.codec
                                     .encode(Message::Chunk(None), &mut this.write_buf)?;
                                 // payload stream finished.
@@ -536,6 +543,8 @@ where
                                     where the state can be collected and consumed.
                                     */
                                     let (ps, pl) = Payload::create(false);
+                                    let ps =
+                                        ps.with_trailers(req.head_mut().trailers_slot());
                                     let (req1, _) =
                                         req.replace_payload(crate::Payload::H1(pl));
                                     req = req1;
@@ -569,6 +578,11 @@ where
                                 break;
                             }
                         }
+                        Message::Trailers(trailers) => {
+                            if let Some(ref mut payload) = this.payload {
+                                payload.feed_trailers(trailers);
+                            }
+                        }
                         Message::Chunk(None) => {
                             if let Some(mut payload) = this.payload.take() {
                                 payload.feed_eof();
@@ -605,6 +619,15 @@ where
                     *this.error = Some(ParseError::TooLarge.into());
                     break;
                 }
+                Err(ParseError::UriTooLong) => {
+                    // Requests with too long URI should be responded with 414
+                    this.messages.push_back(DispatcherMessage::Error(
+                        Response::UriTooLong().finish().drop_body(),
+                    ));
+                    this.flags.insert(Flags::READ_DISCONNECT);
+                    *this.error = Some(ParseError::UriTooLong.into());
+                    break;
+                }
                 Err(err) => {
                     if let Some(mut payload) = this.payload.take() {
                         payload.set_error(PayloadError::EncodingCorrupted);
@@ -735,9 +758,15 @@ where
 
         let mut read_some = false;
 
+        // buffer at least a full request head of the configured size.
+        let max_buf = cmp::max(
+            super::decoder::MAX_BUFFER_SIZE,
+            this.codec.config().h1_limits().max_head_size,
+        );
+
         loop {
             // Return early when read buf exceed decoder's max buffer size.
-            if this.read_buf.len() >= super::decoder::MAX_BUFFER_SIZE {
+            if this.read_buf.len() >= max_buf {
                 /*
                  At this point it's not known IO stream is still scheduled
                  to be waked up. so force wake up dispatcher just in case.
@@ -12,7 +12,7 @@ use crate::header::{map::Value, HeaderName};
 use crate::helpers;
 use crate::http::header::{CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
 use crate::http::{HeaderMap, StatusCode, Version};
-use crate::message::{ConnectionType, RequestHeadType};
+use crate::message::{ConnectionType, RequestHead, RequestHeadType};
 use crate::response::Response;
 
 const AVERAGE_HEADER_SIZE: usize = 30;
@@ -285,27 +285,63 @@ impl MessageType for RequestHeadType {
 
     fn encode_status(&mut self, dst: &mut BytesMut) -> io::Result<()> {
         let head = self.as_ref();
-        dst.reserve(256 + head.headers.len() * AVERAGE_HEADER_SIZE);
-        write!(
-            helpers::Writer(dst),
-            "{} {} {}",
-            head.method,
-            head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"),
-            match head.version {
-                Version::HTTP_09 => "HTTP/0.9",
-                Version::HTTP_10 => "HTTP/1.0",
-                Version::HTTP_11 => "HTTP/1.1",
-                Version::HTTP_2 => "HTTP/2.0",
-                Version::HTTP_3 => "HTTP/3.0",
-                _ =>
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "unsupported version"
-                    )),
-            }
-        )
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        let target = head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/");
+        encode_request_line(head, target, dst)
+    }
+}
+
+/// Request head with an absolute-form request target, as sent to forward proxies.
+pub(crate) struct AbsoluteForm<'a>(pub(crate) &'a mut RequestHeadType);
+
+impl MessageType for AbsoluteForm<'_> {
+    fn status(&self) -> Option<StatusCode> {
+        None
+    }
+
+    fn chunked(&self) -> bool {
+        self.0.chunked()
+    }
+
+    fn camel_case(&self) -> bool {
+        self.0.camel_case()
     }
+
+    fn headers(&self) -> &HeaderMap {
+        MessageType::headers(self.0)
+    }
+
+    fn extra_headers(&self) -> Option<&HeaderMap> {
+        self.0.extra_headers()
+    }
+
+    fn encode_status(&mut self, dst: &mut BytesMut) -> io::Result<()> {
+        let head = self.0.as_ref();
+        encode_request_line(head, &head.uri.to_string(), dst)
+    }
+}
+
+fn encode_request_line(
+    head: &RequestHead,
+    target: &str,
+    dst: &mut BytesMut,
+) -> io::Result<()> {
+    dst.reserve(256 + head.headers.len() * AVERAGE_HEADER_SIZE);
+    write!(
+        helpers::Writer(dst),
+        "{} {} {}",
+        head.method,
+        target,
+        match head.version {
+            Version::HTTP_09 => "HTTP/0.9",
+            Version::HTTP_10 => "HTTP/1.0",
+            Version::HTTP_11 => "HTTP/1.1",
+            Version::HTTP_2 => "HTTP/2.0",
+            Version::HTTP_3 => "HTTP/3.0",
+            _ =>
+                return Err(io::Error::new(io::ErrorKind::Other, "unsupported version")),
+        }
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
 }
 
 impl<T: MessageType> MessageEncoder<T> {
@@ -319,10 +355,19 @@ impl<T: MessageType> MessageEncoder<T> {
         self.te.encode_eof(buf)
     }
 
-    pub fn encode(
+    /// Encode trailers and eof
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.te.encode_trailers(trailers, buf)
+    }
+
+    pub fn encode<M: MessageType>(
         &mut self,
         dst: &mut BytesMut,
-        message: &mut T,
+        message: &mut M,
         head: bool,
         stream: bool,
         version: Version,
@@ -468,6 +513,32 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode trailer fields followed by eof.
+    ///
+    /// Trailers can only be sent with chunked encoding and are discarded otherwise.
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(ref mut eof) if !*eof => {
+                *eof = true;
+                buf.extend_from_slice(b"0\r\n");
+                for (name, value) in trailers {
+                    buf.reserve(name.as_str().len() + value.len() + 4);
+                    buf.extend_from_slice(name.as_str().as_bytes());
+                    buf.extend_from_slice(b": ");
+                    buf.extend_from_slice(value.as_bytes());
+                    buf.extend_from_slice(b"\r\n");
+                }
+                buf.extend_from_slice(b"\r\n");
+                Ok(())
+            }
+            _ => self.encode_eof(buf),
+        }
+    }
 }
 
 /// # Safety
@@ -529,6 +600,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        assert!(!enc.encode(b"test", &mut bytes).unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        enc.encode_eof(&mut bytes).unwrap();
+        assert_eq!(
+            bytes.split().freeze(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\ngrpc-status: 0\r\n\r\n")
+        );
+
+        // trailers are discarded without chunked encoding
+        let mut enc = TransferEncoding::length(4);
+        assert!(enc.encode(b"test", &mut bytes).unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(bytes.split().freeze(), Bytes::from_static(b"test"));
+    }
+
     #[actix_rt::test]
     async fn test_camel_case() {
         let mut bytes = BytesMut::with_capacity(2048);
@@ -1,6 +1,8 @@
 //! HTTP/1 protocol implementation.
 use bytes::{Bytes, BytesMut};
 
+use crate::header::HeaderMap;
+
 mod client;
 mod codec;
 mod decoder;
@@ -14,6 +16,7 @@ mod utils;
 
 pub use self::client::{ClientCodec, ClientPayloadCodec};
 pub use self::codec::Codec;
+pub(crate) use self::decoder::{MAX_BUFFER_SIZE, MAX_HEADERS};
 pub use self::dispatcher::Dispatcher;
 pub use self::expect::ExpectHandler;
 pub use self::payload::Payload;
@@ -28,6 +31,8 @@ pub enum Message<T> {
     Item(T),
     /// Payload chunk
     Chunk(Option<Bytes>),
+    /// Trailer fields of a chunked payload, followed by payload eof (`Chunk(None)`).
+    Trailers(HeaderMap),
 }
 
 impl<T> From<T> for Message<T> {
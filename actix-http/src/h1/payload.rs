@@ -10,6 +10,8 @@ use bytes::Bytes;
 use futures_core::Stream;
 
 use crate::error::PayloadError;
+use crate::header::HeaderMap;
+use crate::message::TrailersSlot;
 
 /// max buffer size 32k
 pub(crate) const MAX_BUFFER_SIZE: usize = 32_768;
@@ -48,6 +50,7 @@ impl Payload {
         (
             PayloadSender {
                 inner: Rc::downgrade(&shared),
+                trailers: None,
             },
             Payload { inner: shared },
         )
@@ -102,9 +105,24 @@ impl Stream for Payload {
 /// Sender part of the payload stream
 pub struct PayloadSender {
     inner: Weak<RefCell<Inner>>,
+    trailers: Option<TrailersSlot>,
 }
 
 impl PayloadSender {
+    /// Deliver received trailers to the given slot.
+    pub(crate) fn with_trailers(mut self, slot: TrailersSlot) -> Self {
+        self.trailers = Some(slot);
+        self
+    }
+
+    /// Feed trailer fields received after the payload.
+    #[inline]
+    pub fn feed_trailers(&mut self, trailers: HeaderMap) {
+        if let Some(ref slot) = self.trailers {
+            slot.set(trailers);
+        }
+    }
+
     #[inline]
     pub fn set_error(&mut self, err: PayloadError) {
         if let Some(shared) = self.inner.upgrade() {
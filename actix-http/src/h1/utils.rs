@@ -64,10 +64,21 @@ where
                         Poll::Ready(item) => {
                             // body is done when item is None
                             body_done = item.is_none();
+                            let mut framed = this.framed.as_mut().as_pin_mut().unwrap();
                             if body_done {
+                                let trailers = this
+                                    .body
+                                    .as_mut()
+                                    .as_pin_mut()
+                                    .unwrap()
+                                    .take_trailers();
+                                if let Some(trailers) = trailers {
+                                    framed
+                                        .as_mut()
+                                        .write(Message::Trailers(trailers))?;
+                                }
                                 let _ = this.body.take();
                             }
-                            let framed = this.framed.as_mut().as_pin_mut().unwrap();
                             framed.write(Message::Chunk(item))?;
                         }
                         Poll::Pending => body_ready = false,
@@ -7,12 +7,12 @@ use std::task::{Context, Poll};
 use std::{cmp, convert::TryFrom};
 
 use actix_codec::{AsyncRead, AsyncWrite};
-use actix_rt::time::{Instant, Sleep};
+use actix_rt::time::{sleep, Instant, Sleep};
 use actix_service::Service;
 use bytes::{Bytes, BytesMut};
-use futures_core::ready;
+use futures_core::{future::LocalBoxFuture, ready};
 use h2::server::{Connection, SendResponse};
-use h2::SendStream;
+use h2::{Ping, PingPong, SendStream};
 use http::header::{HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
 use log::{error, trace};
 
@@ -43,9 +43,52 @@ where
     peer_addr: Option<net::SocketAddr>,
     ka_expire: Instant,
     ka_timer: Option<Sleep>,
+    ping_pong: Option<H2PingPong>,
+    shutdown: Option<LocalBoxFuture<'static, ()>>,
     _phantom: PhantomData<B>,
 }
 
+/// State of HTTP/2 keep-alive PINGs.
+struct H2PingPong {
+    ping_pong: PingPong,
+    timer: Pin<Box<Sleep>>,
+    in_flight: bool,
+}
+
+impl H2PingPong {
+    /// Send PINGs on interval and check that they are acknowledged in time.
+    ///
+    /// Returns `false` if the peer failed to acknowledge a PING within the timeout.
+    fn poll_alive(
+        &mut self,
+        cx: &mut Context<'_>,
+        config: &ServiceConfig,
+    ) -> Result<bool, DispatchError> {
+        loop {
+            if self.in_flight {
+                match self.ping_pong.poll_pong(cx)? {
+                    Poll::Ready(_) => {
+                        self.in_flight = false;
+                        let interval = config.h2_ping_interval().unwrap_or_default();
+                        self.timer.as_mut().reset(Instant::now() + interval);
+                    }
+                    Poll::Pending => {
+                        return Ok(self.timer.as_mut().poll(cx).is_pending());
+                    }
+                }
+            } else if self.timer.as_mut().poll(cx).is_ready() {
+                self.ping_pong.send_ping(Ping::opaque())?;
+                self.in_flight = true;
+                self.timer
+                    .as_mut()
+                    .reset(Instant::now() + config.h2_ping_timeout());
+            } else {
+                return Ok(true);
+            }
+        }
+    }
+}
+
 impl<T, S, B, X, U> Dispatcher<T, S, B, X, U>
 where
     T: AsyncRead + AsyncWrite + Unpin,
@@ -56,7 +99,7 @@ where
 {
     pub(crate) fn new(
         flow: Rc<HttpFlow<S, X, U>>,
-        connection: Connection<T, Bytes>,
+        mut connection: Connection<T, Bytes>,
         on_connect_data: OnConnectData,
         config: ServiceConfig,
         timeout: Option<Sleep>,
@@ -78,6 +121,19 @@ where
             (config.now(), None)
         };
 
+        // keep-alive pings
+        let ping_pong = config.h2_ping_interval().and_then(|interval| {
+            connection.ping_pong().map(|ping_pong| H2PingPong {
+                ping_pong,
+                timer: Box::pin(sleep(interval)),
+                in_flight: false,
+            })
+        });
+
+        let shutdown = config
+            .shutdown_signal()
+            .map(|signal| Box::pin(signal) as LocalBoxFuture<'static, ()>);
+
         Dispatcher {
             flow,
             config,
@@ -86,6 +142,8 @@ where
             on_connect_data,
             ka_expire,
             ka_timer,
+            ping_pong,
+            shutdown,
             _phantom: PhantomData,
         }
     }
@@ -106,6 +164,22 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        // send GOAWAY and stop accepting new streams once shutdown is requested
+        if let Some(ref mut shutdown) = this.shutdown {
+            if shutdown.as_mut().poll(cx).is_ready() {
+                trace!("Starting graceful HTTP/2 connection shutdown");
+                this.shutdown = None;
+                this.connection.graceful_shutdown();
+            }
+        }
+
+        if let Some(ref mut ping_pong) = this.ping_pong {
+            if !ping_pong.poll_alive(cx, &this.config)? {
+                trace!("HTTP/2 keep-alive PING timed out, closing connection");
+                return Poll::Ready(Ok(()));
+            }
+        }
+
         loop {
             match ready!(Pin::new(&mut this.connection).poll_accept(cx)) {
                 None => return Poll::Ready(Ok(())),
@@ -121,17 +195,20 @@ where
                     }
 
                     let (parts, body) = req.into_parts();
-                    let pl = crate::h2::Payload::new(body);
-                    let pl = Payload::<crate::payload::PayloadStream>::H2(pl);
-                    let mut req = Request::with_payload(pl);
+                    let mut req = Request::new();
 
-                    let head = &mut req.head_mut();
+                    let head = req.head_mut();
+                    let pl = crate::h2::Payload::new(body)
+                        .with_trailers(head.trailers_slot());
                     head.uri = parts.uri;
                     head.method = parts.method;
                     head.version = parts.version;
                     head.headers = parts.headers.into();
                     head.peer_addr = this.peer_addr;
 
+                    let pl = Payload::<crate::payload::PayloadStream>::H2(pl);
+                    let (mut req, _) = req.replace_payload(pl);
+
                     // merge on_connect_ext data into request extensions
                     this.on_connect_data.merge_into(&mut req);
 
@@ -341,8 +418,19 @@ where
 
                             None => match ready!(body.as_mut().poll_next(cx)) {
                                 None => {
-                                    if let Err(e) = stream.send_data(Bytes::new(), true)
-                                    {
+                                    let res = match body.as_mut().take_trailers() {
+                                        Some(trailers) => {
+                                            let mut map = http::HeaderMap::with_capacity(
+                                                trailers.len(),
+                                            );
+                                            for (key, value) in trailers.iter() {
+                                                map.append(key, value.clone());
+                                            }
+                                            stream.send_trailers(map)
+                                        }
+                                        None => stream.send_data(Bytes::new(), true),
+                                    };
+                                    if let Err(e) = res {
                                         warn!("{:?}", e);
                                     }
                                     return Poll::Ready(());
@@ -5,8 +5,10 @@ use std::{
     task::{Context, Poll},
 };
 
+use actix_codec::{AsyncRead, AsyncWrite};
 use bytes::Bytes;
 use futures_core::{ready, Stream};
+use h2::server::{Builder, Handshake};
 use h2::RecvStream;
 
 mod dispatcher;
@@ -14,16 +16,55 @@ mod service;
 
 pub use self::dispatcher::Dispatcher;
 pub use self::service::H2Service;
+use crate::config::ServiceConfig;
 use crate::error::PayloadError;
+use crate::message::TrailersSlot;
+
+/// Start server handshake with the HTTP/2 settings of the service config.
+pub(crate) fn handshake<T>(io: T, config: &ServiceConfig) -> Handshake<T, Bytes>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let settings = config.h2_settings();
+    let mut builder = Builder::new();
+
+    if let Some(max) = settings.max_concurrent_streams {
+        builder.max_concurrent_streams(max);
+    }
+    if let Some(size) = settings.initial_window_size {
+        builder.initial_window_size(size);
+    }
+    if let Some(size) = settings.initial_connection_window_size {
+        builder.initial_connection_window_size(size);
+    }
+    if let Some(size) = settings.max_frame_size {
+        builder.max_frame_size(size);
+    }
+    if let Some(size) = settings.max_header_list_size {
+        builder.max_header_list_size(size);
+    }
+
+    builder.handshake(io)
+}
 
 /// HTTP/2 peer stream.
 pub struct Payload {
     stream: RecvStream,
+    trailers: Option<TrailersSlot>,
 }
 
 impl Payload {
     pub(crate) fn new(stream: RecvStream) -> Self {
-        Self { stream }
+        Self {
+            stream,
+            trailers: None,
+        }
+    }
+
+    /// Deliver received trailers to the given slot.
+    pub(crate) fn with_trailers(mut self, slot: TrailersSlot) -> Self {
+        self.trailers = Some(slot);
+        self
     }
 }
 
@@ -46,7 +87,16 @@ impl Stream for Payload {
                 }
             }
             Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
-            None => Poll::Ready(None),
+            None => {
+                if let Some(ref slot) = this.trailers {
+                    match ready!(this.stream.poll_trailers(cx)) {
+                        Ok(Some(trailers)) => slot.set(trailers.into()),
+                        Ok(None) => {}
+                        Err(err) => return Poll::Ready(Some(Err(err.into()))),
+                    }
+                }
+                Poll::Ready(None)
+            }
         }
     }
 }
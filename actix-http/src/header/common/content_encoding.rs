@@ -25,6 +25,11 @@ pub enum ContentEncoding {
 
     /// Indicates the identity function (i.e. no compression, nor modification).
     Identity,
+
+    /// A format using the Zstandard algorithm.
+    ///
+    /// Encoding and decoding require the `compress-zstd` feature.
+    Zstd,
 }
 
 impl ContentEncoding {
@@ -41,14 +46,21 @@ impl ContentEncoding {
             ContentEncoding::Br => "br",
             ContentEncoding::Gzip => "gzip",
             ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Zstd => "zstd",
             ContentEncoding::Identity | ContentEncoding::Auto => "identity",
         }
     }
 
     /// Default Q-factor (quality) value.
+    ///
+    /// Without the `compress-zstd` feature, zstd is not acceptable and has a quality of 0.
     #[inline]
     pub fn quality(self) -> f64 {
         match self {
+            #[cfg(feature = "compress-zstd")]
+            ContentEncoding::Zstd => 1.2,
+            #[cfg(not(feature = "compress-zstd"))]
+            ContentEncoding::Zstd => 0.0,
             ContentEncoding::Br => 1.1,
             ContentEncoding::Gzip => 1.0,
             ContentEncoding::Deflate => 0.9,
@@ -81,6 +93,8 @@ impl From<&str> for ContentEncoding {
             ContentEncoding::Gzip
         } else if val.eq_ignore_ascii_case("deflate") {
             ContentEncoding::Deflate
+        } else if val.eq_ignore_ascii_case("zstd") {
+            ContentEncoding::Zstd
         } else {
             ContentEncoding::default()
         }
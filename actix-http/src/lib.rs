@@ -59,7 +59,7 @@ pub mod ws;
 pub use cookie;
 
 pub use self::builder::HttpServiceBuilder;
-pub use self::config::{KeepAlive, ServiceConfig};
+pub use self::config::{KeepAlive, ServiceConfig, ShutdownSignal};
 pub use self::error::{Error, ResponseError, Result};
 pub use self::extensions::Extensions;
 pub use self::http_message::HttpMessage;
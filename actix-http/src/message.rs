@@ -41,6 +41,18 @@ pub trait Head: Default + 'static {
         F: FnOnce(&MessagePool<Self>) -> R;
 }
 
+/// Trailer fields received after a request payload.
+///
+/// Shared between the request head and the protocol dispatcher feeding the payload.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrailersSlot(Rc<RefCell<Option<HeaderMap>>>);
+
+impl TrailersSlot {
+    pub(crate) fn set(&self, trailers: HeaderMap) {
+        *self.0.borrow_mut() = Some(trailers);
+    }
+}
+
 #[derive(Debug)]
 pub struct RequestHead {
     pub uri: Uri,
@@ -50,6 +62,7 @@ pub struct RequestHead {
     pub extensions: RefCell<Extensions>,
     pub peer_addr: Option<net::SocketAddr>,
     flags: Flags,
+    trailers: Option<TrailersSlot>,
 }
 
 impl Default for RequestHead {
@@ -62,6 +75,7 @@ impl Default for RequestHead {
             flags: Flags::empty(),
             peer_addr: None,
             extensions: RefCell::new(Extensions::new()),
+            trailers: None,
         }
     }
 }
@@ -71,6 +85,7 @@ impl Head for RequestHead {
         self.flags = Flags::empty();
         self.headers.clear();
         self.extensions.get_mut().clear();
+        self.trailers = None;
     }
 
     fn with_pool<F, R>(f: F) -> R
@@ -104,6 +119,22 @@ impl RequestHead {
         &mut self.headers
     }
 
+    /// Trailer fields received after the request payload.
+    ///
+    /// Trailers are only available once the payload has been read to completion.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.trailers
+            .as_ref()
+            .and_then(|slot| slot.0.borrow().clone())
+    }
+
+    /// Slot that receives trailer fields of the request payload.
+    pub(crate) fn trailers_slot(&mut self) -> TrailersSlot {
+        self.trailers
+            .get_or_insert_with(TrailersSlot::default)
+            .clone()
+    }
+
     /// Is to uppercase headers with Camel-Case.
     /// Default is `false`
     #[inline]
@@ -8,7 +8,7 @@ use actix_rt::net::TcpStream;
 use actix_service::{pipeline_factory, IntoServiceFactory, Service, ServiceFactory};
 use bytes::Bytes;
 use futures_core::{ready, Future};
-use h2::server::{self, Handshake};
+use h2::server::Handshake;
 use pin_project::pin_project;
 
 use crate::body::MessageBody;
@@ -562,7 +562,7 @@ where
         match proto {
             Protocol::Http2 => HttpServiceHandlerResponse {
                 state: State::H2Handshake(Some((
-                    server::handshake(io),
+                    crate::h2::handshake(io, &self.cfg),
                     self.cfg.clone(),
                     self.flow.clone(),
                     on_connect_data,
@@ -3,6 +3,8 @@ use bitflags::bitflags;
 use bytes::{Bytes, BytesMut};
 use bytestring::ByteString;
 
+#[cfg(feature = "compress")]
+use super::deflate::{DeflateConfig, DeflateContext};
 use super::frame::Parser;
 use super::proto::{CloseReason, OpCode};
 use super::ProtocolError;
@@ -63,11 +65,15 @@ pub enum Item {
     Last(Bytes),
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 /// WebSocket protocol codec.
+///
+/// Cloning a codec with compression enabled creates a fresh compression context.
 pub struct Codec {
     flags: Flags,
     max_size: usize,
+    #[cfg(feature = "compress")]
+    deflate: Option<DeflateContext>,
 }
 
 bitflags! {
@@ -75,6 +81,8 @@ bitflags! {
         const SERVER         = 0b0000_0001;
         const CONTINUATION   = 0b0000_0010;
         const W_CONTINUATION = 0b0000_0100;
+        const R_COMPRESSED   = 0b0000_1000;
+        const W_COMPRESSED   = 0b0001_0000;
     }
 }
 
@@ -84,6 +92,8 @@ impl Codec {
         Codec {
             max_size: 65_536,
             flags: Flags::SERVER,
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 
@@ -102,6 +112,85 @@ impl Codec {
         self.flags.remove(Flags::SERVER);
         self
     }
+
+    /// Enable the `permessage-deflate` extension with parameters agreed during the handshake.
+    ///
+    /// Text and Binary messages are compressed and decompressed transparently. The max frame
+    /// size also limits the size of decompressed frames.
+    #[cfg(feature = "compress")]
+    pub fn deflate(mut self, config: DeflateConfig) -> Self {
+        self.deflate = Some(DeflateContext::new(config));
+        self
+    }
+
+    /// Write a Text, Binary or Continue frame, compressing its payload if enabled.
+    #[cfg_attr(not(feature = "compress"), allow(unused_variables))]
+    fn write_data(
+        &mut self,
+        dst: &mut BytesMut,
+        data: &[u8],
+        op: OpCode,
+        first: bool,
+        fin: bool,
+    ) -> Result<(), ProtocolError> {
+        let mask = !self.flags.contains(Flags::SERVER);
+
+        #[cfg(feature = "compress")]
+        if let Some(ref mut deflate) = self.deflate {
+            let server = self.flags.contains(Flags::SERVER);
+
+            if first {
+                let compress = deflate.start_message(data.len(), fin, server);
+                self.flags.set(Flags::W_COMPRESSED, compress);
+            }
+
+            if self.flags.contains(Flags::W_COMPRESSED) {
+                let payload = deflate.compress(data, fin, server)?;
+                // only the first frame of a compressed message is marked
+                Parser::write_message_rsv1(dst, payload, op, fin, first, mask);
+                return Ok(());
+            }
+        }
+
+        Parser::write_message(dst, data, op, fin, mask);
+        Ok(())
+    }
+
+    /// Decompress the payload of a Text, Binary or Continue frame if its message is compressed.
+    #[cfg(feature = "compress")]
+    fn read_data(
+        &mut self,
+        finished: bool,
+        rsv1: bool,
+        opcode: OpCode,
+        payload: Option<BytesMut>,
+    ) -> Result<Option<BytesMut>, ProtocolError> {
+        let deflate = match self.deflate {
+            Some(ref mut deflate) => deflate,
+            None => return Ok(payload),
+        };
+
+        match opcode {
+            OpCode::Text | OpCode::Binary => self.flags.set(Flags::R_COMPRESSED, rsv1),
+            _ if rsv1 => return Err(ProtocolError::UnexpectedCompressedFrame),
+            OpCode::Continue => {}
+            _ => return Ok(payload),
+        }
+
+        if !self.flags.contains(Flags::R_COMPRESSED) {
+            return Ok(payload);
+        }
+
+        let server = self.flags.contains(Flags::SERVER);
+        let data = payload.as_deref().unwrap_or(&[]);
+        let payload = deflate.decompress(data, finished, server, self.max_size)?;
+
+        if finished {
+            self.flags.remove(Flags::R_COMPRESSED);
+        }
+
+        Ok(Some(payload))
+    }
 }
 
 impl Encoder<Message> for Codec {
@@ -109,20 +198,12 @@ impl Encoder<Message> for Codec {
 
     fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
-            Message::Text(txt) => Parser::write_message(
-                dst,
-                txt,
-                OpCode::Text,
-                true,
-                !self.flags.contains(Flags::SERVER),
-            ),
-            Message::Binary(bin) => Parser::write_message(
-                dst,
-                bin,
-                OpCode::Binary,
-                true,
-                !self.flags.contains(Flags::SERVER),
-            ),
+            Message::Text(txt) => {
+                self.write_data(dst, txt.as_bytes(), OpCode::Text, true, true)?
+            }
+            Message::Binary(bin) => {
+                self.write_data(dst, &bin, OpCode::Binary, true, true)?
+            }
             Message::Ping(txt) => Parser::write_message(
                 dst,
                 txt,
@@ -146,13 +227,7 @@ impl Encoder<Message> for Codec {
                         return Err(ProtocolError::ContinuationStarted);
                     } else {
                         self.flags.insert(Flags::W_CONTINUATION);
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Text,
-                            false,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data, OpCode::Text, true, false)?
                     }
                 }
                 Item::FirstBinary(data) => {
@@ -160,24 +235,12 @@ impl Encoder<Message> for Codec {
                         return Err(ProtocolError::ContinuationStarted);
                     } else {
                         self.flags.insert(Flags::W_CONTINUATION);
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Binary,
-                            false,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data, OpCode::Binary, true, false)?
                     }
                 }
                 Item::Continue(data) => {
                     if self.flags.contains(Flags::W_CONTINUATION) {
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Continue,
-                            false,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data, OpCode::Continue, false, false)?
                     } else {
                         return Err(ProtocolError::ContinuationNotStarted);
                     }
@@ -185,13 +248,7 @@ impl Encoder<Message> for Codec {
                 Item::Last(data) => {
                     if self.flags.contains(Flags::W_CONTINUATION) {
                         self.flags.remove(Flags::W_CONTINUATION);
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Continue,
-                            true,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data, OpCode::Continue, false, true)?
                     } else {
                         return Err(ProtocolError::ContinuationNotStarted);
                     }
@@ -208,8 +265,15 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.flags.contains(Flags::SERVER), self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
+        match Parser::parse_with_rsv1(
+            src,
+            self.flags.contains(Flags::SERVER),
+            self.max_size,
+        ) {
+            Ok(Some((finished, _rsv1, opcode, payload))) => {
+                #[cfg(feature = "compress")]
+                let payload = self.read_data(finished, _rsv1, opcode, payload)?;
+
                 // continuation is not supported
                 if !finished {
                     return match opcode {
@@ -0,0 +1,654 @@
+//! Per-message deflate extension (RFC 7692).
+
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use flate2::{
+    Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status,
+};
+
+use crate::header::{HeaderMap, HeaderValue, SEC_WEBSOCKET_EXTENSIONS};
+
+use super::ProtocolError;
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+const SERVER_NO_CONTEXT_TAKEOVER: &str = "server_no_context_takeover";
+const CLIENT_NO_CONTEXT_TAKEOVER: &str = "client_no_context_takeover";
+const SERVER_MAX_WINDOW_BITS: &str = "server_max_window_bits";
+const CLIENT_MAX_WINDOW_BITS: &str = "client_max_window_bits";
+
+/// Bytes removed from the end of every compressed message and restored before inflating it.
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const MAX_WINDOW_BITS: u8 = 15;
+const MIN_WINDOW_BITS: u8 = 8;
+
+/// Parameters of the `permessage-deflate` WebSocket extension.
+///
+/// The same type is used to describe the parameters an endpoint is willing to use and the
+/// parameters agreed on during the handshake. Agreed parameters are passed to
+/// [`Codec::deflate`](super::Codec::deflate) to enable compression of Text and Binary messages.
+///
+/// The window sizes used by this implementation's own compressor can not be lowered below 32KiB.
+/// When the peer asks for a smaller window, the compression context is reset for every message
+/// and messages larger than the requested window are sent uncompressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<u8>,
+    client_max_window_bits: Option<u8>,
+}
+
+impl DeflateConfig {
+    /// Create default configuration with context takeover and maximum window sizes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the server to reset its compression context after every message.
+    pub fn server_no_context_takeover(mut self, enabled: bool) -> Self {
+        self.server_no_context_takeover = enabled;
+        self
+    }
+
+    /// Require the client to reset its compression context after every message.
+    pub fn client_no_context_takeover(mut self, enabled: bool) -> Self {
+        self.client_no_context_takeover = enabled;
+        self
+    }
+
+    /// Limit the LZ77 window size, as a base-2 logarithm, used by the server's compressor.
+    ///
+    /// # Panics
+    /// Panics if `bits` is not in the range `8..=15`.
+    pub fn server_max_window_bits(mut self, bits: u8) -> Self {
+        assert!(
+            (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&bits),
+            "window bits must be in the range 8..=15"
+        );
+        self.server_max_window_bits = Some(bits);
+        self
+    }
+
+    /// Limit the LZ77 window size, as a base-2 logarithm, used by the client's compressor.
+    ///
+    /// # Panics
+    /// Panics if `bits` is not in the range `8..=15`.
+    pub fn client_max_window_bits(mut self, bits: u8) -> Self {
+        assert!(
+            (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&bits),
+            "window bits must be in the range 8..=15"
+        );
+        self.client_max_window_bits = Some(bits);
+        self
+    }
+
+    /// Create the `Sec-WebSocket-Extensions` header value offered by a client.
+    pub fn offer(&self) -> HeaderValue {
+        let mut offer = self.to_string();
+
+        // advertise that a limit on the client's window can be honoured
+        if self.client_max_window_bits.is_none() {
+            offer.push_str("; ");
+            offer.push_str(CLIENT_MAX_WINDOW_BITS);
+        }
+
+        // only ascii tokens and digits are written
+        HeaderValue::from_str(&offer).unwrap()
+    }
+
+    /// Create the `Sec-WebSocket-Extensions` header value sent back by a server.
+    pub fn to_header_value(&self) -> HeaderValue {
+        // only ascii tokens and digits are written
+        HeaderValue::from_str(&self.to_string()).unwrap()
+    }
+
+    /// Select the first acceptable `permessage-deflate` offer from client request headers.
+    ///
+    /// Returns agreed parameters, which combine the client's offer with this configuration, or
+    /// `None` if the client did not make any acceptable offer.
+    pub fn negotiate(&self, headers: &HeaderMap) -> Option<DeflateConfig> {
+        parse_extensions(headers)
+            .ok()?
+            .into_iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(EXTENSION_NAME))
+            .find_map(|(_, params)| self.accept_offer(&params))
+    }
+
+    /// Validate the `permessage-deflate` response of a server against this offer.
+    ///
+    /// Returns agreed parameters or `None` if the server declined the extension. The response is
+    /// rejected if it contains other extensions or parameters that were not offered.
+    pub fn accept_response(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<Option<DeflateConfig>, ProtocolError> {
+        let mut extensions = parse_extensions(headers)
+            .map_err(|_| ProtocolError::InvalidExtension)?
+            .into_iter();
+
+        let params = match (extensions.next(), extensions.next()) {
+            (None, _) => return Ok(None),
+            (Some((name, params)), None)
+                if name.eq_ignore_ascii_case(EXTENSION_NAME) =>
+            {
+                params
+            }
+            _ => return Err(ProtocolError::InvalidExtension),
+        };
+
+        let agreed = parse_params(&params).ok_or(ProtocolError::InvalidExtension)?;
+
+        // the server must honour the window limit that was asked of it
+        let server_bits_ok =
+            match (self.server_max_window_bits, agreed.server_max_window_bits) {
+                (Some(offered), Some(bits)) => bits <= offered,
+                (Some(_), None) => false,
+                _ => true,
+            };
+
+        // the server can only fulfil a request to not use context takeover
+        let takeover_ok =
+            !self.server_no_context_takeover || agreed.server_no_context_takeover;
+
+        if !server_bits_ok || !takeover_ok {
+            return Err(ProtocolError::InvalidExtension);
+        }
+
+        Ok(Some(agreed))
+    }
+
+    fn accept_offer(
+        &self,
+        params: &[(String, Option<String>)],
+    ) -> Option<DeflateConfig> {
+        let offer = parse_params(params)?;
+        let client_bits_offered = params
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(CLIENT_MAX_WINDOW_BITS));
+
+        let server_max_window_bits =
+            min_bits(offer.server_max_window_bits, self.server_max_window_bits);
+
+        // a limit can only be placed on the client's window if the client offered to honour it
+        let client_max_window_bits = if client_bits_offered {
+            min_bits(offer.client_max_window_bits, self.client_max_window_bits)
+        } else {
+            None
+        };
+
+        Some(DeflateConfig {
+            server_no_context_takeover: offer.server_no_context_takeover
+                || self.server_no_context_takeover,
+            client_no_context_takeover: offer.client_no_context_takeover
+                || self.client_no_context_takeover,
+            server_max_window_bits,
+            client_max_window_bits,
+        })
+    }
+}
+
+impl std::fmt::Display for DeflateConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(EXTENSION_NAME)?;
+
+        if self.server_no_context_takeover {
+            write!(f, "; {}", SERVER_NO_CONTEXT_TAKEOVER)?;
+        }
+        if self.client_no_context_takeover {
+            write!(f, "; {}", CLIENT_NO_CONTEXT_TAKEOVER)?;
+        }
+        if let Some(bits) = self.server_max_window_bits {
+            write!(f, "; {}={}", SERVER_MAX_WINDOW_BITS, bits)?;
+        }
+        if let Some(bits) = self.client_max_window_bits {
+            write!(f, "; {}={}", CLIENT_MAX_WINDOW_BITS, bits)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn min_bits(a: Option<u8>, b: Option<u8>) -> Option<u8> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+type Extension = (String, Vec<(String, Option<String>)>);
+
+/// Parse all `Sec-WebSocket-Extensions` headers into a list of extensions and their parameters.
+fn parse_extensions(headers: &HeaderMap) -> Result<Vec<Extension>, ()> {
+    let mut extensions = Vec::new();
+
+    for hdr in headers.get_all(SEC_WEBSOCKET_EXTENSIONS) {
+        let hdr = hdr.to_str().map_err(|_| ())?;
+
+        for ext in hdr.split(',').map(str::trim).filter(|ext| !ext.is_empty()) {
+            let mut parts = ext.split(';').map(str::trim);
+
+            let name = match parts.next() {
+                Some(name) if !name.is_empty() => name.to_owned(),
+                _ => return Err(()),
+            };
+
+            let params = parts
+                .map(|param| {
+                    let mut kv = param.splitn(2, '=');
+                    let key = kv.next().unwrap_or("").trim();
+                    if key.is_empty() {
+                        return Err(());
+                    }
+                    let val =
+                        kv.next().map(|val| val.trim().trim_matches('"').to_owned());
+                    Ok((key.to_owned(), val))
+                })
+                .collect::<Result<_, _>>()?;
+
+            extensions.push((name, params));
+        }
+    }
+
+    Ok(extensions)
+}
+
+/// Interpret `permessage-deflate` parameters, rejecting unknown, duplicate or invalid ones.
+fn parse_params(params: &[(String, Option<String>)]) -> Option<DeflateConfig> {
+    let mut config = DeflateConfig::default();
+    let mut seen = Vec::with_capacity(params.len());
+
+    for (name, val) in params {
+        let name = name.to_ascii_lowercase();
+        if seen.contains(&name) {
+            return None;
+        }
+
+        match (name.as_str(), val) {
+            (SERVER_NO_CONTEXT_TAKEOVER, None) => {
+                config.server_no_context_takeover = true
+            }
+            (CLIENT_NO_CONTEXT_TAKEOVER, None) => {
+                config.client_no_context_takeover = true
+            }
+            (SERVER_MAX_WINDOW_BITS, Some(val)) => {
+                config.server_max_window_bits = Some(parse_window_bits(val)?)
+            }
+            (CLIENT_MAX_WINDOW_BITS, Some(val)) => {
+                config.client_max_window_bits = Some(parse_window_bits(val)?)
+            }
+            (CLIENT_MAX_WINDOW_BITS, None) => {}
+            _ => return None,
+        }
+
+        seen.push(name);
+    }
+
+    Some(config)
+}
+
+fn parse_window_bits(val: &str) -> Option<u8> {
+    match val.parse::<u8>() {
+        Ok(bits) if (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&bits) => Some(bits),
+        _ => None,
+    }
+}
+
+/// Compression state of a WebSocket connection using the `permessage-deflate` extension.
+///
+/// Cloning creates a fresh context with the same parameters.
+#[derive(Debug)]
+pub(crate) struct DeflateContext {
+    config: DeflateConfig,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl Clone for DeflateContext {
+    fn clone(&self) -> Self {
+        DeflateContext::new(self.config)
+    }
+}
+
+impl DeflateContext {
+    pub(crate) fn new(config: DeflateConfig) -> Self {
+        DeflateContext {
+            config,
+            compress: Compress::new(Compression::fast(), false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    /// Parameters constraining the compressor of the local endpoint.
+    fn own_params(&self, server: bool) -> (bool, u8) {
+        if server {
+            (
+                self.config.server_no_context_takeover,
+                self.config
+                    .server_max_window_bits
+                    .unwrap_or(MAX_WINDOW_BITS),
+            )
+        } else {
+            (
+                self.config.client_no_context_takeover,
+                self.config
+                    .client_max_window_bits
+                    .unwrap_or(MAX_WINDOW_BITS),
+            )
+        }
+    }
+
+    /// Decide whether to compress a message, given the size of its first fragment.
+    ///
+    /// A window smaller than the one of the compressor is honoured by resetting the context for
+    /// every message, so back-references never reach further than the message itself.
+    pub(crate) fn start_message(&mut self, len: usize, fin: bool, server: bool) -> bool {
+        let (_, bits) = self.own_params(server);
+
+        if bits == MAX_WINDOW_BITS {
+            return true;
+        }
+
+        if fin && len <= 1 << bits {
+            self.compress.reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Compress a message fragment.
+    pub(crate) fn compress(
+        &mut self,
+        data: &[u8],
+        fin: bool,
+        server: bool,
+    ) -> Result<Bytes, ProtocolError> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let start = self.compress.total_in();
+
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+
+            if out.capacity() - out.len() < 64 {
+                out.reserve(data.len() - consumed + 64);
+            }
+
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+            // flush is complete once all input is consumed and output space is left over
+            if (self.compress.total_in() - start) as usize == data.len()
+                && out.len() < out.capacity()
+            {
+                break;
+            }
+        }
+
+        if fin {
+            if out.ends_with(&DEFLATE_TAIL) {
+                out.truncate(out.len() - DEFLATE_TAIL.len());
+            }
+
+            // an empty message is represented by a single empty block
+            if out.is_empty() {
+                out.push(0x00);
+            }
+
+            if self.own_params(server).0 {
+                self.compress.reset();
+            }
+        }
+
+        Ok(Bytes::from(out))
+    }
+
+    /// Decompress a message fragment, failing if the output grows beyond `max_size`.
+    pub(crate) fn decompress(
+        &mut self,
+        data: &[u8],
+        fin: bool,
+        server: bool,
+        max_size: usize,
+    ) -> Result<BytesMut, ProtocolError> {
+        let mut out = BytesMut::new();
+        let mut buf =
+            Vec::with_capacity((data.len() * 2).max(64).min(max_size.saturating_add(1)));
+
+        self.inflate(data, &mut buf, &mut out, max_size)?;
+        if fin {
+            self.inflate(&DEFLATE_TAIL, &mut buf, &mut out, max_size)?;
+
+            // peer's context is discarded after every message
+            let peer_no_context_takeover = if server {
+                self.config.client_no_context_takeover
+            } else {
+                self.config.server_no_context_takeover
+            };
+
+            if peer_no_context_takeover {
+                self.decompress.reset(false);
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn inflate(
+        &mut self,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+        out: &mut BytesMut,
+        max_size: usize,
+    ) -> Result<(), ProtocolError> {
+        let start = self.decompress.total_in();
+
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+
+            buf.clear();
+            let status = self
+                .decompress
+                .decompress_vec(&data[consumed..], buf, FlushDecompress::Sync)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            out.extend_from_slice(buf);
+            if out.len() > max_size {
+                return Err(ProtocolError::Overflow);
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let done = consumed == data.len() && buf.len() < buf.capacity();
+
+            if done || status == Status::StreamEnd {
+                return Ok(());
+            }
+
+            // no progress can be made with the remaining input
+            if buf.is_empty() && status == Status::BufError {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::HeaderName;
+
+    fn headers(vals: &[&'static str]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for val in vals {
+            map.append(
+                HeaderName::from_static("sec-websocket-extensions"),
+                HeaderValue::from_static(val),
+            );
+        }
+        map
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let config = DeflateConfig::new();
+
+        assert_eq!(config.negotiate(&headers(&[])), None);
+        assert_eq!(
+            config.negotiate(&headers(&["x-webkit-deflate-frame"])),
+            None
+        );
+        assert_eq!(
+            config.negotiate(&headers(&["permessage-deflate"])),
+            Some(DeflateConfig::new())
+        );
+
+        let agreed = config
+            .negotiate(&headers(&[
+                "permessage-deflate; unknown_param",
+                "permessage-deflate; server_max_window_bits=10; client_max_window_bits",
+            ]))
+            .unwrap();
+        assert_eq!(agreed, DeflateConfig::new().server_max_window_bits(10));
+        assert_eq!(
+            agreed.to_header_value(),
+            "permessage-deflate; server_max_window_bits=10"
+        );
+
+        // invalid window bits and duplicate params are declined
+        assert_eq!(
+            config
+                .negotiate(&headers(&["permessage-deflate; server_max_window_bits=16"])),
+            None
+        );
+        assert_eq!(
+            config.negotiate(&headers(&[
+                "permessage-deflate; server_no_context_takeover; server_no_context_takeover"
+            ])),
+            None
+        );
+
+        let config = DeflateConfig::new()
+            .client_no_context_takeover(true)
+            .client_max_window_bits(9);
+        let agreed = config
+            .negotiate(&headers(&[
+                "permessage-deflate; client_max_window_bits=\"12\"; server_no_context_takeover",
+            ]))
+            .unwrap();
+        assert_eq!(
+            agreed.to_header_value(),
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover; \
+             client_max_window_bits=9"
+        );
+
+        // the client's window can not be limited unless it offered to honour the limit
+        let agreed = config.negotiate(&headers(&["permessage-deflate"])).unwrap();
+        assert_eq!(
+            agreed.to_header_value(),
+            "permessage-deflate; client_no_context_takeover"
+        );
+    }
+
+    #[test]
+    fn test_accept_response() {
+        let config = DeflateConfig::new().server_max_window_bits(12);
+        assert_eq!(
+            config.offer(),
+            "permessage-deflate; server_max_window_bits=12; client_max_window_bits"
+        );
+
+        assert_eq!(config.accept_response(&headers(&[])).unwrap(), None);
+        assert_eq!(
+            config
+                .accept_response(&headers(&[
+                    "permessage-deflate; server_max_window_bits=10; client_max_window_bits=9"
+                ]))
+                .unwrap(),
+            Some(
+                DeflateConfig::new()
+                    .server_max_window_bits(10)
+                    .client_max_window_bits(9)
+            )
+        );
+
+        assert!(config
+            .accept_response(&headers(&["permessage-deflate"]))
+            .is_err());
+        assert!(config
+            .accept_response(&headers(&[
+                "permessage-deflate; server_max_window_bits=13"
+            ]))
+            .is_err());
+        assert!(config
+            .accept_response(&headers(&[
+                "permessage-deflate; server_max_window_bits=12, x-other"
+            ]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let mut server = DeflateContext::new(DeflateConfig::new());
+        let mut client = DeflateContext::new(DeflateConfig::new());
+
+        let msg = b"{\"event\":\"update\",\"value\":\"hello hello hello\"}";
+        assert!(server.start_message(msg.len(), true, true));
+        let first = server.compress(msg, true, true).unwrap();
+        assert!(first.len() < msg.len());
+        assert_eq!(
+            client.decompress(&first, true, false, 1024).unwrap(),
+            &msg[..]
+        );
+
+        // context takeover makes repeated messages smaller
+        let second = server.compress(msg, true, true).unwrap();
+        assert!(second.len() < first.len());
+        assert_eq!(
+            client.decompress(&second, true, false, 1024).unwrap(),
+            &msg[..]
+        );
+
+        // empty message
+        let empty = server.compress(b"", true, true).unwrap();
+        assert_eq!(&empty[..], &[0x00]);
+        assert!(client
+            .decompress(&empty, true, false, 1024)
+            .unwrap()
+            .is_empty());
+
+        // decompressed size is limited
+        let big = vec![b'a'; 2048];
+        let compressed = server.compress(&big, true, true).unwrap();
+        assert!(matches!(
+            client.decompress(&compressed, true, false, 1024),
+            Err(ProtocolError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_small_window() {
+        let config = DeflateConfig::new().server_max_window_bits(8);
+        let mut server = DeflateContext::new(config);
+        let mut client = DeflateContext::new(config);
+
+        assert!(server.start_message(256, true, true));
+        assert!(!server.start_message(257, true, true));
+        assert!(!server.start_message(10, false, true));
+
+        // client window is not limited
+        assert!(client.start_message(4096, false, false));
+
+        let msg = vec![b'x'; 200];
+        for _ in 0..2 {
+            assert!(server.start_message(msg.len(), true, true));
+            let compressed = server.compress(&msg, true, true).unwrap();
+            assert_eq!(
+                client.decompress(&compressed, true, false, 1024).unwrap(),
+                &msg[..]
+            );
+        }
+    }
+}
@@ -16,12 +16,16 @@ use crate::{
 };
 
 mod codec;
+#[cfg(feature = "compress")]
+mod deflate;
 mod dispatcher;
 mod frame;
 mod mask;
 mod proto;
 
 pub use self::codec::{Codec, Frame, Item, Message};
+#[cfg(feature = "compress")]
+pub use self::deflate::DeflateConfig;
 pub use self::dispatcher::Dispatcher;
 pub use self::frame::Parser;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
@@ -65,6 +69,14 @@ pub enum ProtocolError {
     #[display(fmt = "Unknown continuation fragment: {}.", _0)]
     ContinuationFragment(#[error(not(source))] OpCode),
 
+    /// Received a compressed frame where compression is not allowed.
+    #[display(fmt = "Received a compressed frame where compression is not allowed.")]
+    UnexpectedCompressedFrame,
+
+    /// Invalid WebSocket extension negotiation.
+    #[display(fmt = "Invalid WebSocket extension negotiation.")]
+    InvalidExtension,
+
     /// I/O error.
     #[display(fmt = "I/O error: {}", _0)]
     Io(io::Error),
@@ -204,6 +216,38 @@ pub fn handshake_response(req: &RequestHead) -> ResponseBuilder {
         .take()
 }
 
+/// Verify WebSocket handshake request and create handshake response, negotiating the
+/// `permessage-deflate` extension.
+///
+/// Agreed extension parameters, if any, should be passed to [`Codec::deflate`].
+#[cfg(feature = "compress")]
+pub fn handshake_with_deflate(
+    req: &RequestHead,
+    config: &DeflateConfig,
+) -> Result<(ResponseBuilder, Option<DeflateConfig>), HandshakeError> {
+    verify_handshake(req)?;
+    Ok(handshake_response_with_deflate(req, config))
+}
+
+/// Create WebSocket handshake response, negotiating the `permessage-deflate` extension.
+///
+/// The extension is accepted with the first offer of the client that is compatible with
+/// `config`. Agreed parameters, if any, should be passed to [`Codec::deflate`].
+#[cfg(feature = "compress")]
+pub fn handshake_response_with_deflate(
+    req: &RequestHead,
+    config: &DeflateConfig,
+) -> (ResponseBuilder, Option<DeflateConfig>) {
+    let mut res = handshake_response(req);
+    let deflate = config.negotiate(req.headers());
+
+    if let Some(ref deflate) = deflate {
+        res.insert_header((header::SEC_WEBSOCKET_EXTENSIONS, deflate.to_header_value()));
+    }
+
+    (res, deflate)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +203,47 @@ async fn test_http1_malformed_request() {
     assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
 }
 
+#[actix_rt::test]
+async fn test_http1_head_limits() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1_max_headers(4)
+            .h1_max_head_size(1024)
+            .h1_max_uri_length(16)
+            .h1(|_| future::ok::<_, ()>(Response::Ok().finish()))
+            .tcp()
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\nd: 4\r\ne: 5\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        format!("GET / HTTP/1.1\r\na: {}\r\n\r\n", "x".repeat(2048)).as_bytes(),
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/with/a/long/path HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 414 URI Too Long"));
+}
+
 #[actix_rt::test]
 async fn test_http1_keepalive() {
     let srv = test_server(|| {
@@ -676,3 +717,253 @@ async fn test_h1_on_connect() {
     let response = srv.get("/").send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[actix_rt::test]
+async fn test_h2_graceful_shutdown() {
+    let signal = actix_http::ShutdownSignal::new();
+
+    let srv = test_server({
+        let signal = signal.clone();
+        move || {
+            HttpService::build()
+                .shutdown_signal(signal.clone())
+                .h2(|_| async {
+                    sleep(Duration::from_millis(200)).await;
+                    Ok::<_, Error>(Response::Ok().body("done"))
+                })
+                .tcp()
+        }
+    })
+    .await;
+
+    let tcp = actix_rt::net::TcpStream::connect(srv.addr()).await.unwrap();
+    let (mut client, conn) = h2::client::handshake(tcp).await.unwrap();
+    let (tx, closed) = tokio::sync::oneshot::channel();
+    actix_rt::spawn(async move {
+        let _ = tx.send(conn.await);
+    });
+
+    let req = ::http::Request::get("/").body(()).unwrap();
+    let (res, _) = client.send_request(req, true).unwrap();
+
+    sleep(Duration::from_millis(50)).await;
+    signal.shutdown();
+    assert!(signal.is_shutdown());
+
+    // in-flight stream is finished
+    let res = res.await.unwrap();
+    assert!(res.status().is_success());
+    let mut body = res.into_body();
+    let chunk = body.data().await.unwrap().unwrap();
+    assert_eq!(chunk, Bytes::from_static(b"done"));
+
+    // connection is closed once streams are complete
+    actix_rt::time::timeout(Duration::from_secs(2), closed)
+        .await
+        .expect("connection was not closed")
+        .unwrap()
+        .unwrap();
+}
+
+#[actix_rt::test]
+async fn test_h2_ping_timeout() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h2_ping_interval(100)
+            .h2_ping_timeout(100)
+            .h2(|_| ok::<_, Error>(Response::Ok().finish()))
+            .tcp()
+    })
+    .await;
+
+    // peer that acknowledges PINGs keeps the connection open
+    let tcp = actix_rt::net::TcpStream::connect(srv.addr()).await.unwrap();
+    let (mut client, conn) = h2::client::handshake(tcp).await.unwrap();
+    actix_rt::spawn(async move {
+        let _ = conn.await;
+    });
+
+    sleep(Duration::from_millis(500)).await;
+    let req = ::http::Request::get("/").body(()).unwrap();
+    let (res, _) = client.send_request(req, true).unwrap();
+    assert!(res.await.unwrap().status().is_success());
+
+    // peer that never reads is disconnected
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    stream
+        .write_all(
+            b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00\x00\x04\x00\x00\x00\x00\x00",
+        )
+        .unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_h2_settings() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let active = Arc::new(AtomicUsize::new(0));
+    let max_active = Arc::new(AtomicUsize::new(0));
+
+    let srv = test_server({
+        let active = active.clone();
+        let max_active = max_active.clone();
+        move || {
+            let active = active.clone();
+            let max_active = max_active.clone();
+            HttpService::build()
+                .h2_max_concurrent_streams(1)
+                .h2_initial_window_size(1024 * 1024)
+                .h2_initial_connection_window_size(4 * 1024 * 1024)
+                .h2_max_frame_size(32 * 1024)
+                .h2_max_header_list_size(8 * 1024)
+                .h2(move |_| {
+                    let active = active.clone();
+                    let max_active = max_active.clone();
+                    async move {
+                        let n = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_active.fetch_max(n, Ordering::SeqCst);
+                        sleep(Duration::from_millis(50)).await;
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, Error>(Response::Ok().finish())
+                    }
+                })
+                .tcp()
+        }
+    })
+    .await;
+
+    let tcp = actix_rt::net::TcpStream::connect(srv.addr()).await.unwrap();
+    let (client, conn) = h2::client::handshake(tcp).await.unwrap();
+    actix_rt::spawn(async move {
+        let _ = conn.await;
+    });
+
+    // first request makes sure server settings are received
+    let mut client = client.ready().await.unwrap();
+    let req = ::http::Request::get("/").body(()).unwrap();
+    let (res, _) = client.send_request(req, true).unwrap();
+    assert!(res.await.unwrap().status().is_success());
+
+    let mut responses = Vec::new();
+    for _ in 0..3 {
+        let mut client = client.clone().ready().await.unwrap();
+        let req = ::http::Request::get("/").body(()).unwrap();
+        let (res, _) = client.send_request(req, true).unwrap();
+        responses.push(res);
+    }
+
+    for res in future::join_all(responses).await {
+        assert!(res.unwrap().status().is_success());
+    }
+    assert_eq!(max_active.load(Ordering::SeqCst), 1);
+
+    // advertised header list size is enforced by the client
+    let mut client = client.ready().await.unwrap();
+    let req = ::http::Request::get("/")
+        .header("x-big", "a".repeat(16 * 1024))
+        .body(())
+        .unwrap();
+    assert!(client.send_request(req, true).is_err());
+}
+
+struct TrailersBody {
+    data: Option<Bytes>,
+    trailers: Option<header::HeaderMap>,
+}
+
+impl TrailersBody {
+    fn new(data: &'static str, status: &'static str) -> Self {
+        let mut trailers = header::HeaderMap::new();
+        trailers.insert(
+            header::HeaderName::from_static("grpc-status"),
+            header::HeaderValue::from_static(status),
+        );
+        TrailersBody {
+            data: Some(Bytes::from_static(data.as_bytes())),
+            trailers: Some(trailers),
+        }
+    }
+}
+
+impl body::MessageBody for TrailersBody {
+    fn size(&self) -> body::BodySize {
+        body::BodySize::Stream
+    }
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<Bytes, Error>>> {
+        std::task::Poll::Ready(self.get_mut().data.take().map(Ok))
+    }
+
+    fn take_trailers(self: std::pin::Pin<&mut Self>) -> Option<header::HeaderMap> {
+        self.get_mut().trailers.take()
+    }
+}
+
+/// Echo request body and `grpc-status` request trailer, responding with trailers.
+async fn echo_trailers(mut req: Request) -> Result<Response<TrailersBody>, Error> {
+    let mut pl = req.take_payload();
+    let mut body = Vec::new();
+    while let Some(chunk) = pl.next().await {
+        body.extend_from_slice(&chunk?);
+    }
+    assert_eq!(body, b"data");
+
+    let trailers = req.head().trailers().unwrap();
+    assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+
+    Ok(Response::Ok().message_body(TrailersBody::new("done", "0")))
+}
+
+#[actix_rt::test]
+async fn test_h1_trailers() {
+    let srv = test_server(|| HttpService::build().h1(echo_trailers).tcp()).await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nconnection: close\r\ntransfer-encoding: chunked\r\n\r\n\
+          4\r\ndata\r\n0\r\ngrpc-status: 0\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK"));
+    assert!(data.ends_with("4\r\ndone\r\n0\r\ngrpc-status: 0\r\n\r\n"));
+}
+
+#[actix_rt::test]
+async fn test_h2_trailers() {
+    let srv = test_server(|| HttpService::build().h2(echo_trailers).tcp()).await;
+
+    let tcp = actix_rt::net::TcpStream::connect(srv.addr()).await.unwrap();
+    let (client, conn) = h2::client::handshake(tcp).await.unwrap();
+    actix_rt::spawn(async move {
+        let _ = conn.await;
+    });
+
+    let mut client = client.ready().await.unwrap();
+    let req = ::http::Request::post("/").body(()).unwrap();
+    let (res, mut send) = client.send_request(req, false).unwrap();
+    send.send_data(Bytes::from_static(b"data"), false).unwrap();
+    let mut trailers = ::http::HeaderMap::new();
+    trailers.insert("grpc-status", ::http::HeaderValue::from_static("0"));
+    send.send_trailers(trailers).unwrap();
+
+    let res = res.await.unwrap();
+    assert!(res.status().is_success());
+    let mut body = res.into_body();
+    let chunk = body.data().await.unwrap().unwrap();
+    assert_eq!(chunk, Bytes::from_static(b"done"));
+    assert!(body.data().await.is_none());
+    let trailers = body.trailers().await.unwrap().unwrap();
+    assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+}
@@ -15,6 +15,8 @@ use actix::{
     SpawnHandle,
 };
 use actix_codec::{Decoder, Encoder};
+#[cfg(feature = "compress")]
+pub use actix_http::ws::DeflateConfig;
 pub use actix_http::ws::{
     CloseCode, CloseReason, Frame, HandshakeError, Message, ProtocolError,
 };
@@ -83,6 +85,31 @@ where
     Ok(res.streaming(WebsocketContext::create(actor, stream)))
 }
 
+/// Do WebSocket handshake, negotiating the `permessage-deflate` extension, and start ws actor.
+///
+/// If the client offers an extension compatible with `config`, Text and Binary messages are
+/// compressed with the agreed parameters.
+#[cfg(feature = "compress")]
+pub fn start_with_deflate<A, T>(
+    actor: A,
+    config: &DeflateConfig,
+    req: &HttpRequest,
+    stream: T,
+) -> Result<HttpResponse, Error>
+where
+    A: Actor<Context = WebsocketContext<A>> + StreamHandler<Result<Message, ProtocolError>>,
+    T: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+{
+    let (mut res, deflate) = handshake_with_deflate(req, config)?;
+
+    let codec = match deflate {
+        Some(deflate) => Codec::new().deflate(deflate),
+        None => Codec::new(),
+    };
+
+    Ok(res.streaming(WebsocketContext::with_codec(actor, stream, codec)))
+}
+
 /// Prepare WebSocket handshake response.
 ///
 /// This function returns handshake `HttpResponse`, ready to send to peer.
@@ -179,6 +206,26 @@ pub fn handshake_with_protocols(
     Ok(response)
 }
 
+/// Prepare WebSocket handshake response, negotiating the `permessage-deflate` extension.
+///
+/// This function returns handshake `HttpResponse`, ready to send to peer, and the agreed
+/// extension parameters, if any, which should be passed to [`Codec::deflate`].
+/// It does not perform any IO.
+#[cfg(feature = "compress")]
+pub fn handshake_with_deflate(
+    req: &HttpRequest,
+    config: &DeflateConfig,
+) -> Result<(HttpResponseBuilder, Option<DeflateConfig>), HandshakeError> {
+    let mut res = handshake(req)?;
+    let deflate = config.negotiate(req.headers());
+
+    if let Some(ref deflate) = deflate {
+        res.insert_header((header::SEC_WEBSOCKET_EXTENSIONS, deflate.to_header_value()));
+    }
+
+    Ok((res, deflate))
+}
+
 /// Execution context for `WebSockets` actors
 pub struct WebsocketContext<A>
 where
@@ -298,7 +345,7 @@ where
             inner: ContextParts::new(mb.sender_producer()),
             messages: VecDeque::new(),
         };
-        ctx.add_stream(WsStream::new(stream, codec));
+        ctx.add_stream(WsStream::new(stream, codec.clone()));
 
         WebsocketContextFut::new(ctx, actor, mb, codec)
     }
@@ -56,3 +56,47 @@ async fn test_simple() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
+
+#[cfg(feature = "compress")]
+#[actix_rt::test]
+async fn test_deflate() {
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| async move {
+                let config = ws::DeflateConfig::new().server_no_context_takeover(true);
+                ws::start_with_deflate(Ws, &config, &req, stream)
+            },
+        ))
+    });
+
+    let (res, mut framed) = awc::Client::new()
+        .ws(srv.url("/"))
+        .deflate(ws::DeflateConfig::new())
+        .connect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        res.headers()
+            .get(actix_web::http::header::SEC_WEBSOCKET_EXTENSIONS)
+            .unwrap(),
+        "permessage-deflate; server_no_context_takeover"
+    );
+
+    let text = "{\"event\":\"update\"}".repeat(40);
+    for _ in 0..3 {
+        framed
+            .send(ws::Message::Text(text.clone().into()))
+            .await
+            .unwrap();
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from(text.clone())));
+    }
+
+    framed
+        .send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
+}
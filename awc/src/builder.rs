@@ -6,13 +6,15 @@ use std::time::Duration;
 
 use actix_codec::{AsyncRead, AsyncWrite};
 use actix_http::{
-    client::{Connector, TcpConnect, TcpConnectError, TcpConnection},
+    client::{Connector, Proxy, TcpConnect, TcpConnectError, TcpConnection},
     http::{self, header, Error as HttpError, HeaderMap, HeaderName, Uri},
 };
 use actix_rt::net::TcpStream;
 use actix_service::{boxed, Service};
 
 use crate::connect::DefaultConnector;
+#[cfg(feature = "cookies")]
+use crate::cookie_store::{CookieStore, CookieStoreService};
 use crate::error::SendRequestError;
 use crate::middleware::{NestTransform, Transform};
 use crate::{Client, ClientConfig, ConnectRequest, ConnectResponse, ConnectorService};
@@ -31,6 +33,9 @@ pub struct ClientBuilder<S = (), Io = (), M = ()> {
     connector: Connector<S, Io>,
     middleware: M,
     local_address: Option<IpAddr>,
+    proxies: Vec<Proxy>,
+    #[cfg(feature = "cookies")]
+    cookie_store: Option<CookieStore>,
 }
 
 impl ClientBuilder {
@@ -50,6 +55,9 @@ impl ClientBuilder {
             headers: HeaderMap::new(),
             timeout: Some(Duration::from_secs(5)),
             local_address: None,
+            proxies: Vec::new(),
+            #[cfg(feature = "cookies")]
+            cookie_store: None,
             connector: Connector::new(),
             max_http_version: None,
             stream_window_size: None,
@@ -82,6 +90,9 @@ where
             headers: self.headers,
             timeout: self.timeout,
             local_address: self.local_address,
+            proxies: self.proxies,
+            #[cfg(feature = "cookies")]
+            cookie_store: self.cookie_store,
             connector,
             max_http_version: self.max_http_version,
             stream_window_size: self.stream_window_size,
@@ -110,6 +121,32 @@ where
         self
     }
 
+    /// Route connections through a proxy.
+    ///
+    /// Can be called multiple times; each connection uses the first proxy that applies to its
+    /// host.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Route connections through the proxies configured by the `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `NO_PROXY` environment variables.
+    pub fn proxy_from_env(mut self) -> Self {
+        self.proxies.extend(Proxy::from_env());
+        self
+    }
+
+    /// Store cookies set by responses and send them with later requests.
+    ///
+    /// See [`CookieStore`] for details. Clones of `store` can be used to inspect or persist the
+    /// cookies while the client is in use.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_store(mut self, store: CookieStore) -> Self {
+        self.cookie_store = Some(store);
+        self
+    }
+
     /// Maximum supported HTTP major version.
     ///
     /// Supported versions are HTTP/1.1 and HTTP/2.
@@ -209,6 +246,9 @@ where
             timeout: self.timeout,
             connector: self.connector,
             local_address: self.local_address,
+            proxies: self.proxies,
+            #[cfg(feature = "cookies")]
+            cookie_store: self.cookie_store,
         }
     }
 
@@ -233,14 +273,27 @@ where
         if let Some(val) = self.local_address {
             connector = connector.local_address(val);
         }
+        for proxy in self.proxies {
+            connector = connector.proxy(proxy);
+        }
 
+        let pool_stats = connector.pool_stats();
         let connector = boxed::service(DefaultConnector::new(connector.finish()));
+
+        // inside of the middleware, so that every redirect and retry sees the stored cookies
+        #[cfg(feature = "cookies")]
+        let connector = match self.cookie_store {
+            Some(store) => boxed::service(CookieStoreService::new(store, connector)),
+            None => connector,
+        };
+
         let connector = boxed::service(self.middleware.new_transform(connector));
 
         let config = ClientConfig {
             headers: self.headers,
             timeout: self.timeout,
             connector,
+            pool_stats,
         };
 
         Client(Rc::new(config))
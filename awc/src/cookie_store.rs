@@ -0,0 +1,696 @@
+//! Persistent cookie store for [`Client`](crate::Client).
+
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::HashSet,
+    fmt, io,
+    net::IpAddr,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_http::{
+    cookie::Cookie,
+    http::{
+        header::{HeaderValue, COOKIE, SET_COOKIE},
+        HeaderMap, Uri,
+    },
+    RequestHeadType,
+};
+use actix_service::Service;
+use futures_core::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::connect::{ConnectRequest, ConnectResponse};
+use crate::error::SendRequestError;
+
+/// A cookie store shared by the requests of a [`Client`](crate::Client).
+///
+/// When registered with [`ClientBuilder::cookie_store`](crate::ClientBuilder::cookie_store), the
+/// store captures the `Set-Cookie` headers of every response, including intermediate responses
+/// of followed redirects, and adds the matching cookies to the `Cookie` header of every request.
+/// Cookies set explicitly with [`ClientRequest::cookie`](crate::ClientRequest::cookie) take
+/// precedence over stored cookies with the same name.
+///
+/// The `Domain`, `Path`, `Max-Age`, `Expires` and `Secure` attributes are handled as described in
+/// [RFC 6265](https://tools.ietf.org/html/rfc6265). Secure cookies are only accepted from and sent
+/// to `https` and `wss` URLs. A `Domain` attribute naming a top-level domain or a common public
+/// suffix such as `co.uk` is rejected, unless it is the request host itself, in which case the
+/// cookie is only sent back to that host.
+///
+/// `CookieStore` is a cheap handle; clones share the same cookies. Use
+/// [`save_json`](Self::save_json) and [`load_json`](Self::load_json) to persist the store between
+/// runs.
+///
+/// # Examples
+/// ```rust
+/// use awc::{ClientBuilder, CookieStore};
+///
+/// let store = CookieStore::new();
+/// let client = ClientBuilder::new().cookie_store(store.clone()).finish();
+///
+/// // later, e.g. on shutdown
+/// let mut buf = Vec::new();
+/// store.save_json(&mut buf).unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct CookieStore(Rc<RefCell<Vec<StoredCookie>>>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredCookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    /// Expiry time in seconds since the Unix epoch. Session cookies have none.
+    expires: Option<i64>,
+    secure: bool,
+    http_only: bool,
+}
+
+impl CookieStore {
+    /// Constructs an empty cookie store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a cookie as if it was received in a `Set-Cookie` header of a response from `uri`.
+    ///
+    /// Returns `false` if the cookie was rejected, e.g. because its domain does not match `uri`.
+    pub fn insert(&self, cookie: &Cookie<'_>, uri: &Uri) -> bool {
+        self.0.borrow_mut().add(cookie, uri, now())
+    }
+
+    /// Returns all unexpired cookies in the store.
+    pub fn cookies(&self) -> Vec<Cookie<'static>> {
+        let now = now();
+
+        self.0
+            .borrow()
+            .iter()
+            .filter(|c| !c.is_expired(now))
+            .map(StoredCookie::to_cookie)
+            .collect()
+    }
+
+    /// Returns the unexpired cookies that would be sent with a request to `uri`.
+    pub fn cookies_for(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        self.0
+            .borrow()
+            .matches(uri, now())
+            .into_iter()
+            .map(StoredCookie::to_cookie)
+            .collect()
+    }
+
+    /// Removes all cookies from the store.
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    /// Writes the unexpired persistent cookies of the store to `writer` as JSON.
+    ///
+    /// Session cookies, which have neither `Max-Age` nor `Expires`, are not written.
+    pub fn save_json<W: io::Write>(&self, writer: W) -> serde_json::Result<()> {
+        let now = now();
+        let cookies = self.0.borrow();
+
+        let persistent = cookies
+            .iter()
+            .filter(|c| c.expires.is_some() && !c.is_expired(now))
+            .collect::<Vec<_>>();
+
+        serde_json::to_writer(writer, &persistent)
+    }
+
+    /// Reads a cookie store written by [`save_json`](Self::save_json). Expired cookies are
+    /// dropped.
+    pub fn load_json<R: io::Read>(reader: R) -> serde_json::Result<Self> {
+        let now = now();
+
+        let mut cookies: Vec<StoredCookie> = serde_json::from_reader(reader)?;
+        cookies.retain(|c| !c.is_expired(now));
+
+        Ok(CookieStore(Rc::new(RefCell::new(cookies))))
+    }
+
+    /// Adds the cookies matching the request URL to the `Cookie` header of the request.
+    fn apply(&self, headers: &HeaderMap, uri: &Uri) -> Option<HeaderValue> {
+        let cookies = self.0.borrow();
+        let matches = cookies.matches(uri, now());
+
+        if matches.is_empty() {
+            return None;
+        }
+
+        // cookies set on the request win over stored cookies of the same name
+        let existing = headers
+            .get(COOKIE)
+            .and_then(|val| val.to_str().ok())
+            .unwrap_or("");
+
+        let names = existing
+            .split(';')
+            .filter_map(|pair| pair.split('=').next())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect::<HashSet<_>>();
+
+        let mut cookie = existing.trim().to_owned();
+
+        for c in matches
+            .into_iter()
+            .filter(|c| !names.contains(c.name.as_str()))
+        {
+            if !cookie.is_empty() {
+                cookie.push_str("; ");
+            }
+            cookie.push_str(
+                &Cookie::new(c.name.as_str(), c.value.as_str())
+                    .encoded()
+                    .to_string(),
+            );
+        }
+
+        HeaderValue::from_str(&cookie).ok()
+    }
+
+    /// Stores the cookies of the `Set-Cookie` headers of a response from `uri`.
+    fn store(&self, headers: &HeaderMap, uri: &Uri) {
+        let now = now();
+        let mut cookies = self.0.borrow_mut();
+
+        for cookie in headers
+            .get_all(SET_COOKIE)
+            .filter_map(|val| val.to_str().ok())
+            .filter_map(|val| Cookie::parse_encoded(val).ok())
+        {
+            cookies.add(&cookie, uri, now);
+        }
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.borrow().iter()).finish()
+    }
+}
+
+/// Cookie handling of the list of stored cookies.
+trait Cookies {
+    fn add(&mut self, cookie: &Cookie<'_>, uri: &Uri, now: i64) -> bool;
+
+    fn matches(&self, uri: &Uri, now: i64) -> Vec<&StoredCookie>;
+}
+
+impl Cookies for Vec<StoredCookie> {
+    fn add(&mut self, cookie: &Cookie<'_>, uri: &Uri, now: i64) -> bool {
+        let host = match uri.host() {
+            Some(host) => host
+                .trim_matches(|c| c == '[' || c == ']')
+                .to_ascii_lowercase(),
+            None => return false,
+        };
+
+        let (domain, host_only) = match cookie.domain().map(|d| d.trim_start_matches('.')) {
+            Some(domain) if !domain.is_empty() => {
+                let domain = domain.to_ascii_lowercase();
+
+                if !domain_match(&host, &domain) {
+                    return false;
+                }
+
+                // RFC 6265, section 5.3, step 5
+                if is_public_suffix(&domain) {
+                    if domain != host {
+                        return false;
+                    }
+
+                    (host, true)
+                } else {
+                    (domain, false)
+                }
+            }
+            _ => (host, true),
+        };
+
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_owned(),
+            _ => default_path(uri.path()),
+        };
+
+        let secure = cookie.secure().unwrap_or(false);
+
+        if secure && !is_secure(uri) {
+            return false;
+        }
+
+        // Max-Age takes precedence over Expires
+        let expires = match cookie.max_age() {
+            Some(age) => Some(now.saturating_add(age.whole_seconds())),
+            None => cookie.expires().map(|expires| expires.unix_timestamp()),
+        };
+
+        // replace the cookie with the same name, domain and path and purge expired cookies
+        self.retain(|c| {
+            !c.is_expired(now)
+                && (c.name != cookie.name() || c.domain != domain || c.path != path)
+        });
+
+        // cookies that are already expired only remove the stored cookie
+        let cookie = StoredCookie {
+            name: cookie.name().to_owned(),
+            value: cookie.value().to_owned(),
+            domain,
+            host_only,
+            path,
+            expires,
+            secure,
+            http_only: cookie.http_only().unwrap_or(false),
+        };
+
+        if !cookie.is_expired(now) {
+            self.push(cookie);
+        }
+
+        true
+    }
+
+    fn matches(&self, uri: &Uri, now: i64) -> Vec<&StoredCookie> {
+        let host = match uri.host() {
+            Some(host) => host
+                .trim_matches(|c| c == '[' || c == ']')
+                .to_ascii_lowercase(),
+            None => return Vec::new(),
+        };
+        let secure = is_secure(uri);
+
+        let mut matches = self
+            .iter()
+            .filter(|c| !c.is_expired(now))
+            .filter(|c| !c.secure || secure)
+            .filter(|c| {
+                if c.host_only {
+                    c.domain == host
+                } else {
+                    domain_match(&host, &c.domain)
+                }
+            })
+            .filter(|c| path_match(uri.path(), &c.path))
+            .collect::<Vec<_>>();
+
+        // cookies with longer paths are listed first; the sort is stable
+        matches.sort_by_key(|c| Reverse(c.path.len()));
+
+        matches
+    }
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
+    fn to_cookie(&self) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), self.value.clone());
+        cookie.set_domain(self.domain.clone());
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        cookie
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn is_secure(uri: &Uri) -> bool {
+    matches!(uri.scheme_str(), Some("https") | Some("wss"))
+}
+
+/// Returns `true` if `domain` is a registry controlled suffix that cookies must not be scoped to.
+///
+/// Without a copy of the Public Suffix List this covers top-level domains and the common
+/// `<registry>.<country code>` second-level domains, such as `co.uk` or `com.au`.
+fn is_public_suffix(domain: &str) -> bool {
+    const REGISTRIES: &[&str] = &[
+        "ac", "co", "com", "edu", "go", "gob", "gov", "gouv", "mil", "ne", "net", "or", "org",
+    ];
+
+    let mut labels = domain.rsplit('.');
+    let tld = labels.next().unwrap_or_default();
+
+    match (labels.next(), labels.next()) {
+        (None, _) => true,
+        (Some(sld), None) => {
+            tld.len() == 2
+                && tld.bytes().all(|b| b.is_ascii_alphabetic())
+                && REGISTRIES.contains(&sld)
+        }
+        _ => false,
+    }
+}
+
+/// Domain matching as described in RFC 6265, section 5.1.3.
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+
+    host.len() > domain.len()
+        && host.ends_with(domain)
+        && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+        && host.parse::<IpAddr>().is_err()
+}
+
+/// Path matching as described in RFC 6265, section 5.1.4.
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    let path = if path.is_empty() { "/" } else { path };
+
+    path == cookie_path
+        || path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/'))
+}
+
+/// Default cookie path as described in RFC 6265, section 5.1.4.
+fn default_path(path: &str) -> String {
+    if !path.starts_with('/') {
+        return "/".to_owned();
+    }
+
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(idx) => path[..idx].to_owned(),
+    }
+}
+
+/// Service that adds stored cookies to requests and stores cookies of responses.
+pub(crate) struct CookieStoreService<S> {
+    store: CookieStore,
+    connector: Rc<S>,
+}
+
+impl<S> CookieStoreService<S> {
+    pub(crate) fn new(store: CookieStore, connector: S) -> Self {
+        Self {
+            store,
+            connector: Rc::new(connector),
+        }
+    }
+}
+
+impl<S> Service<ConnectRequest> for CookieStoreService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(connector);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let req = match req {
+            ConnectRequest::Client(head, body, addr) => {
+                let head = match head {
+                    RequestHeadType::Owned(mut head) => {
+                        if let Some(val) = self.store.apply(&head.headers, &head.uri) {
+                            head.headers.insert(COOKIE, val);
+                        }
+                        RequestHeadType::Owned(head)
+                    }
+                    RequestHeadType::Rc(head, mut extra_headers) => {
+                        // extra headers override the headers of the shared head
+                        let val = match extra_headers {
+                            Some(ref headers) if headers.contains_key(COOKIE) => {
+                                self.store.apply(headers, &head.uri)
+                            }
+                            _ => self.store.apply(&head.headers, &head.uri),
+                        };
+
+                        if let Some(val) = val {
+                            extra_headers
+                                .get_or_insert_with(HeaderMap::new)
+                                .insert(COOKIE, val);
+                        }
+                        RequestHeadType::Rc(head, extra_headers)
+                    }
+                };
+
+                ConnectRequest::Client(head, body, addr)
+            }
+            ConnectRequest::Tunnel(mut head, addr) => {
+                if let Some(val) = self.store.apply(&head.headers, &head.uri) {
+                    head.headers.insert(COOKIE, val);
+                }
+                ConnectRequest::Tunnel(head, addr)
+            }
+        };
+
+        let uri = match req {
+            ConnectRequest::Client(ref head, ..) => head.as_ref().uri.clone(),
+            ConnectRequest::Tunnel(ref head, ..) => head.uri.clone(),
+        };
+
+        let store = self.store.clone();
+        let connector = self.connector.clone();
+
+        Box::pin(async move {
+            let res = connector.call(req).await?;
+
+            match res {
+                ConnectResponse::Client(ref res) => store.store(res.headers(), &uri),
+                ConnectResponse::Tunnel(ref head, _) => store.store(&head.headers, &uri),
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    fn insert(store: &CookieStore, cookie: &str, url: &str) -> bool {
+        store.insert(&Cookie::parse(cookie).unwrap(), &uri(url))
+    }
+
+    fn names(store: &CookieStore, url: &str) -> Vec<String> {
+        store
+            .cookies_for(&uri(url))
+            .iter()
+            .map(|c| c.name().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_domain() {
+        let store = CookieStore::new();
+
+        assert!(insert(&store, "host=1", "http://www.example.com/"));
+        assert!(insert(
+            &store,
+            "dom=1; Domain=.Example.com",
+            "http://www.example.com/"
+        ));
+        assert!(!insert(
+            &store,
+            "other=1; Domain=example.org",
+            "http://www.example.com/"
+        ));
+        assert!(!insert(
+            &store,
+            "sub=1; Domain=a.www.example.com",
+            "http://www.example.com/"
+        ));
+
+        assert_eq!(
+            names(&store, "http://www.example.com/"),
+            vec!["host", "dom"]
+        );
+        assert_eq!(names(&store, "http://example.com/"), vec!["dom"]);
+        assert_eq!(names(&store, "http://a.www.example.com/"), vec!["dom"]);
+        assert!(names(&store, "http://badexample.com/").is_empty());
+        assert!(names(&store, "http://example.org/").is_empty());
+
+        // IP addresses only match exactly
+        assert!(insert(&store, "ip=1", "http://127.0.0.1/"));
+        assert!(!insert(&store, "ip=1; Domain=0.0.1", "http://127.0.0.1/"));
+        assert_eq!(names(&store, "http://127.0.0.1/"), vec!["ip"]);
+    }
+
+    #[test]
+    fn test_public_suffix() {
+        let store = CookieStore::new();
+
+        assert!(!insert(
+            &store,
+            "tld=1; Domain=com",
+            "http://evil.example.com/"
+        ));
+        assert!(!insert(
+            &store,
+            "tld=1; Domain=.com",
+            "http://evil.example.com/"
+        ));
+        assert!(!insert(
+            &store,
+            "sld=1; Domain=co.uk",
+            "http://evil.example.co.uk/"
+        ));
+        assert!(insert(
+            &store,
+            "dom=1; Domain=example.co.uk",
+            "http://www.example.co.uk/"
+        ));
+
+        assert!(names(&store, "http://other.com/").is_empty());
+        assert!(names(&store, "http://other.co.uk/").is_empty());
+        assert_eq!(names(&store, "http://example.co.uk/"), vec!["dom"]);
+
+        // a public suffix that is the request host becomes a host-only cookie
+        assert!(insert(
+            &store,
+            "local=1; Domain=localhost",
+            "http://localhost/"
+        ));
+        assert_eq!(names(&store, "http://localhost/"), vec!["local"]);
+        assert!(names(&store, "http://a.localhost/").is_empty());
+    }
+
+    #[test]
+    fn test_path() {
+        assert_eq!(default_path(""), "/");
+        assert_eq!(default_path("/"), "/");
+        assert_eq!(default_path("/login"), "/");
+        assert_eq!(default_path("/api/login"), "/api");
+
+        let store = CookieStore::new();
+        insert(&store, "root=1; Path=/", "http://example.com/api/login");
+        insert(&store, "api=1", "http://example.com/api/login");
+        insert(
+            &store,
+            "v1=1; Path=/api/v1/",
+            "http://example.com/api/login",
+        );
+
+        assert_eq!(names(&store, "http://example.com/"), vec!["root"]);
+        assert_eq!(names(&store, "http://example.com/apis"), vec!["root"]);
+        assert_eq!(names(&store, "http://example.com/api"), vec!["api", "root"]);
+        assert_eq!(
+            names(&store, "http://example.com/api/v1/users?id=1"),
+            vec!["v1", "api", "root"]
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let store = CookieStore::new();
+
+        insert(&store, "a=1; Max-Age=3600", "http://example.com/");
+        insert(
+            &store,
+            "b=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "http://example.com/",
+        );
+        insert(
+            &store,
+            "c=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60",
+            "http://example.com/",
+        );
+        assert_eq!(names(&store, "http://example.com/"), vec!["a", "c"]);
+
+        // replaced by name, domain and path
+        insert(&store, "a=2; Path=/", "http://example.com/");
+        assert_eq!(names(&store, "http://example.com/"), vec!["c", "a"]);
+        assert_eq!(
+            store.cookies_for(&uri("http://example.com/"))[1].value(),
+            "2"
+        );
+        insert(&store, "a=3; Path=/other", "http://example.com/");
+        assert_eq!(store.cookies().len(), 3);
+
+        // expired cookies remove the stored cookie
+        insert(&store, "a=; Max-Age=0", "http://example.com/");
+        insert(
+            &store,
+            "c=; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            "http://example.com/",
+        );
+        assert!(names(&store, "http://example.com/").is_empty());
+        assert_eq!(names(&store, "http://example.com/other"), vec!["a"]);
+
+        store.clear();
+        assert!(store.cookies().is_empty());
+    }
+
+    #[test]
+    fn test_secure() {
+        let store = CookieStore::new();
+
+        assert!(!insert(&store, "a=1; Secure", "http://example.com/"));
+        assert!(insert(&store, "a=1; Secure", "https://example.com/"));
+        assert!(insert(&store, "b=1", "http://example.com/"));
+
+        assert_eq!(names(&store, "http://example.com/"), vec!["b"]);
+        assert_eq!(names(&store, "https://example.com/"), vec!["a", "b"]);
+        assert_eq!(names(&store, "wss://example.com/"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_apply() {
+        let store = CookieStore::new();
+        insert(&store, "a=1", "http://example.com/");
+        insert(&store, "b=x y", "http://example.com/");
+
+        let mut headers = HeaderMap::new();
+        assert!(store.apply(&headers, &uri("http://example.org/")).is_none());
+        assert_eq!(
+            store.apply(&headers, &uri("http://example.com/")).unwrap(),
+            "a=1; b=x%20y"
+        );
+
+        headers.insert(COOKIE, HeaderValue::from_static("a=explicit"));
+        assert_eq!(
+            store.apply(&headers, &uri("http://example.com/")).unwrap(),
+            "a=explicit; b=x%20y"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let store = CookieStore::new();
+        insert(&store, "session=1", "http://example.com/");
+        insert(
+            &store,
+            "a=1; Max-Age=3600; Secure; HttpOnly",
+            "https://example.com/",
+        );
+        insert(
+            &store,
+            "b=1; Max-Age=3600; Domain=example.com; Path=/api",
+            "http://example.com/",
+        );
+
+        let mut buf = Vec::new();
+        store.save_json(&mut buf).unwrap();
+
+        let loaded = CookieStore::load_json(&buf[..]).unwrap();
+        assert_eq!(loaded.0.borrow()[..], store.0.borrow()[1..]);
+        assert_eq!(names(&loaded, "https://www.example.com/api"), vec!["b"]);
+        assert!(loaded.cookies()[0].http_only().unwrap());
+
+        assert!(CookieStore::load_json(&b"{}"[..]).is_err());
+    }
+}
@@ -62,6 +62,32 @@ impl From<HttpError> for WsClientError {
     }
 }
 
+/// Server-Sent Events client error
+#[derive(Debug, Display, From)]
+pub enum SseError {
+    /// Invalid response status
+    #[display(fmt = "Invalid response status")]
+    InvalidResponseStatus(StatusCode),
+
+    /// Invalid content type
+    #[display(fmt = "Invalid content type")]
+    InvalidContentType,
+
+    /// Line or event exceeds the size limit
+    #[display(fmt = "Event stream line or event is too large")]
+    Overflow,
+
+    /// Payload error
+    #[display(fmt = "{}", _0)]
+    Payload(PayloadError),
+
+    /// Send request error
+    #[display(fmt = "{}", _0)]
+    SendRequest(SendRequestError),
+}
+
+impl std::error::Error for SseError {}
+
 /// A set of errors that can occur during parsing json payloads
 #[derive(Debug, Display, From)]
 pub enum JsonPayloadError {
@@ -99,10 +99,13 @@ use std::time::Duration;
 
 #[cfg(feature = "cookies")]
 pub use actix_http::cookie;
-pub use actix_http::{client::Connector, http};
+pub use actix_http::{
+    client::{Connector, HostStats, PoolKey, Proxy},
+    http,
+};
 
 use actix_http::{
-    client::{TcpConnect, TcpConnectError, TcpConnection},
+    client::{PoolStats, TcpConnect, TcpConnectError, TcpConnection},
     http::{Error as HttpError, HeaderMap, Method, Uri},
     RequestHead,
 };
@@ -111,17 +114,23 @@ use actix_service::{boxed, Service};
 
 mod builder;
 mod connect;
+#[cfg(feature = "cookies")]
+mod cookie_store;
 pub mod error;
 mod frozen;
 pub mod middleware;
+pub mod multipart;
 mod request;
 mod response;
 mod sender;
+pub mod sse;
 pub mod test;
 pub mod ws;
 
 pub use self::builder::ClientBuilder;
 pub use self::connect::{BoxedSocket, ConnectRequest, ConnectResponse, ConnectorService};
+#[cfg(feature = "cookies")]
+pub use self::cookie_store::CookieStore;
 pub use self::frozen::{FrozenClientRequest, FrozenSendBuilder};
 pub use self::request::ClientRequest;
 pub use self::response::{ClientResponse, JsonBody, MessageBody};
@@ -153,16 +162,19 @@ pub(crate) struct ClientConfig {
     pub(crate) connector: ConnectorService,
     pub(crate) headers: HeaderMap,
     pub(crate) timeout: Option<Duration>,
+    pub(crate) pool_stats: PoolStats,
 }
 
 impl Default for Client {
     fn default() -> Self {
+        let connector = Connector::new();
+        let pool_stats = connector.pool_stats();
+
         Client(Rc::new(ClientConfig {
-            connector: boxed::service(self::connect::DefaultConnector::new(
-                Connector::new().finish(),
-            )),
+            connector: boxed::service(self::connect::DefaultConnector::new(connector.finish())),
             headers: HeaderMap::new(),
             timeout: Some(Duration::from_secs(5)),
+            pool_stats,
         }))
     }
 }
@@ -186,6 +198,14 @@ impl Client {
         ClientBuilder::new()
     }
 
+    /// Returns the connection statistics of every host with active, idle or waiting connections.
+    ///
+    /// Requests waiting for a connection are only counted when a connection limit is reached;
+    /// see [`Connector::limit`] and [`Connector::limit_per_host`].
+    pub fn pool_stats(&self) -> Vec<HostStats> {
+        self.0.pool_stats.hosts()
+    }
+
     /// Construct HTTP request.
     pub fn request<U>(&self, method: Method, url: U) -> ClientRequest
     where
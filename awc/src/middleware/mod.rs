@@ -1,6 +1,8 @@
 mod redirect;
+mod retry;
 
 pub use self::redirect::Redirect;
+pub use self::retry::{Retry, RetryService};
 
 use std::marker::PhantomData;
 
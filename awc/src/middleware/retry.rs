@@ -0,0 +1,423 @@
+use std::{
+    cmp,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use actix_http::{
+    body::Body,
+    client::{ConnectError, SendRequestError},
+    http::{
+        header::{HttpDate, RETRY_AFTER},
+        Method, StatusCode,
+    },
+    RequestHeadType,
+};
+use actix_rt::time::sleep;
+use actix_service::Service;
+use futures_core::future::LocalBoxFuture;
+use rand::Rng;
+
+use super::Transform;
+
+use crate::connect::{ConnectRequest, ConnectResponse};
+use crate::ClientResponse;
+
+type RetryPredicate = Box<dyn Fn(Result<&ClientResponse, &SendRequestError>) -> bool>;
+
+/// Middleware for retrying requests that failed with a transient error.
+///
+/// By default, connect errors, I/O errors while sending the request and `429`, `502`, `503` and
+/// `504` responses are retried up to 3 times; use [`retry_if`](Self::retry_if) to choose which
+/// outcomes are retried.
+///
+/// Only requests that can be replayed are retried: the body must be empty or in-memory bytes, and
+/// streaming bodies are sent once. Requests with a method that is not idempotent, like `POST`, are
+/// only retried after connect errors, since they never reached the server.
+///
+/// Retries are delayed by an exponential backoff that starts at 100ms and doubles up to 10s, with
+/// random jitter that shortens each delay by up to half. A `Retry-After` header on a retried
+/// response takes precedence over the backoff; responses that ask for a longer delay than the
+/// maximum backoff are returned without retrying. The client timeout covers all attempts.
+///
+/// # Examples
+/// ```rust
+/// use std::time::Duration;
+/// use awc::{middleware::Retry, ClientBuilder};
+///
+/// let client = ClientBuilder::new()
+///     .wrap(
+///         Retry::new()
+///             .max_retries(5)
+///             .backoff(Duration::from_millis(50), Duration::from_secs(2)),
+///     )
+///     .finish();
+/// ```
+pub struct Retry {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    predicate: RetryPredicate,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Retry {
+    pub fn new() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            predicate: Box::new(default_predicate),
+        }
+    }
+
+    /// Sets the maximum number of retries after the first attempt.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Sets the delay before the first retry and the upper bound of the delays.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Enables or disables random jitter of retry delays.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets a predicate that decides if a response or error should be retried.
+    pub fn retry_if<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Result<&ClientResponse, &SendRequestError>) -> bool + 'static,
+    {
+        self.predicate = Box::new(f);
+        self
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_backoff
+            .checked_mul(1 << cmp::min(attempt, 31))
+            .map_or(self.max_backoff, |delay| cmp::min(delay, self.max_backoff));
+
+        if self.jitter {
+            let nanos = delay.as_nanos() as u64;
+            Duration::from_nanos(rand::thread_rng().gen_range(nanos / 2..=nanos))
+        } else {
+            delay
+        }
+    }
+
+    /// Returns the delay before the next attempt, or `None` if the result should be returned.
+    fn retry_delay(
+        &self,
+        res: &Result<ConnectResponse, SendRequestError>,
+        idempotent: bool,
+        attempt: u32,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        match res {
+            Ok(ConnectResponse::Client(res)) if idempotent && (self.predicate)(Ok(res)) => {
+                match retry_after(res) {
+                    Some(delay) if delay > self.max_backoff => None,
+                    Some(delay) => Some(delay),
+                    None => Some(self.backoff_delay(attempt)),
+                }
+            }
+            Err(err) if (idempotent || is_unsent(err)) && (self.predicate)(Err(err)) => {
+                Some(self.backoff_delay(attempt))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn default_predicate(res: Result<&ClientResponse, &SendRequestError>) -> bool {
+    match res {
+        Ok(res) => matches!(
+            res.status(),
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Err(err) => matches!(
+            err,
+            SendRequestError::Connect(_) | SendRequestError::Send(_)
+        ),
+    }
+}
+
+/// Parses a `Retry-After` header in either delay-seconds or HTTP-date form.
+fn retry_after(res: &ClientResponse) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = value.parse::<HttpDate>().ok()?;
+    Some(
+        SystemTime::from(date)
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// Returns true if the request is known to not have been sent.
+fn is_unsent(err: &SendRequestError) -> bool {
+    match err {
+        // a pooled connection closed by the peer is only noticed after sending the request
+        SendRequestError::Connect(ConnectError::Disconnected) => false,
+        SendRequestError::Connect(_) => true,
+        _ => false,
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET
+            | Method::HEAD
+            | Method::PUT
+            | Method::DELETE
+            | Method::OPTIONS
+            | Method::TRACE
+    )
+}
+
+fn replay_body(body: &Body) -> Option<Body> {
+    match body {
+        Body::None => Some(Body::None),
+        Body::Empty => Some(Body::Empty),
+        Body::Bytes(bytes) => Some(Body::Bytes(bytes.clone())),
+        Body::Message(_) => None,
+    }
+}
+
+impl<S> Transform<S, ConnectRequest> for Retry
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Transform = RetryService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        RetryService {
+            config: Rc::new(self),
+            connector: Rc::new(service),
+        }
+    }
+}
+
+pub struct RetryService<S> {
+    config: Rc<Retry>,
+    connector: Rc<S>,
+}
+
+impl<S> Service<ConnectRequest> for RetryService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(connector);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let connector = self.connector.clone();
+
+        let (head, body, addr) = match req {
+            ConnectRequest::Client(head, body, addr) if replay_body(&body).is_some() => {
+                (head, body, addr)
+            }
+            req => return Box::pin(async move { connector.call(req).await }),
+        };
+
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            // share the head between attempts
+            let (head, extra_headers) = match head {
+                RequestHeadType::Owned(head) => (Rc::new(head), None),
+                RequestHeadType::Rc(head, extra_headers) => (head, extra_headers),
+            };
+
+            let idempotent = is_idempotent(&head.method);
+            let mut attempt = 0;
+
+            loop {
+                let head = RequestHeadType::Rc(head.clone(), extra_headers.clone());
+                let body = replay_body(&body).unwrap();
+
+                let res = connector
+                    .call(ConnectRequest::Client(head, body, addr))
+                    .await;
+
+                match config.retry_delay(&res, idempotent, attempt) {
+                    Some(delay) => {
+                        // release the connection before waiting
+                        drop(res);
+                        sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return res,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use actix_web::{
+        test::{start, TestServer},
+        web, App, Error, HttpResponse,
+    };
+
+    use super::*;
+
+    use crate::{test::TestResponse, Client, ClientBuilder};
+
+    /// Starts a server that responds with the given status to the first `failures` requests.
+    fn flaky_server(failures: usize, status: StatusCode) -> (TestServer, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        let srv_hits = hits.clone();
+        let srv = start(move || {
+            let hits = srv_hits.clone();
+            App::new().default_service(web::to(move |_: web::Bytes| {
+                let n = hits.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let res = if n < failures {
+                        HttpResponse::build(status)
+                            .insert_header((RETRY_AFTER, "0"))
+                            .finish()
+                    } else {
+                        HttpResponse::Ok().finish()
+                    };
+                    Ok::<_, Error>(res)
+                }
+            }))
+        });
+
+        (srv, hits)
+    }
+
+    fn client(retry: Retry) -> Client {
+        ClientBuilder::new()
+            .wrap(retry.backoff(Duration::from_millis(1), Duration::from_millis(10)))
+            .finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_retry() {
+        let (srv, hits) = flaky_server(2, StatusCode::SERVICE_UNAVAILABLE);
+        let res = client(Retry::new()).get(srv.url("/")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        let (srv, hits) = flaky_server(2, StatusCode::SERVICE_UNAVAILABLE);
+        let res = client(Retry::new().max_retries(1))
+            .put(srv.url("/"))
+            .send_body("data")
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let (srv, hits) = flaky_server(2, StatusCode::INTERNAL_SERVER_ERROR);
+        let res = client(Retry::new()).get(srv.url("/")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let (srv, hits) = flaky_server(2, StatusCode::INTERNAL_SERVER_ERROR);
+        let retry = Retry::new()
+            .retry_if(|res| res.map_or(false, |res| res.status().is_server_error()));
+        let res = client(retry).get(srv.url("/")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_rt::test]
+    async fn test_no_retry_non_idempotent() {
+        let (srv, hits) = flaky_server(1, StatusCode::SERVICE_UNAVAILABLE);
+        let res = client(Retry::new())
+            .post(srv.url("/"))
+            .send_body("data")
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_no_retry_stream_body() {
+        let (srv, hits) = flaky_server(1, StatusCode::SERVICE_UNAVAILABLE);
+        let body = futures_util::stream::iter(vec![Ok::<_, actix_http::Error>(
+            bytes::Bytes::from_static(b"data"),
+        )]);
+        let res = client(Retry::new())
+            .put(srv.url("/"))
+            .send_stream(body)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let res = TestResponse::with_header(RETRY_AFTER, "120").finish();
+        assert_eq!(retry_after(&res), Some(Duration::from_secs(120)));
+
+        let res =
+            TestResponse::with_header(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT").finish();
+        assert_eq!(retry_after(&res), Some(Duration::from_secs(0)));
+
+        let res = TestResponse::default().finish();
+        assert_eq!(retry_after(&res), None);
+
+        // asking for a longer delay than the maximum backoff disables the retry
+        let retry = Retry::new().retry_if(|_| true);
+        let res = Ok(ConnectResponse::Client(res));
+        assert!(retry.retry_delay(&res, true, 0).is_some());
+        let res = Ok(ConnectResponse::Client(
+            TestResponse::with_header(RETRY_AFTER, "120").finish(),
+        ));
+        assert_eq!(retry.retry_delay(&res, true, 0), None);
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let retry = Retry::new()
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(false);
+        assert_eq!(retry.backoff_delay(0), Duration::from_millis(100));
+        assert_eq!(retry.backoff_delay(2), Duration::from_millis(400));
+        assert_eq!(retry.backoff_delay(40), Duration::from_secs(1));
+
+        let delay = retry.jitter(true).backoff_delay(1);
+        assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,435 @@
+//! `multipart/form-data` request bodies
+//!
+//! A [`Form`] is built from text fields and [`Part`]s and sent with
+//! [`ClientRequest::send_multipart`](crate::ClientRequest::send_multipart), which also sets the
+//! `Content-Type` header with the form's boundary.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use awc::multipart::{Form, Part};
+//! use bytes::Bytes;
+//! use futures_util::stream;
+//!
+//! #[actix_rt::main]
+//! async fn main() {
+//!     let file = stream::iter(vec![Ok::<_, std::io::Error>(Bytes::from("file content"))]);
+//!
+//!     let form = Form::new()
+//!         .text("title", "Report")
+//!         .part(
+//!             "attachment",
+//!             Part::stream(file)
+//!                 .file_name("report.txt")
+//!                 .mime(mime::TEXT_PLAIN_UTF_8),
+//!         );
+//!
+//!     let res = awc::Client::new()
+//!         .post("http://localhost:8080/upload")
+//!         .send_multipart(form)
+//!         .await;
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::{
+    body::{Body, BodyStream, SizedStream},
+    Error,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_core::{ready, Stream};
+use rand::{distributions::Alphanumeric, Rng};
+
+type PartStream = Pin<Box<dyn Stream<Item = Result<Bytes, Error>>>>;
+
+/// A `multipart/form-data` request body.
+///
+/// Parts are sent in the order they are added. If all parts are in memory, the form is sent as a
+/// single buffer; otherwise it is streamed, with a `Content-Length` header if the lengths of all
+/// streaming parts are known.
+pub struct Form {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+/// A single part of a [`Form`].
+pub struct Part {
+    body: PartBody,
+    file_name: Option<String>,
+    mime: Option<mime::Mime>,
+}
+
+enum PartBody {
+    Bytes(Bytes),
+    Stream(PartStream, Option<u64>),
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Form {
+    /// Constructs an empty form with a random boundary.
+    pub fn new() -> Self {
+        let boundary = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        Form {
+            boundary,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Adds a text field.
+    pub fn text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.part(name, Part::text(value))
+    }
+
+    /// Adds a part with in-memory content.
+    pub fn bytes(self, name: impl Into<String>, value: impl Into<Bytes>) -> Self {
+        self.part(name, Part::bytes(value))
+    }
+
+    /// Adds a part.
+    pub fn part(mut self, name: impl Into<String>, part: Part) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Returns the boundary that separates the parts of the form.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Returns the value of the `Content-Type` header for this form.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Returns the length of the encoded form, if the lengths of all parts are known.
+    pub fn content_length(&self) -> Option<u64> {
+        let mut len = self.boundary.len() as u64 + 6;
+
+        for (name, part) in &self.parts {
+            len += part_header(&self.boundary, name, part).len() as u64 + 2;
+            len += match part.body {
+                PartBody::Bytes(ref bytes) => bytes.len() as u64,
+                PartBody::Stream(_, len) => len?,
+            };
+        }
+
+        Some(len)
+    }
+
+    pub(crate) fn into_body(self) -> Body {
+        let len = self.content_length();
+        let mut chunks = VecDeque::with_capacity(self.parts.len() * 3 + 1);
+
+        for (name, part) in self.parts {
+            chunks.push_back(Chunk::Bytes(part_header(&self.boundary, &name, &part)));
+            chunks.push_back(match part.body {
+                PartBody::Bytes(bytes) => Chunk::Bytes(bytes),
+                PartBody::Stream(stream, _) => Chunk::Stream(stream),
+            });
+            chunks.push_back(Chunk::Bytes(Bytes::from_static(b"\r\n")));
+        }
+
+        chunks.push_back(Chunk::Bytes(Bytes::from(format!(
+            "--{}--\r\n",
+            self.boundary
+        ))));
+
+        if chunks.iter().all(|chunk| matches!(chunk, Chunk::Bytes(_))) {
+            let mut buf = BytesMut::with_capacity(len.unwrap_or(0) as usize);
+            for chunk in chunks {
+                if let Chunk::Bytes(bytes) = chunk {
+                    buf.put_slice(&bytes);
+                }
+            }
+            return Body::Bytes(buf.freeze());
+        }
+
+        let stream = FormStream { chunks };
+
+        match len {
+            Some(len) => Body::from_message(SizedStream::new(len, stream)),
+            None => Body::from_message(BodyStream::new(stream)),
+        }
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+impl Part {
+    /// Constructs a part with text content.
+    pub fn text(value: impl Into<String>) -> Self {
+        Self::bytes(value.into())
+    }
+
+    /// Constructs a part with in-memory content.
+    pub fn bytes(value: impl Into<Bytes>) -> Self {
+        Self::new(PartBody::Bytes(value.into()))
+    }
+
+    /// Constructs a part with streaming content of unknown length.
+    ///
+    /// Forms with such a part are sent with chunked transfer encoding.
+    pub fn stream<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        Self::new(PartBody::Stream(Box::pin(ErrInto { stream }), None))
+    }
+
+    /// Constructs a part with streaming content of known length.
+    ///
+    /// The stream must yield exactly `len` bytes.
+    pub fn stream_with_length<S, E>(stream: S, len: u64) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        Self::new(PartBody::Stream(Box::pin(ErrInto { stream }), Some(len)))
+    }
+
+    fn new(body: PartBody) -> Self {
+        Part {
+            body,
+            file_name: None,
+            mime: None,
+        }
+    }
+
+    /// Sets the file name of the part.
+    ///
+    /// Parts with a file name and without a content type are sent as
+    /// `application/octet-stream`.
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Sets the content type of the part.
+    pub fn mime(mut self, mime: mime::Mime) -> Self {
+        self.mime = Some(mime);
+        self
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dbg = f.debug_struct("Part");
+
+        match self.body {
+            PartBody::Bytes(ref bytes) => dbg.field("body", bytes),
+            PartBody::Stream(_, ref len) => dbg.field("body", &"Stream").field("length", len),
+        };
+
+        dbg.field("file_name", &self.file_name)
+            .field("mime", &self.mime)
+            .finish()
+    }
+}
+
+/// Encodes the headers of a part, including the boundary that precedes them.
+fn part_header(boundary: &str, name: &str, part: &Part) -> Bytes {
+    let mut buf = BytesMut::with_capacity(128);
+
+    buf.put_slice(b"--");
+    buf.put_slice(boundary.as_bytes());
+    buf.put_slice(b"\r\nContent-Disposition: form-data; name=\"");
+    buf.put_slice(escape(name).as_bytes());
+    buf.put_slice(b"\"");
+
+    if let Some(ref file_name) = part.file_name {
+        buf.put_slice(b"; filename=\"");
+        buf.put_slice(escape(file_name).as_bytes());
+        buf.put_slice(b"\"");
+    }
+
+    let mime = match part.mime {
+        Some(ref mime) => Some(mime.as_ref()),
+        None if part.file_name.is_some() => Some(mime::APPLICATION_OCTET_STREAM.as_ref()),
+        None => None,
+    };
+
+    if let Some(mime) = mime {
+        buf.put_slice(b"\r\nContent-Type: ");
+        buf.put_slice(mime.as_bytes());
+    }
+
+    buf.put_slice(b"\r\n\r\n");
+    buf.freeze()
+}
+
+/// Escapes a field or file name for a quoted `Content-Disposition` parameter, as browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+enum Chunk {
+    Bytes(Bytes),
+    Stream(PartStream),
+}
+
+/// Stream of the encoded parts of a form.
+struct FormStream {
+    chunks: VecDeque<Chunk>,
+}
+
+impl Stream for FormStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.chunks.front_mut() {
+                None => return Poll::Ready(None),
+                Some(Chunk::Stream(stream)) => match ready!(stream.as_mut().poll_next(cx)) {
+                    Some(item) => return Poll::Ready(Some(item)),
+                    None => {
+                        self.chunks.pop_front();
+                    }
+                },
+                Some(Chunk::Bytes(_)) => {
+                    if let Some(Chunk::Bytes(bytes)) = self.chunks.pop_front() {
+                        return Poll::Ready(Some(Ok(bytes)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Converts the error type of a part stream.
+    struct ErrInto<S> {
+        #[pin]
+        stream: S,
+    }
+}
+
+impl<S, E> Stream for ErrInto<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<Error>,
+{
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project()
+            .stream
+            .poll_next(cx)
+            .map(|item| item.map(|res| res.map_err(Into::into)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::body::MessageBody;
+    use futures_util::{future::poll_fn, stream};
+
+    use super::*;
+
+    async fn read_body(mut body: Body) -> Bytes {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf.freeze()
+    }
+
+    #[actix_rt::test]
+    async fn test_form_bytes() {
+        let form = Form::new()
+            .text("title", "a \"quoted\"\r\nname")
+            .part(
+                "file",
+                Part::bytes("content")
+                    .file_name("a.txt")
+                    .mime(mime::TEXT_PLAIN),
+            )
+            .part("data", Part::bytes(vec![0u8, 1]).file_name("data.bin"));
+
+        let boundary = form.boundary().to_owned();
+        assert_eq!(boundary.len(), 32);
+        assert_eq!(
+            form.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+
+        let len = form.content_length().unwrap();
+        let body = form.into_body();
+        assert!(matches!(body, Body::Bytes(_)));
+
+        let expected = format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+             a \"quoted\"\r\nname\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             content\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"data\"; filename=\"data.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             \u{0}\u{1}\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+
+        let body = read_body(body).await;
+        assert_eq!(body, expected);
+        assert_eq!(body.len() as u64, len);
+    }
+
+    #[actix_rt::test]
+    async fn test_form_stream() {
+        let chunks = || {
+            stream::iter(vec![
+                Ok::<_, Error>(Bytes::from_static(b"ab")),
+                Ok(Bytes::from_static(b"cd")),
+            ])
+        };
+
+        let form = Form::new().text("a\"b", "1").part(
+            "file",
+            Part::stream_with_length(chunks(), 4).file_name("f\"1"),
+        );
+        let len = form.content_length().unwrap();
+
+        let body = form.into_body();
+        assert_eq!(body.size(), actix_http::body::BodySize::Sized(len));
+
+        let body = read_body(body).await;
+        assert_eq!(body.len() as u64, len);
+        assert!(body.windows(8).any(|w| w == b"a%22b\"\r\n"));
+        assert!(body.windows(12).any(|w| w == b"\r\n\r\nabcd\r\n--"));
+
+        let form = Form::new().part("file", Part::stream(chunks()));
+        assert!(form.content_length().is_none());
+        let body = form.into_body();
+        assert_eq!(body.size(), actix_http::body::BodySize::Stream);
+    }
+}
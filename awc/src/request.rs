@@ -18,12 +18,16 @@ use actix_http::{Error, RequestHead};
 
 use crate::error::{FreezeRequestError, InvalidUrl};
 use crate::frozen::FrozenClientRequest;
+use crate::multipart::Form;
 use crate::sender::{PrepForSendingError, RequestSender, SendClientRequest};
+use crate::sse::EventSource;
 use crate::ClientConfig;
 
 cfg_if::cfg_if! {
     if #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))] {
         const HTTPS_ENCODING: &str = "br, gzip, deflate";
+    } else if #[cfg(feature = "compress-zstd")] {
+        const HTTPS_ENCODING: &str = "br, zstd";
     } else if #[cfg(feature = "compress")] {
         const HTTPS_ENCODING: &str = "br";
     } else {
@@ -389,6 +393,19 @@ impl ClientRequest {
         Ok(request)
     }
 
+    /// Freeze request builder and construct a Server-Sent Events [`EventSource`].
+    ///
+    /// The request is sent with the `Accept: text/event-stream` header and is re-sent with the
+    /// `Last-Event-ID` header whenever the connection is lost.
+    pub fn sse(self) -> Result<EventSource, FreezeRequestError> {
+        let req = self
+            .insert_header((header::ACCEPT, "text/event-stream"))
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            .freeze()?;
+
+        Ok(EventSource::new(req))
+    }
+
     /// Complete request construction and send body.
     pub fn send_body<B>(self, body: B) -> SendClientRequest
     where
@@ -442,6 +459,15 @@ impl ClientRequest {
         )
     }
 
+    /// Set a `multipart/form-data` body and generate `ClientRequest`.
+    ///
+    /// The `Content-Type` header is set to include the boundary of the form.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        let content_type = form.content_type();
+        self.insert_header((header::CONTENT_TYPE, content_type))
+            .send_body(form.into_body())
+    }
+
     /// Set an streaming body and generate `ClientRequest`.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
@@ -0,0 +1,471 @@
+//! Server-Sent Events client
+//!
+//! [`EventStream`] decodes a `text/event-stream` response into [`Event`]s. [`EventSource`], created
+//! with [`ClientRequest::sse`](crate::ClientRequest::sse), additionally reconnects when the
+//! connection is lost and resumes the stream by sending the `Last-Event-ID` header.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use futures_util::stream::StreamExt;
+//!
+//! #[actix_rt::main]
+//! async fn main() {
+//!     let mut events = awc::Client::new()
+//!         .get("http://localhost:8080/events")
+//!         .sse()
+//!         .unwrap();
+//!
+//!     while let Some(event) = events.next().await {
+//!         let event = event.unwrap();
+//!         println!("{}: {}", event.event_type(), event.data());
+//!     }
+//! }
+//! ```
+
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::{sleep, Sleep};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_core::{ready, Stream};
+
+use crate::error::{PayloadError, SseError};
+use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::http::StatusCode;
+use crate::response::ClientResponse;
+use crate::{FrozenClientRequest, SendClientRequest};
+
+#[cfg(feature = "compress")]
+type ResponsePayload =
+    actix_http::encoding::Decoder<actix_http::Payload<actix_http::PayloadStream>>;
+#[cfg(not(feature = "compress"))]
+type ResponsePayload = actix_http::PayloadStream;
+
+const DEFAULT_LIMIT: usize = 262_144;
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// A Server-Sent Event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+impl Event {
+    /// Event type. Defaults to `message` when the server did not specify one.
+    pub fn event_type(&self) -> &str {
+        &self.event
+    }
+
+    /// Event data. Multi-line data is joined with `\n`.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Last event ID seen on the stream when this event was dispatched.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Consume the event and return its data.
+    pub fn into_data(self) -> String {
+        self.data
+    }
+}
+
+/// Stream of events decoded from a `text/event-stream` response.
+///
+/// The response status and content type are not checked. An incomplete event at the end of the
+/// stream is discarded.
+pub struct EventStream<S> {
+    res: ClientResponse<S>,
+    buf: BytesMut,
+    limit: usize,
+    started: bool,
+    skip_lf: bool,
+    event: String,
+    data: String,
+    last_event_id: String,
+    retry: Option<Duration>,
+}
+
+impl<S> EventStream<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    /// Create a new event stream from a response.
+    pub fn new(res: ClientResponse<S>) -> Self {
+        EventStream {
+            res,
+            buf: BytesMut::new(),
+            limit: DEFAULT_LIMIT,
+            started: false,
+            skip_lf: false,
+            event: String::new(),
+            data: String::new(),
+            last_event_id: String::new(),
+            retry: None,
+        }
+    }
+
+    /// Set maximum size of a single line or event. By default max size is 256Kb.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Last event ID received on this stream, if any.
+    pub fn last_event_id(&self) -> Option<&str> {
+        if self.last_event_id.is_empty() {
+            None
+        } else {
+            Some(&self.last_event_id)
+        }
+    }
+
+    /// Reconnection time requested by the server, if any.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Take the next complete line from the buffer, without its line terminator.
+    fn next_line(&mut self) -> Option<Bytes> {
+        if self.skip_lf && !self.buf.is_empty() {
+            if self.buf[0] == b'\n' {
+                self.buf.advance(1);
+            }
+            self.skip_lf = false;
+        }
+
+        let pos = self.buf.iter().position(|&b| b == b'\n' || b == b'\r')?;
+        let line = self.buf.split_to(pos).freeze();
+        self.skip_lf = self.buf[0] == b'\r';
+        self.buf.advance(1);
+        Some(line)
+    }
+
+    /// Process a single line, returning an event if the line completed one.
+    fn process_line(&mut self, line: &[u8]) -> Option<Event> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        let (field, value) = match line.iter().position(|&b| b == b':') {
+            // comment
+            Some(0) => return None,
+            Some(pos) => {
+                let mut value = &line[pos + 1..];
+                if value.first() == Some(&b' ') {
+                    value = &value[1..];
+                }
+                (&line[..pos], value)
+            }
+            None => (line, &b""[..]),
+        };
+        let value = String::from_utf8_lossy(value);
+
+        match field {
+            b"event" => self.event = value.into_owned(),
+            b"data" => {
+                self.data.push_str(&value);
+                self.data.push('\n');
+            }
+            b"id" if !value.contains('\0') => self.last_event_id = value.into_owned(),
+            b"retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = mem::take(&mut self.event);
+        let mut data = mem::take(&mut self.data);
+
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+
+        Some(Event {
+            event: if event.is_empty() {
+                "message".to_owned()
+            } else {
+                event
+            },
+            data,
+            id: self.last_event_id().map(ToOwned::to_owned),
+        })
+    }
+}
+
+impl<S> Stream for EventStream<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    type Item = Result<Event, SseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            while let Some(line) = this.next_line() {
+                if let Some(event) = this.process_line(&line) {
+                    return Poll::Ready(Some(Ok(event)));
+                }
+
+                if this.data.len() + this.event.len() > this.limit {
+                    return Poll::Ready(Some(Err(SseError::Overflow)));
+                }
+            }
+
+            if this.buf.len() > this.limit {
+                return Poll::Ready(Some(Err(SseError::Overflow)));
+            }
+
+            match ready!(Pin::new(&mut this.res).poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    this.buf.extend_from_slice(&chunk);
+
+                    // strip byte order mark at the start of the stream
+                    if !this.started && this.buf.len() >= 3 {
+                        if this.buf.starts_with(b"\xEF\xBB\xBF") {
+                            this.buf.advance(3);
+                        }
+                        this.started = true;
+                    }
+                }
+                Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Reconnecting stream of Server-Sent Events.
+///
+/// When the connection is closed or fails, the request is sent again after the reconnection time
+/// (3 seconds by default, or as requested by the server) with the `Last-Event-ID` header set to
+/// the last received event ID. Connection errors are yielded before reconnecting.
+///
+/// The stream ends with an error if the server responds with a status other than `200 OK` or a
+/// content type other than `text/event-stream`. A `204 No Content` response ends the stream
+/// without an error.
+pub struct EventSource {
+    req: FrozenClientRequest,
+    state: State,
+    limit: usize,
+    last_event_id: String,
+    retry: Duration,
+}
+
+enum State {
+    Connecting(SendClientRequest),
+    Streaming(Box<EventStream<ResponsePayload>>),
+    Waiting(Pin<Box<Sleep>>),
+    Done,
+}
+
+impl EventSource {
+    pub(crate) fn new(req: FrozenClientRequest) -> Self {
+        let send = req.send();
+
+        EventSource {
+            req,
+            state: State::Connecting(send),
+            limit: DEFAULT_LIMIT,
+            last_event_id: String::new(),
+            retry: DEFAULT_RETRY,
+        }
+    }
+
+    /// Set maximum size of a single line or event. By default max size is 256Kb.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set reconnection time used until the server requests a different one.
+    ///
+    /// By default, reconnection time is 3 seconds.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Last event ID received from the server, if any.
+    pub fn last_event_id(&self) -> Option<&str> {
+        match self.state {
+            State::Streaming(ref stream) => stream.last_event_id(),
+            _ if self.last_event_id.is_empty() => None,
+            _ => Some(&self.last_event_id),
+        }
+    }
+
+    fn connect(&self) -> SendClientRequest {
+        let value = if self.last_event_id.is_empty() {
+            None
+        } else {
+            HeaderValue::from_bytes(self.last_event_id.as_bytes()).ok()
+        };
+
+        match value {
+            Some(value) => self
+                .req
+                .extra_header(HeaderName::from_static("last-event-id"), value)
+                .send(),
+            None => self.req.send(),
+        }
+    }
+
+    fn reconnect(&mut self) {
+        if let State::Streaming(ref mut stream) = self.state {
+            self.last_event_id = mem::take(&mut stream.last_event_id);
+            if let Some(retry) = stream.retry {
+                self.retry = retry;
+            }
+        }
+
+        self.state = State::Waiting(Box::pin(sleep(self.retry)));
+    }
+}
+
+impl Stream for EventSource {
+    type Item = Result<Event, SseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match this.state {
+                State::Connecting(ref mut send) => match ready!(Pin::new(send).poll(cx)) {
+                    Ok(res) => {
+                        if res.status() == StatusCode::NO_CONTENT {
+                            this.state = State::Done;
+                            return Poll::Ready(None);
+                        }
+
+                        if res.status() != StatusCode::OK {
+                            this.state = State::Done;
+                            return Poll::Ready(Some(Err(SseError::InvalidResponseStatus(
+                                res.status(),
+                            ))));
+                        }
+
+                        if !is_event_stream(&res) {
+                            this.state = State::Done;
+                            return Poll::Ready(Some(Err(SseError::InvalidContentType)));
+                        }
+
+                        let mut stream = EventStream::new(res).limit(this.limit);
+                        stream.last_event_id = mem::take(&mut this.last_event_id);
+                        this.state = State::Streaming(Box::new(stream));
+                    }
+                    Err(err) => {
+                        this.reconnect();
+                        return Poll::Ready(Some(Err(err.into())));
+                    }
+                },
+
+                State::Streaming(ref mut stream) => {
+                    match ready!(Pin::new(&mut **stream).poll_next(cx)) {
+                        Some(Ok(event)) => return Poll::Ready(Some(Ok(event))),
+                        Some(Err(SseError::Overflow)) => {
+                            this.state = State::Done;
+                            return Poll::Ready(Some(Err(SseError::Overflow)));
+                        }
+                        Some(Err(err)) => {
+                            this.reconnect();
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        None => this.reconnect(),
+                    }
+                }
+
+                State::Waiting(ref mut timer) => {
+                    ready!(timer.as_mut().poll(cx));
+                    this.state = State::Connecting(this.connect());
+                }
+
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+fn is_event_stream<S>(res: &ClientResponse<S>) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.split(';').next())
+        .map(|mime| mime.trim().eq_ignore_ascii_case("text/event-stream"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, StreamExt as _};
+
+    use super::*;
+    use crate::test::TestResponse;
+
+    fn event_stream(
+        chunks: &[&'static [u8]],
+    ) -> EventStream<impl Stream<Item = Result<Bytes, PayloadError>> + Unpin> {
+        let chunks = chunks
+            .iter()
+            .map(|chunk| Ok(Bytes::from_static(chunk)))
+            .collect::<Vec<_>>();
+
+        let res = TestResponse::default()
+            .finish()
+            .map_body(|_, _| actix_http::Payload::Stream(stream::iter(chunks)));
+
+        EventStream::new(res)
+    }
+
+    #[actix_rt::test]
+    async fn test_decode() {
+        let mut events = event_stream(&[
+            b"\xEF\xBB\xBF: comment\n",
+            b"data: first\n\nevent: upd",
+            b"ate\rid: 2\r\ndata:line 1\r\ndata\ndata:  line 3\n\n",
+            b"retry: 1500\nretry: x\nid: a\0b\n\n",
+            b"data: partial",
+        ]);
+
+        let ev = events.next().await.unwrap().unwrap();
+        assert_eq!(ev.event_type(), "message");
+        assert_eq!(ev.data(), "first");
+        assert_eq!(ev.id(), None);
+
+        let ev = events.next().await.unwrap().unwrap();
+        assert_eq!(ev.event_type(), "update");
+        assert_eq!(ev.data(), "line 1\n\n line 3");
+        assert_eq!(ev.id(), Some("2"));
+
+        assert!(events.next().await.is_none());
+        assert_eq!(events.last_event_id(), Some("2"));
+        assert_eq!(events.retry(), Some(Duration::from_millis(1500)));
+    }
+
+    #[actix_rt::test]
+    async fn test_limit() {
+        let mut events = event_stream(&[b"data: 0123456789"]).limit(8);
+        match events.next().await {
+            Some(Err(SseError::Overflow)) => {}
+            _ => panic!(),
+        }
+    }
+}
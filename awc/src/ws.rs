@@ -38,6 +38,8 @@ use actix_http::{ws, Payload, RequestHead};
 use actix_rt::time::timeout;
 use actix_service::Service;
 
+#[cfg(feature = "compress")]
+pub use actix_http::ws::DeflateConfig;
 pub use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Message};
 
 use crate::connect::{BoxedSocket, ConnectRequest};
@@ -60,6 +62,9 @@ pub struct WebsocketsRequest {
 
     #[cfg(feature = "cookies")]
     cookies: Option<CookieJar>,
+
+    #[cfg(feature = "compress")]
+    deflate: Option<DeflateConfig>,
 }
 
 impl WebsocketsRequest {
@@ -95,6 +100,8 @@ impl WebsocketsRequest {
             server_mode: false,
             #[cfg(feature = "cookies")]
             cookies: None,
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 
@@ -155,6 +162,16 @@ impl WebsocketsRequest {
         self
     }
 
+    /// Offer the `permessage-deflate` extension to the server.
+    ///
+    /// If the server accepts the offer, Text and Binary messages are compressed with the agreed
+    /// parameters. A server response that does not match the offer fails the connection.
+    #[cfg(feature = "compress")]
+    pub fn deflate(mut self, config: DeflateConfig) -> Self {
+        self.deflate = Some(config);
+        self
+    }
+
     /// Disable payload masking. By default ws client masks frame payload.
     pub fn server_mode(mut self) -> Self {
         self.server_mode = true;
@@ -313,6 +330,13 @@ impl WebsocketsRequest {
             );
         }
 
+        #[cfg(feature = "compress")]
+        if let Some(ref deflate) = self.deflate {
+            self.head
+                .headers
+                .insert(header::SEC_WEBSOCKET_EXTENSIONS, deflate.offer());
+        }
+
         // Generate a random key for the `Sec-WebSocket-Key` header.
         // a base64-encoded (see Section 4 of [RFC4648]) value that,
         // when decoded, is 16 bytes in length (RFC 6455)
@@ -327,6 +351,8 @@ impl WebsocketsRequest {
         let head = self.head;
         let max_size = self.max_size;
         let server_mode = self.server_mode;
+        #[cfg(feature = "compress")]
+        let deflate = self.deflate;
 
         let req = ConnectRequest::Tunnel(head, self.addr);
 
@@ -399,15 +425,30 @@ impl WebsocketsRequest {
             return Err(WsClientError::MissingWebSocketAcceptHeader);
         };
 
+        // check extensions accepted by the server
+        #[cfg(feature = "compress")]
+        let deflate = match deflate {
+            Some(deflate) => deflate.accept_response(&head.headers)?,
+            None => None,
+        };
+
         // response and ws framed
         Ok((
             ClientResponse::new(head, Payload::None),
             framed.into_map_codec(|_| {
-                if server_mode {
+                let codec = if server_mode {
                     ws::Codec::new().max_size(max_size)
                 } else {
                     ws::Codec::new().max_size(max_size).client_mode()
-                }
+                };
+
+                #[cfg(feature = "compress")]
+                let codec = match deflate {
+                    Some(deflate) => codec.deflate(deflate),
+                    None => codec,
+                };
+
+                codec
             }),
         ))
     }
@@ -23,7 +23,7 @@ use actix_web::{
     dev::{AppConfig, BodyEncoding},
     http::{header, Cookie},
     middleware::Compress,
-    test, web, App, Error, HttpMessage, HttpRequest, HttpResponse,
+    test, web, App, Error, HttpMessage, HttpRequest, HttpResponse, Responder,
 };
 use awc::error::{JsonPayloadError, PayloadError, SendRequestError};
 
@@ -695,6 +695,82 @@ async fn test_client_streaming_explicit() {
     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 }
 
+#[actix_rt::test]
+async fn test_client_multipart() {
+    use awc::multipart::{Form, Part};
+
+    let srv = test::start(|| {
+        App::new().default_service(web::to(|req: HttpRequest, body: Bytes| {
+            let header = |name| {
+                req.headers()
+                    .get(name)
+                    .map(|val| val.to_str().unwrap().to_owned())
+                    .unwrap_or_default()
+            };
+            HttpResponse::Ok()
+                .insert_header(("x-content-type", header(header::CONTENT_TYPE)))
+                .insert_header(("x-content-length", header(header::CONTENT_LENGTH)))
+                .body(body)
+        }))
+    });
+
+    let file = || {
+        stream::iter(vec![Ok::<_, actix_http::Error>(Bytes::from_static(
+            b"file",
+        ))])
+    };
+    let form = || {
+        Form::new().text("title", "test").part(
+            "file",
+            Part::stream_with_length(file(), 4).file_name("a.txt"),
+        )
+    };
+
+    let boundary = form().boundary().to_owned();
+    let len = form().content_length().unwrap();
+    assert_ne!(form().boundary(), boundary);
+
+    let mut res = srv.post("/").send_multipart(form()).await.unwrap();
+    assert!(res.status().is_success());
+
+    let content_type = res
+        .headers()
+        .get("x-content-type")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/form-data; boundary=")
+        .unwrap()
+        .to_owned();
+    assert_eq!(
+        res.headers().get("x-content-length").unwrap(),
+        &len.to_string()
+    );
+
+    let body = res.body().await.unwrap();
+    assert_eq!(body.len() as u64, len);
+    assert_eq!(
+        body,
+        format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+             test\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             file\r\n\
+             --{b}--\r\n",
+            b = boundary
+        )
+    );
+
+    // parts of unknown length are sent with chunked encoding
+    let form = Form::new().part("file", Part::stream(file()));
+    let res = srv.post("/").send_multipart(form).await.unwrap();
+    assert_eq!(res.headers().get("x-content-length").unwrap(), "");
+}
+
 #[actix_rt::test]
 async fn test_body_streaming_implicit() {
     let srv = test::start(|| {
@@ -787,6 +863,67 @@ async fn test_client_cookie_handling() {
     assert_eq!(c2, cookie2);
 }
 
+#[actix_rt::test]
+async fn test_client_cookie_store() {
+    let srv = test::start(|| {
+        App::new()
+            .route(
+                "/auth/login",
+                web::to(|| {
+                    HttpResponse::Ok()
+                        .cookie(Cookie::build("session", "abc").path("/").finish())
+                        // default path is "/auth"
+                        .cookie(Cookie::build("login", "1").finish())
+                        .finish()
+                }),
+            )
+            .route(
+                "/redirect",
+                web::to(|| {
+                    HttpResponse::Found()
+                        .insert_header((header::LOCATION, "/echo"))
+                        .cookie(Cookie::build("hop", "1").path("/").finish())
+                        .finish()
+                }),
+            )
+            .route(
+                "/echo",
+                web::to(|req: HttpRequest| {
+                    let cookie = req
+                        .headers()
+                        .get(header::COOKIE)
+                        .map(|val| val.to_str().unwrap().to_owned())
+                        .unwrap_or_default();
+                    HttpResponse::Ok().body(cookie)
+                }),
+            )
+    });
+
+    let store = awc::CookieStore::new();
+    let client = awc::Client::builder()
+        .cookie_store(store.clone())
+        .wrap(awc::middleware::Redirect::new())
+        .finish();
+
+    let res = client.get(srv.url("/auth/login")).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    // cookies set by the redirect response are sent to the redirect target
+    let mut res = client.get(srv.url("/redirect")).send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), "session=abc; hop=1");
+
+    // explicit cookies take precedence
+    let mut res = client
+        .get(srv.url("/echo"))
+        .cookie(Cookie::new("session", "explicit"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.body().await.unwrap(), "session=explicit; hop=1");
+
+    assert_eq!(store.cookies().len(), 3);
+}
+
 #[actix_rt::test]
 async fn client_unread_response() {
     let addr = test::unused_addr();
@@ -903,3 +1040,185 @@ async fn test_local_address() {
 
     assert_eq!(res.status(), 200);
 }
+
+#[actix_rt::test]
+async fn test_pool_limit_per_host() {
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(|| async {
+            actix_rt::time::sleep(Duration::from_millis(200)).await;
+            Ok::<_, Error>(HttpResponse::Ok().body("done"))
+        })))
+    });
+
+    let client = awc::Client::builder()
+        .connector(
+            awc::Connector::new()
+                .limit_per_host(1)
+                .wait_timeout(Duration::from_millis(50)),
+        )
+        .finish();
+
+    let slow = async {
+        let mut res = client.get(srv.url("/")).send().await.unwrap();
+        res.body().await.unwrap()
+    };
+
+    let check = async {
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+
+        let hosts = client.pool_stats();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(
+            hosts[0].key().authority().port_u16(),
+            Some(srv.addr().port())
+        );
+        assert_eq!((hosts[0].active(), hosts[0].idle()), (1, 0));
+
+        // the only connection to the host is in use
+        match client.get(srv.url("/")).send().await {
+            Err(SendRequestError::Connect(awc::error::ConnectError::PoolTimeout)) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    };
+
+    let (body, _) = futures_util::future::join(slow, check).await;
+    assert_eq!(body, "done");
+
+    let hosts = client.pool_stats();
+    assert_eq!((hosts[0].active(), hosts[0].idle()), (0, 1));
+}
+
+#[actix_rt::test]
+async fn test_sse_reconnect() {
+    use futures_util::stream::StreamExt;
+
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").to(|req: HttpRequest| {
+            let events = match req.headers().get("last-event-id") {
+                None => vec![
+                    web::SseEvent::data("a").id("1"),
+                    web::SseEvent::data("b")
+                        .id("2")
+                        .retry(Duration::from_millis(10)),
+                ],
+                Some(id) if id == "2" => vec![web::SseEvent::data("c").event("last").id("3")],
+                Some(_) => return HttpResponse::NoContent().finish(),
+            };
+
+            web::Sse::new(stream::iter(events.into_iter().map(Ok::<_, Error>))).respond_to(&req)
+        }))
+    });
+
+    let mut events = srv.get("/").sse().unwrap();
+
+    let mut received = Vec::new();
+    while let Some(event) = events.next().await {
+        let event = event.unwrap();
+        received.push((
+            event.event_type().to_owned(),
+            event.data().to_owned(),
+            event.id().map(ToOwned::to_owned),
+        ));
+    }
+
+    assert_eq!(
+        received,
+        vec![
+            ("message".to_owned(), "a".to_owned(), Some("1".to_owned())),
+            ("message".to_owned(), "b".to_owned(), Some("2".to_owned())),
+            ("last".to_owned(), "c".to_owned(), Some("3".to_owned())),
+        ]
+    );
+    assert_eq!(events.last_event_id(), Some("3"));
+}
+
+#[actix_rt::test]
+async fn test_forward_proxy() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    // the test server acts as the proxy and echoes the request target
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::new(map_config(
+                App::new().default_service(web::to(|req: HttpRequest| {
+                    let auth = req
+                        .headers()
+                        .get(header::PROXY_AUTHORIZATION)
+                        .map(|val| val.to_str().unwrap().to_owned())
+                        .unwrap_or_default();
+                    HttpResponse::Ok().body(format!("{} {}", req.uri(), auth))
+                })),
+                |_| AppConfig::default(),
+            ))
+            .tcp(),
+        )
+    })
+    .await;
+
+    let proxy = awc::Proxy::http(format!("http://user:pass@{}", srv.addr())).unwrap();
+    let client = awc::Client::builder().proxy(proxy).finish();
+
+    for url in &["http://example.com/path?a=1", "http://example.org/"] {
+        let mut res = client.get(*url).send().await.unwrap();
+        let body = res.body().await.unwrap();
+        assert_eq!(body, format!("{} Basic dXNlcjpwYXNz", url));
+    }
+
+    // connections to the proxy are shared by all target hosts
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+}
+
+#[actix_rt::test]
+async fn test_socks5_proxy() {
+    use std::net::{Shutdown, TcpListener, TcpStream};
+
+    let srv = test::start(|| {
+        App::new().default_service(web::to(|req: HttpRequest| {
+            HttpResponse::Ok().body(req.uri().to_string())
+        }))
+    });
+    let target = srv.addr();
+
+    // minimal SOCKS5 proxy that relays every connection to the test server
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+
+        let mut buf = [0; 3];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5, 1, 0]);
+        client.write_all(&[5, 0]).unwrap();
+
+        let mut buf = [0; 18];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..16], b"\x05\x01\x00\x03\x0bexample.com");
+        client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+
+        let mut upstream = TcpStream::connect(target).unwrap();
+        let mut upstream2 = upstream.try_clone().unwrap();
+        let mut client2 = client.try_clone().unwrap();
+        std::thread::spawn(move || {
+            let _ = std::io::copy(&mut upstream2, &mut client2);
+            let _ = client2.shutdown(Shutdown::Write);
+        });
+        let _ = std::io::copy(&mut client, &mut upstream);
+        let _ = upstream.shutdown(Shutdown::Write);
+    });
+
+    let proxy = awc::Proxy::all(format!("socks5://{}", proxy_addr)).unwrap();
+    let client = awc::Client::builder().proxy(proxy).finish();
+
+    let mut res = client.get("http://example.com/path").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // requests through tunnels use the origin form
+    let body = res.body().await.unwrap();
+    assert_eq!(body, "/path");
+}
@@ -66,3 +66,64 @@ async fn test_simple() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
+
+#[actix_rt::test]
+async fn test_deflate() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, mut framed): (Request, Framed<_, _>)| async move {
+                let config = ws::DeflateConfig::new().server_no_context_takeover(true);
+                let (mut res, deflate) =
+                    ws::handshake_response_with_deflate(req.head(), &config);
+                framed
+                    .send(h1::Message::Item((
+                        res.finish().drop_body(),
+                        BodySize::None,
+                    )))
+                    .await?;
+
+                let codec = ws::Codec::new().deflate(deflate.unwrap());
+                let framed = framed.replace_codec(codec);
+                ws::Dispatcher::with(framed, ws_service).await
+            })
+            .finish(|_| ok::<_, Error>(Response::NotFound()))
+            .tcp()
+    })
+    .await;
+
+    let (res, mut framed) = awc::Client::new()
+        .ws(srv.url("/"))
+        .deflate(awc::ws::DeflateConfig::new().server_max_window_bits(10))
+        .connect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        res.headers()
+            .get(actix_http::http::header::SEC_WEBSOCKET_EXTENSIONS)
+            .unwrap(),
+        "permessage-deflate; server_no_context_takeover; server_max_window_bits=10"
+    );
+
+    let text = "{\"event\":\"update\"}".repeat(40);
+    for _ in 0..3 {
+        framed
+            .send(ws::Message::Text(text.clone().into()))
+            .await
+            .unwrap();
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from(text.clone())));
+    }
+
+    let bin = Bytes::from(vec![7u8; 5000]);
+    framed.send(ws::Message::Binary(bin.clone())).await.unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Binary(bin));
+
+    framed
+        .send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
+}
@@ -0,0 +1,174 @@
+//! For middleware documentation, see [`AllowContentType`].
+
+use std::rc::Rc;
+
+use actix_service::{Service, Transform};
+use futures_util::future::{err, ok, Either, Ready};
+use mime::Mime;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    error::{Error, ErrorUnsupportedMediaType},
+    http::header::{CONTENT_LENGTH, TRANSFER_ENCODING},
+    HttpMessage,
+};
+
+/// Middleware for restricting the content types of request bodies.
+///
+/// Requests with a body whose `Content-Type` is missing or not in the allow-list are rejected with
+/// `415 Unsupported Media Type`. Requests without a body are always allowed. An allowed type with
+/// a `*` subtype, like `multipart/*`, matches any subtype; parameters like `charset` are ignored.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::AllowContentType, web, App, HttpResponse};
+///
+/// let app = App::new().service(
+///     web::scope("/api")
+///         .wrap(
+///             AllowContentType::new()
+///                 .allow(mime::APPLICATION_JSON)
+///                 .allow(mime::MULTIPART_FORM_DATA),
+///         )
+///         .route("/upload", web::post().to(|| HttpResponse::Ok())),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AllowContentType {
+    allowed: Rc<Vec<Mime>>,
+}
+
+impl AllowContentType {
+    /// Constructs an `AllowContentType` middleware that allows no request bodies.
+    pub fn new() -> Self {
+        AllowContentType::default()
+    }
+
+    /// Adds a content type to the allow-list.
+    pub fn allow(mut self, mime: Mime) -> Self {
+        Rc::get_mut(&mut self.allowed)
+            .expect("Multiple copies exist")
+            .push(mime);
+        self
+    }
+
+    fn is_allowed(&self, mime: &Mime) -> bool {
+        self.allowed.iter().any(|allowed| {
+            (allowed.type_() == mime::STAR || allowed.type_() == mime.type_())
+                && (allowed.subtype() == mime::STAR || allowed.subtype() == mime.subtype())
+        })
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AllowContentType
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AllowContentTypeMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AllowContentTypeMiddleware {
+            service,
+            inner: self.clone(),
+        })
+    }
+}
+
+/// Content type allow-list middleware service.
+pub struct AllowContentTypeMiddleware<S> {
+    service: S,
+    inner: AllowContentType,
+}
+
+impl<S, B> Service<ServiceRequest> for AllowContentTypeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !has_body(&req) {
+            return Either::Left(self.service.call(req));
+        }
+
+        match req.mime_type() {
+            Ok(Some(ref mime)) if self.inner.is_allowed(mime) => {
+                Either::Left(self.service.call(req))
+            }
+            _ => Either::Right(err(ErrorUnsupportedMediaType(
+                "Unsupported request content type",
+            ))),
+        }
+    }
+}
+
+fn has_body(req: &ServiceRequest) -> bool {
+    let headers = req.headers();
+
+    if headers.contains_key(TRANSFER_ENCODING) {
+        return true;
+    }
+
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|val| val.to_str().ok())
+        .map_or(false, |val| val != "0")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{header::CONTENT_TYPE, StatusCode},
+        test::{self, TestRequest},
+    };
+
+    async fn check<S>(mw: &S, req: TestRequest) -> StatusCode
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse, Error = Error>,
+    {
+        match mw.call(req.to_srv_request()).await {
+            Ok(res) => res.status(),
+            Err(err) => err.as_response_error().status_code(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_allow_content_type() {
+        let mw = AllowContentType::new()
+            .allow(mime::APPLICATION_JSON)
+            .allow("multipart/*".parse().unwrap())
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let status = check(&mw, TestRequest::post()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let req = TestRequest::post()
+            .insert_header((CONTENT_LENGTH, "2"))
+            .insert_header((CONTENT_TYPE, "application/json; charset=utf-8"));
+        assert_eq!(check(&mw, req).await, StatusCode::OK);
+
+        let req = TestRequest::post()
+            .insert_header((TRANSFER_ENCODING, "chunked"))
+            .insert_header((CONTENT_TYPE, "multipart/form-data; boundary=abc"));
+        assert_eq!(check(&mw, req).await, StatusCode::OK);
+
+        let req = TestRequest::post()
+            .insert_header((CONTENT_LENGTH, "2"))
+            .insert_header((CONTENT_TYPE, "text/plain"));
+        assert_eq!(check(&mw, req).await, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let req = TestRequest::post().insert_header((CONTENT_LENGTH, "2"));
+        assert_eq!(check(&mw, req).await, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -0,0 +1,227 @@
+//! For middleware documentation, see [`BodyLimit`].
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::error::PayloadError;
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::future::{err, ok, Either, Ready};
+
+use crate::{
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::Error,
+    http::header::CONTENT_LENGTH,
+    HttpMessage,
+};
+
+/// Middleware for limiting the size of request bodies.
+///
+/// Requests announcing a larger `Content-Length` are rejected with `413 Payload Too Large` before
+/// the wrapped service is called. Bodies of unknown length are counted while they are read; once
+/// the limit is exceeded, the payload stream yields [`PayloadError::Overflow`] to whichever
+/// extractor is reading it, including streaming ones like [`web::Payload`](crate::web::Payload).
+///
+/// When `BodyLimit` middleware are nested, the smallest limit applies.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::BodyLimit, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(BodyLimit::new(64 * 1024))
+///     .service(
+///         web::resource("/avatar")
+///             .wrap(BodyLimit::new(16 * 1024))
+///             .to(|| HttpResponse::Ok()),
+///     );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit {
+    limit: usize,
+}
+
+impl BodyLimit {
+    /// Constructs a `BodyLimit` middleware allowing request bodies of up to `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        BodyLimit { limit }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BodyLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = BodyLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(BodyLimitMiddleware {
+            service,
+            limit: self.limit,
+        })
+    }
+}
+
+/// Body limit middleware service.
+pub struct BodyLimitMiddleware<S> {
+    service: S,
+    limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for BodyLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let len = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<u64>().ok());
+
+        if let Some(len) = len {
+            if len > self.limit as u64 {
+                return Either::Right(err(PayloadError::Overflow.into()));
+            }
+        }
+
+        let payload = req.take_payload();
+        if !matches!(payload, Payload::None) {
+            let payload = LimitedPayload {
+                payload,
+                limit: self.limit,
+                size: 0,
+                overflowed: false,
+            };
+            req.set_payload(Payload::Stream(Box::pin(payload)));
+        }
+
+        Either::Left(self.service.call(req))
+    }
+}
+
+/// Payload stream that errors once more than `limit` bytes have been read.
+struct LimitedPayload {
+    payload: Payload,
+    limit: usize,
+    size: usize,
+    overflowed: bool,
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.overflowed {
+            return Poll::Ready(None);
+        }
+
+        match futures_util::ready!(Pin::new(&mut this.payload).poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                this.size += chunk.len();
+
+                if this.size > this.limit {
+                    this.overflowed = true;
+                    Poll::Ready(Some(Err(PayloadError::Overflow)))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt as _;
+
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    async fn read_all(mut body: web::Payload) -> Result<HttpResponse, Error> {
+        let mut len = 0;
+        while let Some(chunk) = body.next().await {
+            len += chunk?.len();
+        }
+        Ok(HttpResponse::Ok().body(len.to_string()))
+    }
+
+    #[actix_rt::test]
+    async fn test_content_length() {
+        let mw = BodyLimit::new(10)
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .insert_header((CONTENT_LENGTH, "11"))
+            .to_srv_request();
+        let err = mw.call(req).await.err().unwrap();
+        let res = err.as_response_error().error_response();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::default()
+            .insert_header((CONTENT_LENGTH, "10"))
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_streaming() {
+        let srv = test::init_service(
+            App::new()
+                .wrap(BodyLimit::new(10))
+                .route("/", web::post().to(read_all)),
+        )
+        .await;
+
+        let req = TestRequest::post().set_payload("0123456789").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, Bytes::from_static(b"10"));
+
+        let req = TestRequest::post().set_payload("0123456789a").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_nested() {
+        let srv = test::init_service(
+            App::new().wrap(BodyLimit::new(10)).service(
+                web::resource("/small")
+                    .wrap(BodyLimit::new(4))
+                    .route(web::post().to(read_all)),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/small")
+            .set_payload("01234")
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,712 @@
+//! For middleware documentation, see [`Cache`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
+
+use super::conditional_get::body_bytes;
+use crate::{
+    dev::{Body, MessageBody, ResponseBody},
+    error::Error,
+    http::{
+        header::{
+            self, CacheDirective, HeaderName, HeaderValue, AGE, AUTHORIZATION, CACHE_CONTROL,
+            SET_COOKIE, VARY,
+        },
+        HeaderMap, Method, StatusCode,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    HttpMessage, HttpResponse,
+};
+
+/// Middleware for caching responses in memory.
+///
+/// `GET` and `HEAD` responses are cached when their `Cache-Control` header sets a freshness
+/// lifetime using `s-maxage` or `max-age` and does not contain `no-store`, `no-cache` or
+/// `private`. Responses setting cookies, with `Vary: *` or with a streaming body are not cached.
+/// Responses to requests with an `Authorization` header are only cached if marked `public` or
+/// with `s-maxage`. Cached responses are selected by method, URI and the request headers named
+/// in their `Vary` header.
+///
+/// Requests with `Cache-Control: no-cache` or `max-age=0` skip the cache lookup, and requests with
+/// `no-store` bypass the cache entirely.
+///
+/// When a response has a `stale-while-revalidate` directive, it keeps being served for that long
+/// after it becomes stale. One request at a time is forwarded to refresh the entry while all other
+/// requests are served the stale response.
+///
+/// The least recently used entries are evicted once the cache holds `capacity` responses. The
+/// cache outcome is reported in the `X-Cache` response header and can be logged using the
+/// `%{cache}x` [`Logger`](super::Logger) format token.
+///
+/// Each `Cache` constructed with [`new`](Self::new) has its own storage, which is shared by its
+/// clones. Since the server's app factory runs once per worker, construct the middleware outside
+/// of it to share the cache between workers.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::Cache, web, App, HttpResponse, HttpServer};
+///
+/// let cache = Cache::new(1024);
+///
+/// let server = HttpServer::new(move || {
+///     App::new().wrap(cache.clone()).route(
+///         "/",
+///         web::get().to(|| {
+///             HttpResponse::Ok()
+///                 .insert_header(("cache-control", "max-age=60, stale-while-revalidate=30"))
+///                 .body("hello")
+///         }),
+///     )
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Cache {
+    store: Arc<Mutex<Store>>,
+    max_entry_size: usize,
+    status_header: Option<HeaderName>,
+}
+
+impl Cache {
+    /// Constructs a `Cache` middleware holding up to `capacity` responses.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Cache capacity must be greater than zero");
+
+        Cache {
+            store: Arc::new(Mutex::new(Store {
+                capacity,
+                ..Store::default()
+            })),
+            max_entry_size: 1024 * 1024,
+            status_header: Some(HeaderName::from_static("x-cache")),
+        }
+    }
+
+    /// Sets the maximum body size of cached responses.
+    ///
+    /// Default is 1MiB.
+    pub fn max_entry_size(mut self, size: usize) -> Self {
+        self.max_entry_size = size;
+        self
+    }
+
+    /// Sets the name of the response header reporting the cache outcome.
+    ///
+    /// Default is `X-Cache`.
+    ///
+    /// # Panics
+    /// Panics if `name` is not a valid header name.
+    pub fn status_header(mut self, name: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes()).expect("Invalid header name");
+        self.status_header = Some(name);
+        self
+    }
+
+    /// Disables the response header reporting the cache outcome.
+    pub fn disable_status_header(mut self) -> Self {
+        self.status_header = None;
+        self
+    }
+}
+
+/// Outcome of a [`Cache`] lookup.
+///
+/// Stored in the request extensions of `GET` and `HEAD` requests handled by the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Response was served from the cache.
+    Hit,
+    /// Response was served from the cache after becoming stale.
+    Stale,
+    /// Response was produced by the wrapped service.
+    Miss,
+}
+
+impl CacheStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Stale => "STALE",
+            CacheStatus::Miss => "MISS",
+        }
+    }
+}
+
+impl fmt::Display for CacheStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Default)]
+struct Store {
+    capacity: usize,
+    len: usize,
+    tick: u64,
+    entries: HashMap<String, Vec<Entry>>,
+    /// Key of each entry, ordered from least to most recently used.
+    lru: BTreeMap<u64, String>,
+}
+
+struct Entry {
+    /// Request header values selected by the response's `Vary` header.
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored: Instant,
+    ttl: Duration,
+    stale_ttl: Duration,
+    revalidating: bool,
+    tick: u64,
+}
+
+impl Entry {
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+}
+
+enum Lookup {
+    Fresh(HttpResponse),
+    Stale(HttpResponse),
+    Revalidate,
+    Miss,
+}
+
+impl Store {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn lookup(&mut self, key: &str, headers: &HeaderMap, now: Instant) -> Lookup {
+        let tick = self.next_tick();
+
+        let entry = match self.entries.get_mut(key) {
+            Some(entries) => match entries.iter_mut().find(|entry| entry.matches(headers)) {
+                Some(entry) => entry,
+                None => return Lookup::Miss,
+            },
+            None => return Lookup::Miss,
+        };
+
+        let age = now.saturating_duration_since(entry.stored);
+
+        let lookup = if age <= entry.ttl {
+            Lookup::Fresh(entry.to_response(age))
+        } else if age <= entry.ttl + entry.stale_ttl {
+            if entry.revalidating {
+                Lookup::Stale(entry.to_response(age))
+            } else {
+                entry.revalidating = true;
+                Lookup::Revalidate
+            }
+        } else {
+            Lookup::Miss
+        };
+
+        self.lru.remove(&entry.tick);
+        entry.tick = tick;
+        self.lru.insert(tick, key.to_owned());
+
+        lookup
+    }
+
+    /// Allows the entry selected by the request headers to be revalidated again.
+    fn end_revalidation(&mut self, key: &str, headers: &HeaderMap) {
+        if let Some(entries) = self.entries.get_mut(key) {
+            if let Some(entry) = entries.iter_mut().find(|entry| entry.matches(headers)) {
+                entry.revalidating = false;
+            }
+        }
+    }
+
+    /// Stores the entry, replacing entries previously selected by the request headers.
+    fn insert(&mut self, key: &str, req_headers: &HeaderMap, mut entry: Entry) {
+        self.remove(key, |prev| prev.matches(req_headers));
+
+        entry.tick = self.next_tick();
+        self.lru.insert(entry.tick, key.to_owned());
+        self.entries.entry(key.to_owned()).or_default().push(entry);
+        self.len += 1;
+
+        while self.len > self.capacity {
+            let (tick, key) = match self.lru.iter().next() {
+                Some((tick, key)) => (*tick, key.clone()),
+                None => break,
+            };
+
+            self.remove(&key, |entry| entry.tick == tick);
+        }
+    }
+
+    fn remove(&mut self, key: &str, f: impl Fn(&Entry) -> bool) {
+        let entries = match self.entries.get_mut(key) {
+            Some(entries) => entries,
+            None => return,
+        };
+
+        let lru = &mut self.lru;
+        let len = &mut self.len;
+
+        entries.retain(|entry| {
+            if f(entry) {
+                lru.remove(&entry.tick);
+                *len -= 1;
+                false
+            } else {
+                true
+            }
+        });
+
+        if entries.is_empty() {
+            self.entries.remove(key);
+        }
+    }
+}
+
+impl Entry {
+    fn to_response(&self, age: Duration) -> HttpResponse {
+        let mut res = HttpResponse::with_body(self.status, Body::Bytes(self.body.clone()));
+
+        for (name, value) in self.headers.iter() {
+            res.headers_mut().append(name.clone(), value.clone());
+        }
+
+        res.headers_mut()
+            .insert(AGE, HeaderValue::from(age.as_secs()));
+
+        res
+    }
+}
+
+fn cache_directives(headers: &HeaderMap) -> Vec<CacheDirective> {
+    header::from_comma_delimited(headers.get_all(CACHE_CONTROL)).unwrap_or_default()
+}
+
+/// Returns whether the status code is cacheable by default, per RFC 7231 §6.1.
+fn is_cacheable_status(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+impl Cache {
+    /// Creates a cache entry from the response, if it may be stored.
+    fn to_entry<B: MessageBody + 'static>(
+        &self,
+        req_headers: &HeaderMap,
+        res: &ServiceResponse<B>,
+    ) -> Option<Entry> {
+        if !is_cacheable_status(res.status()) || res.headers().contains_key(SET_COOKIE) {
+            return None;
+        }
+
+        let mut ttl = None;
+        let mut s_maxage = false;
+        let mut public = false;
+        let mut stale_ttl = Duration::from_secs(0);
+
+        for directive in cache_directives(res.headers()) {
+            match directive {
+                CacheDirective::NoStore | CacheDirective::NoCache | CacheDirective::Private => {
+                    return None
+                }
+                CacheDirective::Public => public = true,
+                CacheDirective::SMaxAge(secs) => {
+                    ttl = Some(secs);
+                    s_maxage = true;
+                }
+                CacheDirective::MaxAge(secs) if !s_maxage => ttl = Some(secs),
+                CacheDirective::Extension(ref name, Some(ref secs))
+                    if name == "stale-while-revalidate" =>
+                {
+                    if let Ok(secs) = secs.parse() {
+                        stale_ttl = Duration::from_secs(secs);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let ttl = Duration::from_secs(u64::from(ttl?));
+        if ttl.as_secs() == 0 && stale_ttl.as_secs() == 0 {
+            return None;
+        }
+
+        if req_headers.contains_key(AUTHORIZATION) && !public && !s_maxage {
+            return None;
+        }
+
+        let mut vary = Vec::new();
+        for val in res.headers().get_all(VARY) {
+            for name in val.to_str().ok()?.split(',') {
+                let name = name.trim();
+
+                if name == "*" {
+                    return None;
+                }
+
+                if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                    let value = req_headers.get(&name).cloned();
+                    vary.push((name, value));
+                }
+            }
+        }
+
+        let body = body_bytes(res.response().body())?;
+        if body.len() > self.max_entry_size {
+            return None;
+        }
+
+        Some(Entry {
+            vary,
+            status: res.status(),
+            headers: res.headers().clone(),
+            body: Bytes::copy_from_slice(body),
+            stored: Instant::now(),
+            ttl,
+            stale_ttl,
+            revalidating: false,
+            tick: 0,
+        })
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Cache
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CacheMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CacheMiddleware {
+            service: Rc::new(service),
+            cache: self.clone(),
+        })
+    }
+}
+
+/// Cache middleware service.
+pub struct CacheMiddleware<S> {
+    service: Rc<S>,
+    cache: Cache,
+}
+
+impl<S, B> Service<ServiceRequest> for CacheMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if *req.method() != Method::GET && *req.method() != Method::HEAD {
+            return self.service.call(req).boxed_local();
+        }
+
+        let service = self.service.clone();
+        let cache = self.cache.clone();
+
+        async move {
+            let key = format!("{} {}", req.method(), req.uri());
+
+            let mut no_store = false;
+            let mut no_lookup = false;
+
+            for directive in cache_directives(req.headers()) {
+                match directive {
+                    CacheDirective::NoStore => no_store = true,
+                    CacheDirective::NoCache | CacheDirective::MaxAge(0) => no_lookup = true,
+                    _ => {}
+                }
+            }
+
+            let lookup = if no_store || no_lookup {
+                Lookup::Miss
+            } else {
+                let now = Instant::now();
+                cache.store.lock().unwrap().lookup(&key, req.headers(), now)
+            };
+
+            let (status, res) = match lookup {
+                Lookup::Fresh(res) => (CacheStatus::Hit, res),
+                Lookup::Stale(res) => (CacheStatus::Stale, res),
+                Lookup::Revalidate | Lookup::Miss => {
+                    let req_headers = req.headers().clone();
+
+                    // lets a later request refresh the entry if this one fails or is cancelled
+                    let _revalidation = match lookup {
+                        Lookup::Revalidate => Some(Revalidation {
+                            cache: &cache,
+                            key: &key,
+                            headers: &req_headers,
+                        }),
+                        _ => None,
+                    };
+
+                    let mut res = service.call(req).await?;
+
+                    if !no_store {
+                        let mut store = cache.store.lock().unwrap();
+
+                        match cache.to_entry(&req_headers, &res) {
+                            Some(entry) => store.insert(&key, &req_headers, entry),
+                            None => store.remove(&key, |entry| entry.matches(&req_headers)),
+                        }
+                    }
+
+                    res.request().extensions_mut().insert(CacheStatus::Miss);
+                    if let Some(ref name) = cache.status_header {
+                        res.headers_mut()
+                            .insert(name.clone(), HeaderValue::from_static("MISS"));
+                    }
+
+                    return Ok(res);
+                }
+            };
+
+            req.extensions_mut().insert(status);
+
+            let mut res = res.map_body(|_, body| match body {
+                ResponseBody::Body(body) | ResponseBody::Other(body) => {
+                    ResponseBody::Other(body)
+                }
+            });
+
+            if let Some(ref name) = cache.status_header {
+                res.headers_mut()
+                    .insert(name.clone(), HeaderValue::from_static(status.as_str()));
+            }
+
+            Ok(req.into_response(res))
+        }
+        .boxed_local()
+    }
+}
+
+/// Ends the revalidation of a stale entry when dropped.
+///
+/// Entries replaced by the refreshed response are not revalidating, so the guard only has an effect
+/// when the refresh failed or was cancelled.
+struct Revalidation<'a> {
+    cache: &'a Cache,
+    key: &'a str,
+    headers: &'a HeaderMap,
+}
+
+impl Drop for Revalidation<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut store) = self.cache.store.lock() {
+            store.end_revalidation(self.key, self.headers);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_service::IntoService;
+
+    use futures_util::future::err;
+
+    use super::*;
+    use crate::{
+        error::ErrorInternalServerError,
+        http::header::ACCEPT_LANGUAGE,
+        test::{self, TestRequest},
+    };
+
+    fn counting_service(
+        cache_control: &'static str,
+    ) -> (
+        Rc<Cell<usize>>,
+        impl Service<ServiceRequest, Response = ServiceResponse, Error = Error>,
+    ) {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        let srv = move |req: ServiceRequest| {
+            counter.set(counter.get() + 1);
+            let body = format!("response {}", counter.get());
+
+            ok(req.into_response(
+                HttpResponse::Ok()
+                    .insert_header((CACHE_CONTROL, cache_control))
+                    .insert_header((VARY, "accept-language"))
+                    .body(body),
+            ))
+        };
+
+        (count, srv.into_service())
+    }
+
+    async fn get<S>(srv: &S, req: TestRequest) -> (String, String)
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse, Error = Error>,
+    {
+        let res = test::call_service(srv, req.to_srv_request()).await;
+        let status = res.headers().get("x-cache").unwrap().to_str().unwrap();
+        let status = status.to_owned();
+        let body = test::read_body(res).await;
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[actix_rt::test]
+    async fn test_hit_and_miss() {
+        let (count, srv) = counting_service("max-age=60");
+        let mw = Cache::new(10).new_transform(srv).await.unwrap();
+
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("MISS".to_owned(), "response 1".to_owned()));
+
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("HIT".to_owned(), "response 1".to_owned()));
+
+        // different vary header value
+        let req = TestRequest::default().insert_header((ACCEPT_LANGUAGE, "de"));
+        let res = get(&mw, req).await;
+        assert_eq!(res, ("MISS".to_owned(), "response 2".to_owned()));
+
+        // request skips lookup
+        let req = TestRequest::default().insert_header((CACHE_CONTROL, "no-cache"));
+        let res = get(&mw, req).await;
+        assert_eq!(res, ("MISS".to_owned(), "response 3".to_owned()));
+
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("HIT".to_owned(), "response 3".to_owned()));
+
+        // other methods are not cached
+        let req = TestRequest::post().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert!(!res.headers().contains_key("x-cache"));
+        assert_eq!(count.get(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn test_not_stored() {
+        for cache_control in &["no-store", "private, max-age=60", "no-cache", "public"] {
+            let (_, srv) = counting_service(cache_control);
+            let mw = Cache::new(10).new_transform(srv).await.unwrap();
+
+            get(&mw, TestRequest::default()).await;
+            let (status, _) = get(&mw, TestRequest::default()).await;
+            assert_eq!(status, "MISS", "{}", cache_control);
+        }
+
+        let (_, srv) = counting_service("max-age=60");
+        let mw = Cache::new(10).new_transform(srv).await.unwrap();
+        let req = || TestRequest::default().insert_header((AUTHORIZATION, "Bearer abc"));
+        get(&mw, req()).await;
+        assert_eq!(get(&mw, req()).await.0, "MISS");
+
+        let (_, srv) = counting_service("s-maxage=60");
+        let mw = Cache::new(10).new_transform(srv).await.unwrap();
+        get(&mw, req()).await;
+        assert_eq!(get(&mw, req()).await.0, "HIT");
+    }
+
+    #[actix_rt::test]
+    async fn test_lru_eviction() {
+        let (count, srv) = counting_service("max-age=60");
+        let mw = Cache::new(2).new_transform(srv).await.unwrap();
+
+        let req = |path| TestRequest::with_uri(path);
+
+        get(&mw, req("/a")).await;
+        get(&mw, req("/b")).await;
+        assert_eq!(get(&mw, req("/a")).await.0, "HIT");
+
+        // evicts "/b", the least recently used entry
+        get(&mw, req("/c")).await;
+        assert_eq!(get(&mw, req("/a")).await.0, "HIT");
+        assert_eq!(get(&mw, req("/b")).await.0, "MISS");
+        assert_eq!(count.get(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn test_stale_while_revalidate() {
+        let store = Cache::new(10);
+        let (_, srv) = counting_service("max-age=0, stale-while-revalidate=60");
+        let mw = store.clone().new_transform(srv).await.unwrap();
+
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("MISS".to_owned(), "response 1".to_owned()));
+
+        // simulate a revalidation that is in progress
+        {
+            let mut store = store.store.lock().unwrap();
+            let now = Instant::now() + Duration::from_secs(1);
+            let lookup = store.lookup("GET /", &HeaderMap::new(), now);
+            assert!(matches!(lookup, Lookup::Revalidate));
+        }
+
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("STALE".to_owned(), "response 1".to_owned()));
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_revalidation() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        // the first refresh fails
+        let srv = move |req: ServiceRequest| {
+            counter.set(counter.get() + 1);
+
+            if counter.get() == 2 {
+                return err(ErrorInternalServerError("refresh failed"));
+            }
+
+            ok(req.into_response(
+                HttpResponse::Ok()
+                    .insert_header((CACHE_CONTROL, "max-age=0, stale-while-revalidate=60"))
+                    .body(format!("response {}", counter.get())),
+            ))
+        };
+
+        let mw = Cache::new(10)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("MISS".to_owned(), "response 1".to_owned()));
+
+        actix_rt::time::sleep(Duration::from_millis(10)).await;
+        assert!(mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .is_err());
+
+        // the entry is refreshed by the next request instead of being served stale
+        let res = get(&mw, TestRequest::default()).await;
+        assert_eq!(res, ("MISS".to_owned(), "response 3".to_owned()));
+        assert_eq!(count.get(), 3);
+    }
+}
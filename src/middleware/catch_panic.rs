@@ -0,0 +1,328 @@
+//! For middleware documentation, see [`CatchPanic`].
+
+use std::{
+    any::Any,
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_service::{Service, Transform};
+use futures_util::future::{ok, Ready};
+use log::error;
+use pin_project::pin_project;
+
+use crate::{
+    error::{Error, InternalError},
+    http::StatusCode,
+    service::{ServiceRequest, ServiceResponse},
+    HttpResponse,
+};
+
+/// Middleware for turning panics in the wrapped service into error responses.
+///
+/// Without this middleware, a panicking handler tears down the connection it was called on. With
+/// it, the panic is caught, reported to a hook and answered with a `500 Internal Server Error`
+/// response; use [`error_response`](Self::error_response) to change the response.
+///
+/// The default hook logs the panic message and request line with the ERROR level. Use
+/// [`on_panic`](Self::on_panic) to report panics elsewhere.
+///
+/// Register `CatchPanic` as the outermost middleware so that panics in other middleware are caught
+/// too. Note that the default panic hook of the standard library still prints panics to stderr.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::CatchPanic, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(CatchPanic::new().on_panic(|report| {
+///         eprintln!("{} panicked: {:?}", report.request_line(), report.message());
+///     }))
+///     .route("/", web::get().to(|| async { panic!("oops"); "" }));
+/// ```
+#[derive(Clone)]
+pub struct CatchPanic(Rc<Inner>);
+
+struct Inner {
+    error: Box<dyn Fn() -> Error>,
+    hook: Box<dyn Fn(&PanicReport<'_>)>,
+}
+
+impl Default for CatchPanic {
+    fn default() -> Self {
+        CatchPanic::new()
+    }
+}
+
+impl CatchPanic {
+    /// Constructs a `CatchPanic` middleware.
+    pub fn new() -> Self {
+        CatchPanic(Rc::new(Inner {
+            error: Box::new(|| {
+                InternalError::new("Internal Server Error", StatusCode::INTERNAL_SERVER_ERROR)
+                    .into()
+            }),
+            hook: Box::new(|report| {
+                error!(
+                    "Panic in handler for \"{}\": {}",
+                    report.request_line(),
+                    report.message().unwrap_or("Box<dyn Any>")
+                );
+            }),
+        }))
+    }
+
+    /// Sets a function that creates the response sent when a panic is caught.
+    pub fn error_response<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> HttpResponse + 'static,
+    {
+        Rc::get_mut(&mut self.0).unwrap().error =
+            Box::new(move || InternalError::from_response("Handler panicked", f()).into());
+        self
+    }
+
+    /// Sets a function that is called with each caught panic.
+    pub fn on_panic<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PanicReport<'_>) + 'static,
+    {
+        Rc::get_mut(&mut self.0).unwrap().hook = Box::new(f);
+        self
+    }
+}
+
+impl Inner {
+    fn report(&self, request_line: &str, payload: Box<dyn Any + Send>) -> Error {
+        (self.hook)(&PanicReport {
+            request_line,
+            payload: &*payload,
+        });
+
+        (self.error)()
+    }
+}
+
+/// Details of a panic caught by [`CatchPanic`].
+pub struct PanicReport<'a> {
+    request_line: &'a str,
+    payload: &'a (dyn Any + Send),
+}
+
+impl<'a> PanicReport<'a> {
+    /// Returns the first line of the request, e.g. `GET /test HTTP/1.1`.
+    pub fn request_line(&self) -> &str {
+        self.request_line
+    }
+
+    /// Returns the panic payload.
+    pub fn payload(&self) -> &(dyn Any + Send) {
+        self.payload
+    }
+
+    /// Returns the panic message, if the payload is a string.
+    pub fn message(&self) -> Option<&str> {
+        if let Some(msg) = self.payload.downcast_ref::<&'static str>() {
+            Some(msg)
+        } else {
+            self.payload.downcast_ref::<String>().map(String::as_str)
+        }
+    }
+}
+
+impl fmt::Debug for PanicReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicReport")
+            .field("request_line", &self.request_line)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CatchPanic
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CatchPanicMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CatchPanicMiddleware {
+            service,
+            inner: self.0.clone(),
+        })
+    }
+}
+
+/// Panic catching middleware service.
+pub struct CatchPanicMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for CatchPanicMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = CatchPanicFuture<S>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_line = if req.query_string().is_empty() {
+            format!("{} {} {:?}", req.method(), req.path(), req.version())
+        } else {
+            format!(
+                "{} {}?{} {:?}",
+                req.method(),
+                req.path(),
+                req.query_string(),
+                req.version()
+            )
+        };
+
+        let service = &self.service;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| service.call(req))) {
+            Ok(fut) => CatchPanicFuture {
+                fut: Some(fut),
+                error: None,
+                request_line,
+                inner: self.inner.clone(),
+            },
+            Err(payload) => CatchPanicFuture {
+                fut: None,
+                error: Some(self.inner.report(&request_line, payload)),
+                request_line,
+                inner: self.inner.clone(),
+            },
+        }
+    }
+}
+
+#[pin_project]
+pub struct CatchPanicFuture<S: Service<ServiceRequest>> {
+    #[pin]
+    fut: Option<S::Future>,
+    error: Option<Error>,
+    request_line: String,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Future for CatchPanicFuture<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        let fut = match this.fut.as_mut().as_pin_mut() {
+            Some(fut) => fut,
+            None => return Poll::Ready(Err(this.error.take().unwrap())),
+        };
+
+        match panic::catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+            Ok(res) => res,
+            Err(payload) => {
+                // the panicked future must not be polled again
+                this.fut.set(None);
+                Poll::Ready(Err(this.inner.report(this.request_line, payload)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    fn capture() -> (CatchPanic, Rc<RefCell<Vec<(String, String)>>>) {
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let hook_reports = reports.clone();
+
+        let mw = CatchPanic::new().on_panic(move |report| {
+            hook_reports.borrow_mut().push((
+                report.request_line().to_owned(),
+                report.message().unwrap().to_owned(),
+            ));
+        });
+
+        (mw, reports)
+    }
+
+    #[actix_rt::test]
+    async fn test_catch_panic() {
+        let (mw, reports) = capture();
+
+        let srv = test::init_service(
+            App::new()
+                .wrap(mw)
+                .route("/ok", web::get().to(|| async { "ok" }))
+                .route(
+                    "/panic",
+                    web::get().to(|| async {
+                        if true {
+                            panic!("handler failed: {}", 42);
+                        }
+                        "unreachable"
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/panic?a=1").to_request();
+        let err = srv.call(req).await.err().unwrap();
+        let res = err.as_response_error().error_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = TestRequest::with_uri("/ok").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        assert_eq!(
+            *reports.borrow(),
+            vec![(
+                "GET /panic?a=1 HTTP/1.1".to_owned(),
+                "handler failed: 42".to_owned()
+            )]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_panic_in_call() {
+        let (mw, reports) = capture();
+
+        let srv = |_: ServiceRequest| -> Ready<Result<ServiceResponse, Error>> {
+            panic!("call failed")
+        };
+
+        let mw = mw
+            .error_response(|| HttpResponse::ServiceUnavailable().finish())
+            .new_transform(actix_service::fn_service(srv))
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let err = mw.call(req).await.err().unwrap();
+        let res = err.as_response_error().error_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(reports.borrow()[0].1, "call failed");
+    }
+}
@@ -1,18 +1,18 @@
 //! For middleware documentation, see [`Compress`].
 
 use std::{
-    cmp,
+    cmp, fmt,
     future::Future,
     marker::PhantomData,
     pin::Pin,
-    str::FromStr,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 use actix_http::{
-    body::MessageBody,
+    body::{BodySize, MessageBody},
     encoding::Encoder,
-    http::header::{ContentEncoding, ACCEPT_ENCODING},
+    http::header::{ContentEncoding, ACCEPT_ENCODING, CONTENT_TYPE},
     Error,
 };
 use actix_service::{Service, Transform};
@@ -23,6 +23,7 @@ use pin_project::pin_project;
 use crate::{
     dev::BodyEncoding,
     service::{ServiceRequest, ServiceResponse},
+    HttpResponse,
 };
 
 /// Middleware for compressing response payloads.
@@ -38,13 +39,101 @@ use crate::{
 ///     .wrap(middleware::Compress::default())
 ///     .default_service(web::to(|| HttpResponse::NotFound()));
 /// ```
+///
+/// Compression can be tuned further:
+/// ```rust
+/// use actix_web::{http::ContentEncoding, middleware::Compress};
+///
+/// let compress = Compress::default()
+///     .level(ContentEncoding::Gzip, 6)
+///     .min_size(1024)
+///     .content_type_filter(|mime| mime.type_() != mime::IMAGE)
+///     .preference(vec![ContentEncoding::Gzip, ContentEncoding::Br]);
+/// ```
 #[derive(Debug, Clone)]
-pub struct Compress(ContentEncoding);
+pub struct Compress(Arc<Inner>);
+
+#[derive(Clone)]
+struct Inner {
+    encoding: ContentEncoding,
+    preference: Vec<ContentEncoding>,
+    levels: Vec<(ContentEncoding, u32)>,
+    min_size: u64,
+    content_type_filter: Option<Arc<dyn Fn(&mime::Mime) -> bool + Send + Sync>>,
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("encoding", &self.encoding)
+            .field("preference", &self.preference)
+            .field("levels", &self.levels)
+            .field("min_size", &self.min_size)
+            .field("content_type_filter", &self.content_type_filter.is_some())
+            .finish()
+    }
+}
 
 impl Compress {
     /// Create new `Compress` middleware with the specified encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress(encoding)
+        Compress(Arc::new(Inner {
+            encoding,
+            preference: default_preference(),
+            levels: Vec::new(),
+            min_size: 0,
+            content_type_filter: None,
+        }))
+    }
+
+    /// Set compression level used for the given encoding.
+    ///
+    /// Levels are interpreted by each algorithm and clamped to their valid range: 0-9 for gzip
+    /// and deflate, 0-11 for brotli and 1-22 for zstd. By default a fast level is used.
+    pub fn level(mut self, encoding: ContentEncoding, level: u32) -> Self {
+        let inner = Arc::make_mut(&mut self.0);
+        inner.levels.retain(|(enc, _)| *enc != encoding);
+        inner.levels.push((encoding, level));
+        self
+    }
+
+    /// Set minimum response body size, in bytes, for compression to be applied.
+    ///
+    /// Bodies with a known size smaller than this are sent uncompressed. Streaming bodies of
+    /// unknown size are always eligible for compression.
+    ///
+    /// By default, all bodies are eligible.
+    pub fn min_size(mut self, bytes: u64) -> Self {
+        Arc::make_mut(&mut self.0).min_size = bytes;
+        self
+    }
+
+    /// Set predicate deciding if a response should be compressed based on its content type.
+    ///
+    /// The predicate receives the response's parsed `Content-Type` and should return `true` to
+    /// allow compression. Responses without a valid `Content-Type` header are always eligible.
+    ///
+    /// By default, all content types are eligible.
+    pub fn content_type_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&mime::Mime) -> bool + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.0).content_type_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Set server preferred order of encodings.
+    ///
+    /// The order is used to choose between encodings the client gives equal quality values in
+    /// its `Accept-Encoding` header. Encodings not listed are never selected automatically.
+    ///
+    /// Defaults to zstd (when the `compress-zstd` feature is enabled), brotli, gzip and deflate.
+    pub fn preference<I>(mut self, encodings: I) -> Self
+    where
+        I: IntoIterator<Item = ContentEncoding>,
+    {
+        Arc::make_mut(&mut self.0).preference = encodings.into_iter().collect();
+        self
     }
 }
 
@@ -54,6 +143,48 @@ impl Default for Compress {
     }
 }
 
+impl Inner {
+    fn level(&self, encoding: ContentEncoding) -> Option<u32> {
+        self.levels
+            .iter()
+            .find(|(enc, _)| *enc == encoding)
+            .map(|(_, level)| *level)
+    }
+
+    /// Checks response size and content type against configured thresholds.
+    fn is_compressible<B: MessageBody>(&self, res: &HttpResponse<B>) -> bool {
+        if let BodySize::Sized(size) = res.body().size() {
+            if size < self.min_size {
+                return false;
+            }
+        }
+
+        if let Some(ref filter) = self.content_type_filter {
+            let mime = res
+                .headers()
+                .get(&CONTENT_TYPE)
+                .and_then(|val| val.to_str().ok())
+                .and_then(|val| val.parse::<mime::Mime>().ok());
+
+            if let Some(mime) = mime {
+                return filter(&mime);
+            }
+        }
+
+        true
+    }
+}
+
+fn default_preference() -> Vec<ContentEncoding> {
+    vec![
+        #[cfg(feature = "compress-zstd")]
+        ContentEncoding::Zstd,
+        ContentEncoding::Br,
+        ContentEncoding::Gzip,
+        ContentEncoding::Deflate,
+    ]
+}
+
 impl<S, B> Transform<S, ServiceRequest> for Compress
 where
     B: MessageBody,
@@ -68,14 +199,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(CompressMiddleware {
             service,
-            encoding: self.0,
+            inner: self.0.clone(),
         })
     }
 }
 
 pub struct CompressMiddleware<S> {
     service: S,
-    encoding: ContentEncoding,
+    inner: Arc<Inner>,
 }
 
 impl<S, B> Service<ServiceRequest> for CompressMiddleware<S>
@@ -94,7 +225,7 @@ where
         // negotiate content-encoding
         let encoding = if let Some(val) = req.headers().get(&ACCEPT_ENCODING) {
             if let Ok(enc) = val.to_str() {
-                AcceptEncoding::parse(enc, self.encoding)
+                AcceptEncoding::parse(enc, self.inner.encoding, &self.inner.preference)
             } else {
                 ContentEncoding::Identity
             }
@@ -104,6 +235,7 @@ where
 
         CompressResponse {
             encoding,
+            inner: self.inner.clone(),
             fut: self.service.call(req),
             _phantom: PhantomData,
         }
@@ -119,6 +251,7 @@ where
     #[pin]
     fut: S::Future,
     encoding: ContentEncoding,
+    inner: Arc<Inner>,
     _phantom: PhantomData<B>,
 }
 
@@ -136,13 +269,17 @@ where
             Ok(resp) => {
                 let enc = if let Some(enc) = resp.response().get_encoding() {
                     enc
-                } else {
+                } else if this.inner.is_compressible(resp.response()) {
                     *this.encoding
+                } else {
+                    ContentEncoding::Identity
                 };
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                let level = this.inner.level(enc);
+
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    Encoder::response_with_level(enc, level, head, body)
+                })))
             }
             Err(e) => Poll::Ready(Err(e)),
         }
@@ -152,6 +289,7 @@ where
 struct AcceptEncoding {
     encoding: ContentEncoding,
     quality: f64,
+    preference: usize,
 }
 
 impl Eq for AcceptEncoding {}
@@ -164,7 +302,7 @@ impl Ord for AcceptEncoding {
         } else if self.quality < other.quality {
             cmp::Ordering::Greater
         } else {
-            cmp::Ordering::Equal
+            self.preference.cmp(&other.preference)
         }
     }
 }
@@ -177,42 +315,196 @@ impl PartialOrd for AcceptEncoding {
 
 impl PartialEq for AcceptEncoding {
     fn eq(&self, other: &AcceptEncoding) -> bool {
-        self.quality == other.quality
+        self.quality == other.quality && self.preference == other.preference
     }
 }
 
 impl AcceptEncoding {
-    fn new(tag: &str) -> Option<AcceptEncoding> {
+    fn new(tag: &str, preference: &[ContentEncoding]) -> Option<AcceptEncoding> {
         let parts: Vec<&str> = tag.split(';').collect();
         let encoding = match parts.len() {
             0 => return None,
+            // `Auto` stands for the `*` wildcard
+            _ if parts[0] == "*" => ContentEncoding::Auto,
             _ => ContentEncoding::from(parts[0]),
         };
+
+        // unknown codings parse as identity; skip them instead
+        if encoding == ContentEncoding::Identity && !parts[0].eq_ignore_ascii_case("identity") {
+            return None;
+        }
+
+        if !is_supported(encoding) {
+            return None;
+        }
+
         let quality = match parts.len() {
-            1 => encoding.quality(),
-            _ => f64::from_str(parts[1]).unwrap_or(0.0),
+            1 => 1.0,
+            _ => parts[1]
+                .trim_start_matches("q=")
+                .trim_start_matches("Q=")
+                .parse::<f64>()
+                .unwrap_or(0.0),
         };
-        Some(AcceptEncoding { encoding, quality })
+
+        // identity and unknown encodings rank after every preferred encoding
+        let preference = preference
+            .iter()
+            .position(|enc| *enc == encoding)
+            .unwrap_or(preference.len());
+
+        Some(AcceptEncoding {
+            encoding,
+            quality,
+            preference,
+        })
     }
 
-    /// Parse a raw Accept-Encoding header value into an ordered list.
-    pub fn parse(raw: &str, encoding: ContentEncoding) -> ContentEncoding {
+    /// Parse a raw Accept-Encoding header value and select the best encoding.
+    ///
+    /// Ties between equal quality values are broken using the `preference` order.
+    pub fn parse(
+        raw: &str,
+        encoding: ContentEncoding,
+        preference: &[ContentEncoding],
+    ) -> ContentEncoding {
         let mut encodings: Vec<_> = raw
             .replace(' ', "")
             .split(',')
-            .map(|l| AcceptEncoding::new(l))
+            .filter_map(|l| AcceptEncoding::new(l, preference))
             .collect();
+
+        // the wildcard only matches encodings not listed explicitly, even with a zero quality
+        let listed: Vec<_> = encodings.iter().map(|enc| enc.encoding).collect();
+
+        encodings.retain(|enc| enc.quality > 0.0);
         encodings.sort();
 
         for enc in encodings {
-            if let Some(enc) = enc {
-                if encoding == ContentEncoding::Auto {
+            if enc.encoding == ContentEncoding::Auto {
+                let matched = if encoding == ContentEncoding::Auto {
+                    preference
+                        .iter()
+                        .copied()
+                        .find(|enc| is_supported(*enc) && !listed.contains(enc))
+                } else if !listed.contains(&encoding) {
+                    Some(encoding)
+                } else {
+                    None
+                };
+
+                if let Some(enc) = matched {
+                    return enc;
+                }
+            } else if encoding == ContentEncoding::Auto {
+                if enc.encoding == ContentEncoding::Identity
+                    || preference.contains(&enc.encoding)
+                {
                     return enc.encoding;
-                } else if encoding == enc.encoding {
-                    return encoding;
                 }
+            } else if encoding == enc.encoding {
+                return encoding;
             }
         }
+
         ContentEncoding::Identity
     }
 }
+
+/// Checks if support for the encoding is compiled in.
+fn is_supported(encoding: ContentEncoding) -> bool {
+    cfg!(feature = "compress-zstd") || encoding != ContentEncoding::Zstd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{middleware::DefaultHeaders, test, web, App};
+
+    #[test]
+    fn test_accept_encoding_preference() {
+        use ContentEncoding::{Auto, Br, Deflate, Gzip, Identity};
+
+        let pref = [Br, Gzip, Deflate];
+        assert_eq!(AcceptEncoding::parse("gzip, br", Auto, &pref), Br);
+        assert_eq!(AcceptEncoding::parse("gzip, br", Auto, &[Gzip, Br]), Gzip);
+        assert_eq!(
+            AcceptEncoding::parse("gzip;q=1.0, br;q=0.5", Auto, &pref),
+            Gzip
+        );
+        assert_eq!(
+            AcceptEncoding::parse("br;q=0, gzip;q=0.1", Auto, &pref),
+            Gzip
+        );
+        assert_eq!(AcceptEncoding::parse("br", Auto, &[Gzip]), Identity);
+        assert_eq!(
+            AcceptEncoding::parse("identity, br;q=0.5", Auto, &pref),
+            Identity
+        );
+        assert_eq!(
+            AcceptEncoding::parse("gzip, deflate", Deflate, &pref),
+            Deflate
+        );
+        assert_eq!(AcceptEncoding::parse("gzip", Deflate, &pref), Identity);
+    }
+
+    #[test]
+    fn test_accept_encoding_wildcard() {
+        use ContentEncoding::{Auto, Br, Deflate, Gzip, Identity};
+
+        let pref = [Br, Gzip, Deflate];
+        assert_eq!(AcceptEncoding::parse("*", Auto, &pref), Br);
+        assert_eq!(AcceptEncoding::parse("br;q=0, *", Auto, &pref), Gzip);
+        assert_eq!(
+            AcceptEncoding::parse("gzip;q=0.5, *;q=0.8", Auto, &pref),
+            Br
+        );
+        assert_eq!(AcceptEncoding::parse("*;q=0", Auto, &pref), Identity);
+        assert_eq!(AcceptEncoding::parse("*", Deflate, &pref), Deflate);
+        assert_eq!(
+            AcceptEncoding::parse("deflate;q=0, *", Deflate, &pref),
+            Identity
+        );
+    }
+
+    #[test]
+    fn test_compress_is_send() {
+        fn assert_send<T: Send>(_: T) {}
+        assert_send(Compress::default().content_type_filter(|_| true));
+    }
+
+    #[actix_rt::test]
+    async fn test_min_size_and_content_type_filter() {
+        let srv = test::init_service(
+            App::new()
+                .wrap(
+                    Compress::default()
+                        .min_size(10)
+                        .content_type_filter(|mime| mime.type_() != mime::IMAGE),
+                )
+                .service(web::resource("/small").to(|| HttpResponse::Ok().body("tiny")))
+                .service(
+                    web::resource("/image")
+                        .wrap(DefaultHeaders::new().header(CONTENT_TYPE, "image/png"))
+                        .to(|| HttpResponse::Ok().body("not a real image body")),
+                )
+                .service(
+                    web::resource("/text")
+                        .to(|| HttpResponse::Ok().body("some larger text body")),
+                ),
+        )
+        .await;
+
+        for (path, compressed) in &[("/small", false), ("/image", false), ("/text", true)] {
+            let req = test::TestRequest::with_uri(path)
+                .insert_header((ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            let res = test::call_service(&srv, req).await;
+
+            let encoding = res
+                .headers()
+                .get(actix_http::http::header::CONTENT_ENCODING);
+            assert_eq!(encoding.is_some(), *compressed, "{}", path);
+        }
+    }
+}
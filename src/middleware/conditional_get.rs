@@ -0,0 +1,383 @@
+//! For middleware documentation, see [`ConditionalGet`].
+
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_service::{Service, Transform};
+use futures_util::future::{ok, Ready};
+
+use crate::{
+    dev::{Body, MessageBody, ResponseBody},
+    error::Error,
+    http::{
+        header::{self, EntityTag, HeaderValue, HttpDate},
+        Method, StatusCode,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    HttpMessage, HttpRequest, HttpResponse,
+};
+
+/// Middleware for handling conditional `GET` and `HEAD` requests.
+///
+/// Successful responses are checked against the `If-Match`, `If-Unmodified-Since`,
+/// `If-None-Match` and `If-Modified-Since` request headers, in the order defined by
+/// [RFC 7232 §6](https://tools.ietf.org/html/rfc7232#section-6). Responses are replaced with an
+/// empty `304 Not Modified` or `412 Precondition Failed` response as appropriate.
+///
+/// Validators are taken from the `ETag` and `Last-Modified` headers set by the handler. If no
+/// `ETag` is set and the body is fully buffered, a weak `ETag` is generated by hashing the body.
+///
+/// Requests with other methods are not evaluated, since by the time a response is produced the
+/// request has already been acted upon. Handlers of state-changing requests should evaluate
+/// preconditions themselves.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::ConditionalGet, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(ConditionalGet::new())
+///     .route("/", web::get().to(|| HttpResponse::Ok().body("Hello world!")));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ConditionalGet {
+    generate_etag: bool,
+}
+
+impl Default for ConditionalGet {
+    fn default() -> Self {
+        ConditionalGet {
+            generate_etag: true,
+        }
+    }
+}
+
+impl ConditionalGet {
+    /// Constructs a `ConditionalGet` middleware that generates `ETag`s for buffered bodies.
+    pub fn new() -> Self {
+        ConditionalGet::default()
+    }
+
+    /// Sets whether an `ETag` is generated for buffered bodies without one.
+    ///
+    /// Default is `true`.
+    pub fn generate_etag(mut self, generate: bool) -> Self {
+        self.generate_etag = generate;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ConditionalGet
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ConditionalGetMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ConditionalGetMiddleware {
+            service,
+            generate_etag: self.generate_etag,
+        })
+    }
+}
+
+/// Conditional request middleware service.
+pub struct ConditionalGetMiddleware<S> {
+    service: S,
+    generate_etag: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for ConditionalGetMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = ConditionalGetFuture<S, B>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let enabled = *req.method() == Method::GET || *req.method() == Method::HEAD;
+
+        ConditionalGetFuture {
+            fut: self.service.call(req),
+            enabled,
+            generate_etag: self.generate_etag,
+            _body: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct ConditionalGetFuture<S: Service<ServiceRequest>, B> {
+    #[pin]
+    fut: S::Future,
+    enabled: bool,
+    generate_etag: bool,
+    _body: PhantomData<B>,
+}
+
+impl<S, B> Future for ConditionalGetFuture<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures_util::ready!(this.fut.poll(cx))?;
+
+        if !*this.enabled || !res.status().is_success() {
+            return Poll::Ready(Ok(res));
+        }
+
+        if *this.generate_etag && !res.headers().contains_key(header::ETAG) {
+            if let Some(etag) = body_bytes(res.response().body()).map(hash_etag) {
+                if let Ok(val) = HeaderValue::from_str(&etag.to_string()) {
+                    res.headers_mut().insert(header::ETAG, val);
+                }
+            }
+        }
+
+        let etag = res
+            .headers()
+            .get(header::ETAG)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<EntityTag>().ok());
+
+        let last_modified = res
+            .headers()
+            .get(header::LAST_MODIFIED)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<HttpDate>().ok());
+
+        let res = match evaluate(res.request(), etag.as_ref(), last_modified) {
+            Precondition::Passed => res,
+            Precondition::NotModified => res.map_body(|head, _| {
+                head.status = StatusCode::NOT_MODIFIED;
+                head.headers.remove(header::CONTENT_TYPE);
+                head.headers.remove(header::CONTENT_LENGTH);
+                ResponseBody::Other(Body::None)
+            }),
+            Precondition::Failed => {
+                let failed = HttpResponse::PreconditionFailed().finish().into_body();
+                res.into_response(failed)
+            }
+        };
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+enum Precondition {
+    Passed,
+    NotModified,
+    Failed,
+}
+
+/// Evaluates request preconditions against the response validators.
+fn evaluate(
+    req: &HttpRequest,
+    etag: Option<&EntityTag>,
+    last_modified: Option<HttpDate>,
+) -> Precondition {
+    let modified_after = |since: HttpDate| match last_modified {
+        Some(lm) => unix_secs(lm) > unix_secs(since),
+        None => true,
+    };
+
+    // step 1 and 2: If-Match and If-Unmodified-Since
+    match req.get_header::<header::IfMatch>() {
+        Some(header::IfMatch::Any) => {}
+        Some(header::IfMatch::Items(ref items)) => {
+            if !etag.map_or(false, |etag| items.iter().any(|item| item.strong_eq(etag))) {
+                return Precondition::Failed;
+            }
+        }
+        None => {
+            if let Some(header::IfUnmodifiedSince(since)) = req.get_header() {
+                if last_modified.is_some() && modified_after(since) {
+                    return Precondition::Failed;
+                }
+            }
+        }
+    }
+
+    // step 3 and 4: If-None-Match and If-Modified-Since
+    match req.get_header::<header::IfNoneMatch>() {
+        Some(header::IfNoneMatch::Any) => Precondition::NotModified,
+        Some(header::IfNoneMatch::Items(ref items)) => {
+            if etag.map_or(false, |etag| items.iter().any(|item| item.weak_eq(etag))) {
+                Precondition::NotModified
+            } else {
+                Precondition::Passed
+            }
+        }
+        None => match req.get_header::<header::IfModifiedSince>() {
+            Some(header::IfModifiedSince(since)) if !modified_after(since) => {
+                Precondition::NotModified
+            }
+            _ => Precondition::Passed,
+        },
+    }
+}
+
+fn unix_secs(date: HttpDate) -> u64 {
+    SystemTime::from(date)
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or(0)
+}
+
+/// Returns the body bytes if the body is fully buffered.
+pub(super) fn body_bytes<B: MessageBody + 'static>(body: &ResponseBody<B>) -> Option<&[u8]> {
+    let body = match body {
+        ResponseBody::Body(ref body) => (body as &dyn MessageBody).downcast_ref::<Body>()?,
+        ResponseBody::Other(ref body) => body,
+    };
+
+    match body {
+        Body::Bytes(ref bytes) => Some(bytes),
+        Body::Empty => Some(b""),
+        _ => None,
+    }
+}
+
+/// Creates a weak `ETag` from the FNV-1a hash and length of the body.
+fn hash_etag(body: &[u8]) -> EntityTag {
+    let hash = body.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    EntityTag::weak(format!("{:x}-{:x}", body.len(), hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::{
+        http::header::{IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE},
+        test::{self, TestRequest},
+    };
+
+    const LAST_MODIFIED: &str = "Sun, 07 Feb 2021 10:00:00 GMT";
+    const EARLIER: &str = "Sat, 06 Feb 2021 10:00:00 GMT";
+
+    async fn check(mw: ConditionalGet, req: TestRequest) -> (StatusCode, Option<HeaderValue>) {
+        let srv = |req: ServiceRequest| {
+            ok(req.into_response(
+                HttpResponse::Ok()
+                    .insert_header((header::LAST_MODIFIED, LAST_MODIFIED))
+                    .body("Hello world!"),
+            ))
+        };
+        let mw = mw.new_transform(srv.into_service()).await.unwrap();
+        let res = test::call_service(&mw, req.to_srv_request()).await;
+        (res.status(), res.headers().get(header::ETAG).cloned())
+    }
+
+    #[actix_rt::test]
+    async fn test_generated_etag() {
+        let (status, etag) = check(ConditionalGet::new(), TestRequest::default()).await;
+        assert_eq!(status, StatusCode::OK);
+        let etag = etag.unwrap();
+        assert!(etag.to_str().unwrap().starts_with("W/\"c-"));
+
+        let req = TestRequest::default().insert_header((IF_NONE_MATCH, etag.clone()));
+        assert_eq!(
+            check(ConditionalGet::new(), req).await.0,
+            StatusCode::NOT_MODIFIED
+        );
+
+        let req = TestRequest::default().insert_header((IF_NONE_MATCH, "\"other\""));
+        assert_eq!(check(ConditionalGet::new(), req).await.0, StatusCode::OK);
+
+        // weak tags never match If-Match
+        let req = TestRequest::default().insert_header((IF_MATCH, etag));
+        assert_eq!(
+            check(ConditionalGet::new(), req).await.0,
+            StatusCode::PRECONDITION_FAILED
+        );
+
+        let req = TestRequest::default().insert_header((IF_MATCH, "*"));
+        assert_eq!(check(ConditionalGet::new(), req).await.0, StatusCode::OK);
+
+        let (_, etag) = check(
+            ConditionalGet::new().generate_etag(false),
+            TestRequest::default(),
+        )
+        .await;
+        assert!(etag.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_dates() {
+        let req = TestRequest::default().insert_header((IF_MODIFIED_SINCE, LAST_MODIFIED));
+        assert_eq!(
+            check(ConditionalGet::new(), req).await.0,
+            StatusCode::NOT_MODIFIED
+        );
+
+        let req = TestRequest::default().insert_header((IF_MODIFIED_SINCE, EARLIER));
+        assert_eq!(check(ConditionalGet::new(), req).await.0, StatusCode::OK);
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let req = TestRequest::default()
+            .insert_header((IF_NONE_MATCH, "\"other\""))
+            .insert_header((IF_MODIFIED_SINCE, LAST_MODIFIED));
+        assert_eq!(check(ConditionalGet::new(), req).await.0, StatusCode::OK);
+
+        let req = TestRequest::default().insert_header((IF_UNMODIFIED_SINCE, EARLIER));
+        assert_eq!(
+            check(ConditionalGet::new(), req).await.0,
+            StatusCode::PRECONDITION_FAILED
+        );
+
+        let req = TestRequest::default().insert_header((IF_UNMODIFIED_SINCE, LAST_MODIFIED));
+        assert_eq!(check(ConditionalGet::new(), req).await.0, StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_handler_etag() {
+        let srv = |req: ServiceRequest| {
+            ok(req.into_response(
+                HttpResponse::Ok()
+                    .insert_header(header::ETag(EntityTag::strong("v1".to_owned())))
+                    .streaming(futures_util::stream::empty::<Result<_, Error>>()),
+            ))
+        };
+        let mw = ConditionalGet::new()
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .insert_header((IF_MATCH, "\"v1\""))
+            .insert_header((IF_NONE_MATCH, "W/\"v1\""))
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"v1\"");
+
+        let req = TestRequest::post()
+            .insert_header((IF_NONE_MATCH, "\"v1\""))
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,521 @@
+//! For middleware documentation, see [`Csrf`].
+
+use std::{collections::HashSet, rc::Rc};
+
+use actix_http::{
+    cookie::{Cookie, CookieJar, Key, SameSite},
+    error::{ErrorForbidden, PayloadError},
+};
+use actix_service::{Service, Transform};
+use bytes::BytesMut;
+use futures_util::{
+    future::{self, ok, FutureExt as _, LocalBoxFuture, Ready},
+    stream::{self, StreamExt as _},
+};
+use regex::RegexSet;
+
+use super::random_hex;
+
+use crate::{
+    dev::Payload,
+    error::Error,
+    http::{
+        header::{HeaderName, CONTENT_TYPE, ORIGIN, REFERER},
+        Method,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    HttpMessage,
+};
+
+/// Maximum size of a URL encoded form body read to find the token field.
+const FORM_LIMIT: usize = 64 * 1024;
+
+/// Middleware for protecting against cross-site request forgery using double-submit cookies.
+///
+/// A random token is issued in a cookie signed with the given [`Key`]. Requests with unsafe
+/// methods (anything but `GET`, `HEAD`, `OPTIONS` and `TRACE`) must submit the same token in the
+/// `X-CSRF-Token` header or, for URL encoded forms, in the `csrf_token` field. Otherwise they are
+/// rejected with `403 Forbidden`.
+///
+/// If an unsafe request carries an `Origin` header, or failing that a `Referer` header, its origin
+/// must match the request's own origin or one of the [allowed origins](Self::allowed_origin).
+///
+/// Handlers can access the token using the [`CsrfToken`] extractor, e.g. to render it into forms.
+///
+/// Requires the `secure-cookies` feature.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{cookie::Key, middleware::{Csrf, CsrfToken}, web, App, HttpResponse};
+///
+/// async fn form(token: CsrfToken) -> HttpResponse {
+///     HttpResponse::Ok().body(format!(
+///         r#"<form method="post"><input type="hidden" name="csrf_token" value="{}"></form>"#,
+///         token
+///     ))
+/// }
+///
+/// let key = Key::generate();
+///
+/// let app = App::new()
+///     .wrap(Csrf::new(key).exempt("/webhook"))
+///     .route("/", web::get().to(form));
+/// ```
+#[derive(Clone)]
+pub struct Csrf(Rc<Inner>);
+
+struct Inner {
+    key: Key,
+    cookie_name: String,
+    cookie_secure: bool,
+    cookie_same_site: SameSite,
+    header_name: HeaderName,
+    field_name: String,
+    allowed_origins: HashSet<String>,
+    exempt: HashSet<String>,
+    exempt_regex: RegexSet,
+}
+
+impl Csrf {
+    /// Constructs a `Csrf` middleware that signs token cookies with `key`.
+    pub fn new(key: Key) -> Self {
+        Csrf(Rc::new(Inner {
+            key,
+            cookie_name: "csrf-token".to_owned(),
+            cookie_secure: true,
+            cookie_same_site: SameSite::Lax,
+            header_name: HeaderName::from_static("x-csrf-token"),
+            field_name: "csrf_token".to_owned(),
+            allowed_origins: HashSet::new(),
+            exempt: HashSet::new(),
+            exempt_regex: RegexSet::empty(),
+        }))
+    }
+
+    /// Sets the name of the token cookie.
+    ///
+    /// Default is `csrf-token`.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().cookie_name = name.into();
+        self
+    }
+
+    /// Sets whether the token cookie is only sent over HTTPS.
+    ///
+    /// Default is `true`.
+    pub fn cookie_secure(mut self, secure: bool) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().cookie_secure = secure;
+        self
+    }
+
+    /// Sets the `SameSite` attribute of the token cookie.
+    ///
+    /// Default is `Lax`.
+    pub fn cookie_same_site(mut self, same_site: SameSite) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().cookie_same_site = same_site;
+        self
+    }
+
+    /// Sets the name of the request header carrying the submitted token.
+    ///
+    /// Default is `X-CSRF-Token`.
+    ///
+    /// # Panics
+    /// Panics if `name` is not a valid header name.
+    pub fn header_name(mut self, name: &str) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().header_name =
+            HeaderName::from_bytes(name.as_bytes()).expect("Invalid CSRF header name");
+        self
+    }
+
+    /// Sets the name of the URL encoded form field carrying the submitted token.
+    ///
+    /// Default is `csrf_token`.
+    pub fn field_name(mut self, name: impl Into<String>) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().field_name = name.into();
+        self
+    }
+
+    /// Allows unsafe requests from the given origin, e.g. `https://app.example.com`.
+    ///
+    /// Requests from the request's own origin are always allowed.
+    pub fn allowed_origin(mut self, origin: impl Into<String>) -> Self {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .allowed_origins
+            .insert(origin.into());
+        self
+    }
+
+    /// Skips token and origin checks for the specified path.
+    pub fn exempt(mut self, path: impl Into<String>) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().exempt.insert(path.into());
+        self
+    }
+
+    /// Skips token and origin checks for paths that match regex.
+    pub fn exempt_regex(mut self, path: impl Into<String>) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        let mut patterns = inner.exempt_regex.patterns().to_vec();
+        patterns.push(path.into());
+        inner.exempt_regex = RegexSet::new(patterns).unwrap();
+        self
+    }
+}
+
+impl Inner {
+    /// Returns the token from the request cookie, if its signature is valid.
+    fn cookie_token(&self, req: &ServiceRequest) -> Option<String> {
+        let cookie = req.cookie(&self.cookie_name)?;
+
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie.into_owned());
+        let cookie = jar.signed(&self.key).get(&self.cookie_name)?;
+
+        Some(cookie.value().to_owned())
+    }
+
+    fn token_cookie(&self, token: &str) -> Cookie<'static> {
+        let cookie = Cookie::build(self.cookie_name.clone(), token.to_owned())
+            .path("/")
+            .http_only(true)
+            .secure(self.cookie_secure)
+            .same_site(self.cookie_same_site)
+            .finish();
+
+        let mut jar = CookieJar::new();
+        jar.signed(&self.key).add(cookie);
+        jar.get(&self.cookie_name).unwrap().clone()
+    }
+
+    fn is_exempt(&self, path: &str) -> bool {
+        self.exempt.contains(path) || self.exempt_regex.is_match(path)
+    }
+
+    fn check_origin(&self, req: &ServiceRequest) -> Result<(), Error> {
+        let origin = if let Some(origin) = req.headers().get(ORIGIN) {
+            origin.to_str().ok().map(ToOwned::to_owned)
+        } else if let Some(referer) = req.headers().get(REFERER) {
+            referer
+                .to_str()
+                .ok()
+                .and_then(|referer| url::Url::parse(referer).ok())
+                .map(|url| url.origin().ascii_serialization())
+        } else {
+            return Ok(());
+        };
+
+        let origin = origin.ok_or_else(|| ErrorForbidden("Invalid CSRF request origin"))?;
+
+        let own_origin = {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        };
+
+        if origin == own_origin || self.allowed_origins.contains(&origin) {
+            Ok(())
+        } else {
+            Err(ErrorForbidden("CSRF request origin not allowed"))
+        }
+    }
+
+    /// Reads the submitted token from the request header or URL encoded form body.
+    async fn submitted_token(&self, req: &mut ServiceRequest) -> Result<Option<String>, Error> {
+        if let Some(token) = req.headers().get(&self.header_name) {
+            return Ok(token.to_str().ok().map(ToOwned::to_owned));
+        }
+
+        let is_form = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .map_or(false, |val| {
+                val.to_ascii_lowercase()
+                    .starts_with("application/x-www-form-urlencoded")
+            });
+
+        if !is_form {
+            return Ok(None);
+        }
+
+        let mut payload = req.take_payload();
+        let mut body = BytesMut::new();
+
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk?;
+
+            if body.len() + chunk.len() > FORM_LIMIT {
+                return Err(PayloadError::Overflow.into());
+            }
+
+            body.extend_from_slice(&chunk);
+        }
+
+        let body = body.freeze();
+        let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&body)
+            .ok()
+            .and_then(|fields| {
+                fields
+                    .into_iter()
+                    .find(|(name, _)| *name == self.field_name)
+                    .map(|(_, value)| value)
+            });
+
+        // put the body back for the handler
+        let body = stream::once(future::ok::<_, PayloadError>(body));
+        req.set_payload(Payload::Stream(Box::pin(body)));
+
+        Ok(token)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Csrf
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CsrfMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CsrfMiddleware {
+            service: Rc::new(service),
+            inner: self.0.clone(),
+        })
+    }
+}
+
+/// CSRF middleware service.
+pub struct CsrfMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            let cookie_token = inner.cookie_token(&req);
+
+            if !is_safe(req.method()) && !inner.is_exempt(req.path()) {
+                inner.check_origin(&req)?;
+
+                let expected = cookie_token
+                    .as_deref()
+                    .ok_or_else(|| ErrorForbidden("CSRF cookie missing or invalid"))?;
+
+                match inner.submitted_token(&mut req).await? {
+                    Some(ref token) if constant_time_eq(token, expected) => {}
+                    _ => return Err(ErrorForbidden("CSRF token missing or invalid")),
+                }
+            }
+
+            let (token, is_new) = match cookie_token {
+                Some(token) => (token, false),
+                None => (random_hex(32), true),
+            };
+
+            req.extensions_mut()
+                .insert(CsrfToken(token.as_str().into()));
+
+            let mut res = service.call(req).await?;
+
+            if is_new {
+                res.response_mut().add_cookie(&inner.token_cookie(&token))?;
+            }
+
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+extension_str! {
+    /// Extractor for the CSRF token issued by the [`Csrf`] middleware.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use actix_web::{middleware::CsrfToken, Responder};
+    ///
+    /// async fn index(token: CsrfToken) -> impl Responder {
+    ///     format!(r#"<meta name="csrf-token" content="{}">"#, token)
+    /// }
+    /// ```
+    pub struct CsrfToken;
+    "Csrf middleware is not registered"
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    async fn token(token: CsrfToken) -> String {
+        token.to_string()
+    }
+
+    async fn echo(body: Bytes) -> Bytes {
+        body
+    }
+
+    #[actix_rt::test]
+    async fn test_csrf() {
+        let srv = test::init_service(
+            App::new()
+                .wrap(Csrf::new(Key::generate()))
+                .route("/", web::get().to(token))
+                .route("/", web::post().to(echo)),
+        )
+        .await;
+
+        let req = TestRequest::get().to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = res.response().cookies().next().unwrap().into_owned();
+        assert!(cookie.http_only().unwrap());
+        let token = test::read_body(res).await;
+        let token = std::str::from_utf8(&token).unwrap().to_owned();
+        assert_eq!(token.len(), 64);
+
+        // cookie is reused
+        let req = TestRequest::get().cookie(cookie.clone()).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert!(res.response().cookies().next().is_none());
+        assert_eq!(test::read_body(res).await, token.as_bytes());
+
+        // token in header
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .insert_header(("x-csrf-token", token.as_str()))
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // token in form field, body is still readable by handler
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .set_form(&[("name", "bob"), ("csrf_token", token.as_str())])
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = test::read_body(res).await;
+        assert!(body.starts_with(b"name=bob&csrf_token="));
+
+        // wrong token
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .insert_header(("x-csrf-token", "0000"))
+            .to_request();
+        let res = srv.call(req).await;
+        assert_eq!(
+            res.err().unwrap().as_response_error().status_code(),
+            StatusCode::FORBIDDEN
+        );
+
+        // no cookie
+        let req = TestRequest::post()
+            .insert_header(("x-csrf-token", token.as_str()))
+            .to_request();
+        assert!(srv.call(req).await.is_err());
+
+        // forged cookie
+        let req = TestRequest::post()
+            .cookie(Cookie::new("csrf-token", token.clone()))
+            .insert_header(("x-csrf-token", token.as_str()))
+            .to_request();
+        assert!(srv.call(req).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_origin() {
+        let csrf = Csrf::new(Key::generate()).allowed_origin("https://app.example.com");
+        let srv = test::init_service(
+            App::new()
+                .wrap(csrf)
+                .route("/", web::get().to(token))
+                .route("/", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res = test::call_service(&srv, TestRequest::get().to_request()).await;
+        let cookie = res.response().cookies().next().unwrap().into_owned();
+        let token = test::read_body(res).await;
+        let token = std::str::from_utf8(&token).unwrap().to_owned();
+
+        let post = |name, origin| {
+            TestRequest::post()
+                .cookie(cookie.clone())
+                .insert_header(("x-csrf-token", token.as_str()))
+                .insert_header((name, origin))
+                .to_request()
+        };
+
+        let req = post(ORIGIN, "https://app.example.com");
+        assert!(srv.call(req).await.is_ok());
+
+        let req = post(ORIGIN, "http://localhost:8080");
+        assert!(srv.call(req).await.is_ok());
+
+        let req = post(REFERER, "http://localhost:8080/form?x=1");
+        assert!(srv.call(req).await.is_ok());
+
+        let req = post(ORIGIN, "https://evil.example.com");
+        assert!(srv.call(req).await.is_err());
+
+        let req = post(REFERER, "https://evil.example.com/form");
+        assert!(srv.call(req).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_exempt() {
+        let srv = test::init_service(
+            App::new()
+                .wrap(Csrf::new(Key::generate()).exempt("/webhook"))
+                .route("/webhook", web::post().to(HttpResponse::Ok))
+                .route("/other", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/webhook").to_request();
+        assert!(srv.call(req).await.is_ok());
+
+        let req = TestRequest::post().uri("/other").to_request();
+        assert!(srv.call(req).await.is_err());
+    }
+}
@@ -20,11 +20,12 @@ use regex::{Regex, RegexSet};
 use time::OffsetDateTime;
 
 use crate::{
-    dev::{BodySize, MessageBody, ResponseBody},
+    dev::{BodySize, MessageBody, ResponseBody, ResponseHead},
     error::{Error, Result},
-    http::{HeaderName, StatusCode},
+    http::{HeaderMap, HeaderName, StatusCode},
+    middleware::{CacheStatus, ReqId},
     service::{ServiceRequest, ServiceResponse},
-    HttpResponse,
+    HttpRequest, HttpResponse,
 };
 
 /// Middleware for logging request and response summaries to the terminal.
@@ -71,6 +72,36 @@ use crate::{
 /// `%{FOO}o` | `response.headers["FOO"]`
 /// `%{FOO}e` | `env_var["FOO"]`
 /// `%{FOO}xi` | [Custom request replacement](Logger::custom_request_replace) labelled "FOO"
+/// `%{FOO}xo` | [Custom response replacement](Logger::custom_response_replace) labelled "FOO"
+/// `%{request-id}x` | Request ID assigned by the [`RequestId`](super::RequestId) middleware
+/// `%{cache}x` | Outcome of the [`Cache`](super::Cache) middleware (`HIT`, `STALE` or `MISS`)
+///
+/// # Structured Output
+/// A [`structured`](Logger::structured) Logger collects each field as a key-value pair in a
+/// [`LogEntry`] instead of rendering a format string. Entries are logged as JSON objects by
+/// default; use [`sink`](Logger::sink) to hand them to a different log pipeline.
+///
+/// Field | Description
+/// ----- | -----------
+/// `time` | Time when the request started processing
+/// `remote_addr` | Peer IP address (or IP address of reverse proxy if used)
+/// `method` | Request method
+/// `path` | Request path
+/// `query` | Request query string, if not empty
+/// `version` | HTTP version
+/// `status` | Response status code
+/// `size` | Size of response body in bytes
+/// `duration_ms` | Time taken to serve the request, in milliseconds
+/// `request_id` | Request ID assigned by the [`RequestId`](super::RequestId) middleware, if any
+/// `cache` | Outcome of the [`Cache`](super::Cache) middleware, if any
+/// `request_header.foo` | [Logged request header](Logger::request_header) "foo"
+/// `response_header.foo` | [Logged response header](Logger::response_header) "foo"
+/// `FOO` | [Custom request](Logger::custom_request_replace) or
+/// [response](Logger::custom_response_replace) field labelled "FOO"
+///
+/// # Sampling
+/// Use [`errors_only`](Logger::errors_only) or [`sample`](Logger::sample) to reduce log volume.
+/// Responses with a client or server error status are always logged.
 ///
 /// # Security
 /// **\*** "Real IP" remote address is calculated using
@@ -84,8 +115,10 @@ pub struct Logger(Rc<Inner>);
 #[derive(Debug, Clone)]
 struct Inner {
     format: Format,
+    structured: Option<Structured>,
     exclude: HashSet<String>,
     exclude_regex: RegexSet,
+    sample_rate: f64,
 }
 
 impl Logger {
@@ -93,11 +126,86 @@ impl Logger {
     pub fn new(format: &str) -> Logger {
         Logger(Rc::new(Inner {
             format: Format::new(format),
+            structured: None,
             exclude: HashSet::new(),
             exclude_regex: RegexSet::empty(),
+            sample_rate: 1.0,
         }))
     }
 
+    /// Create `Logger` middleware with structured output.
+    ///
+    /// See the [structured output](#structured-output) section for the logged fields.
+    ///
+    /// # Example
+    /// ```rust
+    /// use actix_web::{http::header, middleware::Logger};
+    ///
+    /// Logger::structured()
+    ///     .request_header(header::USER_AGENT)
+    ///     .sink(|entry| println!("{}", entry.to_json()));
+    /// ```
+    pub fn structured() -> Logger {
+        Logger(Rc::new(Inner {
+            format: Format(Vec::new()),
+            structured: Some(Structured::default()),
+            exclude: HashSet::new(),
+            exclude_regex: RegexSet::empty(),
+            sample_rate: 1.0,
+        }))
+    }
+
+    /// Log the value of the given request header as the `request_header.{name}` field.
+    ///
+    /// Only used in structured mode.
+    pub fn request_header(mut self, name: HeaderName) -> Self {
+        if let Some(ref mut structured) = Rc::get_mut(&mut self.0).unwrap().structured {
+            structured.request_headers.push(name);
+        }
+        self
+    }
+
+    /// Log the value of the given response header as the `response_header.{name}` field.
+    ///
+    /// Only used in structured mode.
+    pub fn response_header(mut self, name: HeaderName) -> Self {
+        if let Some(ref mut structured) = Rc::get_mut(&mut self.0).unwrap().structured {
+            structured.response_headers.push(name);
+        }
+        self
+    }
+
+    /// Set a function that receives each structured log entry.
+    ///
+    /// By default, entries are logged as JSON objects with the INFO level. Only used in
+    /// structured mode.
+    pub fn sink(mut self, f: impl Fn(&LogEntry) + 'static) -> Self {
+        if let Some(ref mut structured) = Rc::get_mut(&mut self.0).unwrap().structured {
+            structured.sink = LogSink(Rc::new(f));
+        }
+        self
+    }
+
+    /// Only log responses with a client or server error status.
+    pub fn errors_only(self) -> Self {
+        self.sample(0.0)
+    }
+
+    /// Log only the given fraction of requests, chosen at random.
+    ///
+    /// Responses with a client or server error status are always logged.
+    ///
+    /// # Panics
+    /// Panics if `rate` is not between 0.0 and 1.0.
+    pub fn sample(mut self, rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "Sample rate must be between 0.0 and 1.0"
+        );
+        Rc::get_mut(&mut self.0).unwrap().sample_rate = rate;
+        self
+    }
+
     /// Ignore and do not log access info for specified path.
     pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
         Rc::get_mut(&mut self.0)
@@ -123,6 +231,8 @@ impl Logger {
     ///
     /// It is convention to print "-" to indicate no output instead of an empty string.
     ///
+    /// In structured mode, the returned string is logged as the field named `label`.
+    ///
     /// # Example
     /// ```rust
     /// # use actix_web::{http::HeaderValue, middleware::Logger};
@@ -137,6 +247,14 @@ impl Logger {
     ) -> Self {
         let inner = Rc::get_mut(&mut self.0).unwrap();
 
+        if let Some(ref mut structured) = inner.structured {
+            let f = CustomRequestFn {
+                inner_fn: Rc::new(f),
+            };
+            upsert(&mut structured.custom_request, label, f);
+            return self;
+        }
+
         let ft = inner.format.0.iter_mut().find(
             |ft| matches!(ft, FormatText::CustomRequest(unit_label, _) if label == unit_label),
         );
@@ -156,6 +274,61 @@ impl Logger {
 
         self
     }
+
+    /// Register a function that receives the request and response head and returns a String for
+    /// use in the log line. The label passed as the first argument should match a replacement
+    /// substring in the logger format like `%{label}xo`.
+    ///
+    /// In structured mode, the returned string is logged as the field named `label`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use actix_web::middleware::Logger;
+    /// Logger::new("example %{CACHE}xo").custom_response_replace("CACHE", |_req, res| {
+    ///     match res.headers().get("x-cache") {
+    ///         Some(_) => "hit".to_owned(),
+    ///         None => "miss".to_owned(),
+    ///     }
+    /// });
+    /// ```
+    pub fn custom_response_replace(
+        mut self,
+        label: &str,
+        f: impl Fn(&HttpRequest, &ResponseHead) -> String + 'static,
+    ) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        let f = CustomResponseFn {
+            inner_fn: Rc::new(f),
+        };
+
+        if let Some(ref mut structured) = inner.structured {
+            upsert(&mut structured.custom_response, label, f);
+            return self;
+        }
+
+        let ft = inner.format.0.iter_mut().find(
+            |ft| matches!(ft, FormatText::CustomResponse(unit_label, _) if label == unit_label),
+        );
+
+        if let Some(FormatText::CustomResponse(_, response_fn)) = ft {
+            response_fn.replace(f);
+        } else {
+            debug!(
+                "Attempted to register custom response logging function for nonexistent label: {}",
+                label
+            );
+        }
+
+        self
+    }
+}
+
+/// Replaces the function registered for `label` or appends a new one.
+fn upsert<F>(fns: &mut Vec<(String, F)>, label: &str, f: F) {
+    match fns.iter_mut().find(|(unit_label, _)| unit_label == label) {
+        Some((_, prev)) => *prev = f,
+        None => fns.push((label.to_owned(), f)),
+    }
 }
 
 impl Default for Logger {
@@ -167,8 +340,10 @@ impl Default for Logger {
     fn default() -> Logger {
         Logger(Rc::new(Inner {
             format: Format::default(),
+            structured: None,
             exclude: HashSet::new(),
             exclude_regex: RegexSet::empty(),
+            sample_rate: 1.0,
         }))
     }
 }
@@ -193,6 +368,13 @@ where
                     label
                 );
             }
+
+            if let FormatText::CustomResponse(label, None) = unit {
+                warn!(
+                    "No custom response replacement function was registered for label \"{}\".",
+                    label
+                );
+            }
         }
 
         ok(LoggerMiddleware {
@@ -226,20 +408,37 @@ where
             LoggerResponse {
                 fut: self.service.call(req),
                 format: None,
+                entry: None,
+                sampled: false,
                 time: OffsetDateTime::now_utc(),
+                inner: self.inner.clone(),
                 _phantom: PhantomData,
             }
         } else {
             let now = OffsetDateTime::now_utc();
-            let mut format = self.inner.format.clone();
+            let rate = self.inner.sample_rate;
+            let sampled = rate >= 1.0 || rand::random::<f64>() < rate;
+
+            let (format, entry) = match self.inner.structured {
+                Some(ref structured) => (None, Some(structured.render_request(now, &req))),
+                None => {
+                    let mut format = self.inner.format.clone();
+
+                    for unit in &mut format.0 {
+                        unit.render_request(now, &req);
+                    }
+
+                    (Some(format), None)
+                }
+            };
 
-            for unit in &mut format.0 {
-                unit.render_request(now, &req);
-            }
             LoggerResponse {
                 fut: self.service.call(req),
-                format: Some(format),
+                format,
+                entry,
+                sampled,
                 time: now,
+                inner: self.inner.clone(),
                 _phantom: PhantomData,
             }
         }
@@ -256,6 +455,9 @@ where
     fut: S::Future,
     time: OffsetDateTime,
     format: Option<Format>,
+    entry: Option<LogEntry>,
+    sampled: bool,
+    inner: Rc<Inner>,
     _phantom: PhantomData<B>,
 }
 
@@ -280,20 +482,36 @@ where
             }
         }
 
+        let status = res.status();
+        if !*this.sampled && !status.is_client_error() && !status.is_server_error() {
+            *this.format = None;
+            *this.entry = None;
+        }
+
         if let Some(ref mut format) = this.format {
             for unit in &mut format.0 {
                 unit.render_response(res.response());
+                unit.render_request_extensions(res.request());
+                unit.render_custom_response(res.request(), res.response().head());
             }
         }
 
         let time = *this.time;
         let format = this.format.take();
+        let entry = match (this.entry.take(), &this.inner.structured) {
+            (Some(mut entry), Some(structured)) => {
+                structured.render_response(&mut entry, &res);
+                Some((entry, structured.sink.clone()))
+            }
+            _ => None,
+        };
 
         Poll::Ready(Ok(res.map_body(move |_, body| {
             ResponseBody::Body(StreamLog {
                 body,
                 time,
                 format,
+                entry,
                 size: 0,
             })
         })))
@@ -307,6 +525,7 @@ pub struct StreamLog<B> {
     #[pin]
     body: ResponseBody<B>,
     format: Option<Format>,
+    entry: Option<(LogEntry, LogSink)>,
     size: usize,
     time: OffsetDateTime,
 }
@@ -323,6 +542,182 @@ impl<B> PinnedDrop for StreamLog<B> {
             };
             log::info!("{}", FormatDisplay(&render));
         }
+
+        let this = self.project();
+        if let Some((mut entry, sink)) = this.entry.take() {
+            let rt = OffsetDateTime::now_utc() - *this.time;
+            entry.insert("size", LogValue::Int(*this.size as u64));
+            entry.insert(
+                "duration_ms",
+                LogValue::Float((rt.whole_nanoseconds() as f64) / 1_000_000.0),
+            );
+            (sink.0)(&entry);
+        }
+    }
+}
+
+/// A structured access log entry.
+///
+/// Fields are kept in the order they were collected. See the
+/// [structured output](Logger#structured-output) section of [`Logger`] for the logged fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogEntry {
+    fields: Vec<(String, LogValue)>,
+}
+
+impl LogEntry {
+    /// Returns the value of the field with the given key.
+    pub fn get(&self, key: &str) -> Option<&LogValue> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns an iterator over all fields.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LogValue)> {
+        self.fields.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Renders the entry as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let mut buf = String::from("{");
+
+        for (idx, (key, value)) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                buf.push(',');
+            }
+
+            buf.push_str(&serde_json::to_string(key).unwrap());
+            buf.push(':');
+
+            match value {
+                LogValue::Null => buf.push_str("null"),
+                LogValue::Str(s) => buf.push_str(&serde_json::to_string(s).unwrap()),
+                LogValue::Int(n) => buf.push_str(&n.to_string()),
+                LogValue::Float(n) => buf.push_str(&format!("{:.6}", n)),
+            }
+        }
+
+        buf.push('}');
+        buf
+    }
+
+    fn insert(&mut self, key: impl Into<String>, value: LogValue) {
+        self.fields.push((key.into(), value));
+    }
+}
+
+/// Value of a [`LogEntry`] field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogValue {
+    /// Value is missing, e.g. a header that was not sent.
+    Null,
+    /// A string value.
+    Str(String),
+    /// An integer value.
+    Int(u64),
+    /// A floating point value.
+    Float(f64),
+}
+
+impl fmt::Display for LogValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogValue::Null => f.write_str("-"),
+            LogValue::Str(s) => f.write_str(s),
+            LogValue::Int(n) => n.fmt(f),
+            LogValue::Float(n) => write!(f, "{:.6}", n),
+        }
+    }
+}
+
+/// Configuration of a structured `Logger`.
+#[derive(Debug, Clone, Default)]
+struct Structured {
+    request_headers: Vec<HeaderName>,
+    response_headers: Vec<HeaderName>,
+    custom_request: Vec<(String, CustomRequestFn)>,
+    custom_response: Vec<(String, CustomResponseFn)>,
+    sink: LogSink,
+}
+
+impl Structured {
+    fn render_request(&self, now: OffsetDateTime, req: &ServiceRequest) -> LogEntry {
+        let mut entry = LogEntry::default();
+
+        entry.insert("time", LogValue::Str(now.format("%Y-%m-%dT%H:%M:%S")));
+        entry.insert(
+            "remote_addr",
+            req.connection_info()
+                .remote_addr()
+                .map_or(LogValue::Null, |addr| LogValue::Str(addr.to_owned())),
+        );
+        entry.insert("method", LogValue::Str(req.method().to_string()));
+        entry.insert("path", LogValue::Str(req.path().to_owned()));
+        if !req.query_string().is_empty() {
+            entry.insert("query", LogValue::Str(req.query_string().to_owned()));
+        }
+        entry.insert("version", LogValue::Str(format!("{:?}", req.version())));
+
+        for name in &self.request_headers {
+            entry.insert(
+                format!("request_header.{}", name),
+                header_value(req.headers(), name),
+            );
+        }
+
+        for (label, f) in &self.custom_request {
+            entry.insert(label.as_str(), LogValue::Str(f.call(req)));
+        }
+
+        entry
+    }
+
+    fn render_response<B>(&self, entry: &mut LogEntry, res: &ServiceResponse<B>) {
+        entry.insert("status", LogValue::Int(res.status().as_u16().into()));
+
+        if let Some(id) = res.request().extensions().get::<ReqId>() {
+            entry.insert("request_id", LogValue::Str(id.to_string()));
+        }
+
+        if let Some(status) = res.request().extensions().get::<CacheStatus>() {
+            entry.insert("cache", LogValue::Str(status.to_string()));
+        }
+
+        for name in &self.response_headers {
+            entry.insert(
+                format!("response_header.{}", name),
+                header_value(res.headers(), name),
+            );
+        }
+
+        for (label, f) in &self.custom_response {
+            let value = f.call(res.request(), res.response().head());
+            entry.insert(label.as_str(), LogValue::Str(value));
+        }
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: &HeaderName) -> LogValue {
+    match headers.get(name).and_then(|val| val.to_str().ok()) {
+        Some(val) => LogValue::Str(val.to_owned()),
+        None => LogValue::Null,
+    }
+}
+
+#[derive(Clone)]
+struct LogSink(Rc<dyn Fn(&LogEntry)>);
+
+impl Default for LogSink {
+    fn default() -> Self {
+        LogSink(Rc::new(|entry| log::info!("{}", entry.to_json())))
+    }
+}
+
+impl fmt::Debug for LogSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("log_sink")
     }
 }
 
@@ -344,6 +739,10 @@ impl<B: MessageBody> MessageBody for StreamLog<B> {
             val => val,
         }
     }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
 }
 
 /// A formatting style for the `Logger` consisting of multiple concatenated `FormatText` items.
@@ -363,7 +762,10 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     pub fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([aioe]|xi)|[atPrUsbTD]?)").unwrap();
+        let fmt = Regex::new(
+            r"%(\{([A-Za-z0-9\-_]+)\}([aioe]|xi|xo)|\{(request-id|cache)\}x|[atPrUsbTD]?)",
+        )
+        .unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -392,6 +794,13 @@ impl Format {
                     }
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
                     "xi" => FormatText::CustomRequest(key.as_str().to_owned(), None),
+                    "xo" => FormatText::CustomResponse(key.as_str().to_owned(), None),
+                    _ => unreachable!(),
+                })
+            } else if let Some(key) = cap.get(4) {
+                results.push(match key.as_str() {
+                    "request-id" => FormatText::RequestId,
+                    "cache" => FormatText::CacheStatus,
                     _ => unreachable!(),
                 })
             } else {
@@ -439,6 +848,9 @@ enum FormatText {
     ResponseHeader(HeaderName),
     EnvironHeader(String),
     CustomRequest(String, Option<CustomRequestFn>),
+    CustomResponse(String, Option<CustomResponseFn>),
+    RequestId,
+    CacheStatus,
 }
 
 #[derive(Clone)]
@@ -458,6 +870,23 @@ impl fmt::Debug for CustomRequestFn {
     }
 }
 
+#[derive(Clone)]
+struct CustomResponseFn {
+    inner_fn: Rc<dyn Fn(&HttpRequest, &ResponseHead) -> String>,
+}
+
+impl CustomResponseFn {
+    fn call(&self, req: &HttpRequest, res: &ResponseHead) -> String {
+        (self.inner_fn)(req, res)
+    }
+}
+
+impl fmt::Debug for CustomResponseFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("custom_response_fn")
+    }
+}
+
 impl FormatText {
     fn render(
         &self,
@@ -511,6 +940,33 @@ impl FormatText {
         }
     }
 
+    fn render_request_extensions(&mut self, req: &HttpRequest) {
+        match self {
+            FormatText::RequestId => {
+                *self = match req.extensions().get::<ReqId>() {
+                    Some(id) => FormatText::Str(id.to_string()),
+                    None => FormatText::Str("-".to_owned()),
+                };
+            }
+            FormatText::CacheStatus => {
+                *self = match req.extensions().get::<CacheStatus>() {
+                    Some(status) => FormatText::Str(status.to_string()),
+                    None => FormatText::Str("-".to_owned()),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn render_custom_response(&mut self, req: &HttpRequest, res: &ResponseHead) {
+        if let FormatText::CustomResponse(_, response_fn) = self {
+            *self = match response_fn {
+                Some(f) => FormatText::Str(f.call(req, res)),
+                None => FormatText::Str("-".to_owned()),
+            };
+        }
+    }
+
     fn render_request(&mut self, now: OffsetDateTime, req: &ServiceRequest) {
         match self {
             FormatText::RequestLine => {
@@ -587,6 +1043,8 @@ impl<'a> fmt::Display for FormatDisplay<'a> {
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+
     use actix_service::{IntoService, Service, Transform};
     use futures_util::future::ok;
 
@@ -671,6 +1129,35 @@ mod tests {
         assert!(s.contains("/test/route/yeah"));
     }
 
+    #[actix_rt::test]
+    async fn test_request_id() {
+        let mut format = Format::new("id: %{request-id}x, cache: %{cache}x");
+
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(ReqId::new("abc"));
+        req.extensions_mut().insert(CacheStatus::Hit);
+        for unit in &mut format.0 {
+            unit.render_request_extensions(&req);
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(s, "id: abc, cache: HIT");
+    }
+
+    #[test]
+    fn test_unknown_x_key() {
+        // only known keys are supported without a direction suffix
+        let _ = Logger::new("%{foo}x %{FOO}x");
+        let _ = Format::new("%{foo}x");
+    }
+
     #[actix_rt::test]
     async fn test_default_format() {
         let mut format = Format::default();
@@ -805,4 +1292,142 @@ mod tests {
         let req = TestRequest::default().to_srv_request();
         srv.call(req).await.unwrap();
     }
+
+    #[actix_rt::test]
+    async fn test_custom_response_log() {
+        let mut format = Format::new("cache: %{CACHE}xo");
+        if let FormatText::CustomResponse(_, f) = &mut format.0[1] {
+            f.replace(CustomResponseFn {
+                inner_fn: Rc::new(|_req, res| {
+                    res.headers()
+                        .get("x-cache")
+                        .map_or("-".to_owned(), |v| v.to_str().unwrap().to_owned())
+                }),
+            });
+        }
+
+        let req = TestRequest::default().to_http_request();
+        let resp = HttpResponse::Ok()
+            .insert_header(("x-cache", "hit"))
+            .finish();
+        for unit in &mut format.0 {
+            unit.render_custom_response(&req, resp.head());
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(s, "cache: hit");
+    }
+
+    fn capture(logger: Logger) -> (Logger, Rc<RefCell<Vec<LogEntry>>>) {
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        let sink_entries = entries.clone();
+        let logger = logger.sink(move |entry| sink_entries.borrow_mut().push(entry.clone()));
+        (logger, entries)
+    }
+
+    #[actix_rt::test]
+    async fn test_structured() {
+        let (logger, entries) = capture(
+            Logger::structured()
+                .request_header(header::USER_AGENT)
+                .response_header(HeaderName::from_static("x-test"))
+                .custom_request_replace("user", |_req| "bob".to_owned())
+                .custom_response_replace("handler", |_req, res| res.status.as_str().to_owned()),
+        );
+
+        let srv = |req: ServiceRequest| {
+            ok(req.into_response(
+                HttpResponse::Ok()
+                    .insert_header(("X-Test", "ttt"))
+                    .body("hello"),
+            ))
+        };
+        let srv = logger.new_transform(srv.into_service()).await.unwrap();
+
+        let req = TestRequest::with_uri("/test?page=2")
+            .insert_header((header::USER_AGENT, "ACTIX-WEB"))
+            .peer_addr("127.0.0.1:8081".parse().unwrap())
+            .to_srv_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(res).await, Bytes::from_static(b"hello"));
+
+        let entries = entries.borrow();
+        assert_eq!(entries.len(), 1);
+
+        let entry = &entries[0];
+        let str = |s: &str| Some(LogValue::Str(s.to_owned()));
+        assert_eq!(entry.get("remote_addr").cloned(), str("127.0.0.1:8081"));
+        assert_eq!(entry.get("method").cloned(), str("GET"));
+        assert_eq!(entry.get("path").cloned(), str("/test"));
+        assert_eq!(entry.get("query").cloned(), str("page=2"));
+        assert_eq!(entry.get("version").cloned(), str("HTTP/1.1"));
+        assert_eq!(
+            entry.get("request_header.user-agent").cloned(),
+            str("ACTIX-WEB")
+        );
+        assert_eq!(entry.get("response_header.x-test").cloned(), str("ttt"));
+        assert_eq!(entry.get("user").cloned(), str("bob"));
+        assert_eq!(entry.get("handler").cloned(), str("200"));
+        assert_eq!(entry.get("status"), Some(&LogValue::Int(200)));
+        assert_eq!(entry.get("size"), Some(&LogValue::Int(5)));
+        assert!(matches!(entry.get("duration_ms"), Some(LogValue::Float(_))));
+        assert!(entry.get("request_id").is_none());
+    }
+
+    #[test]
+    fn test_log_entry_json() {
+        let mut entry = LogEntry::default();
+        entry.insert("path", LogValue::Str("/a \"b\"".to_owned()));
+        entry.insert("status", LogValue::Int(404));
+        entry.insert("referer", LogValue::Null);
+        entry.insert("duration_ms", LogValue::Float(1.5));
+
+        assert_eq!(
+            entry.to_json(),
+            r#"{"path":"/a \"b\"","status":404,"referer":null,"duration_ms":1.500000}"#
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_errors_only() {
+        let (logger, entries) = capture(Logger::structured().errors_only());
+
+        let srv = |req: ServiceRequest| {
+            let res = if req.path() == "/err" {
+                HttpResponse::InternalServerError().finish()
+            } else {
+                HttpResponse::Ok().finish()
+            };
+            ok(req.into_response(res))
+        };
+        let srv = logger.new_transform(srv.into_service()).await.unwrap();
+
+        for path in &["/", "/err", "/"] {
+            let req = TestRequest::with_uri(path).to_srv_request();
+            drop(test::call_service(&srv, req).await);
+        }
+
+        let entries = entries.borrow();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].get("status"), Some(&LogValue::Int(500)));
+    }
+
+    #[actix_rt::test]
+    async fn test_sample() {
+        let (logger, entries) = capture(Logger::structured().sample(1.0));
+        let srv = logger.new_transform(test::ok_service()).await.unwrap();
+
+        for _ in 0..3 {
+            let req = TestRequest::default().to_srv_request();
+            drop(test::call_service(&srv, req).await);
+        }
+        assert_eq!(entries.borrow().len(), 3);
+    }
 }
@@ -1,20 +1,117 @@
 //! Commonly used middleware.
 
+/// Defines a cheaply cloneable string that a middleware stores in the request extensions, along
+/// with an extractor for it that fails with a `500 Internal Server Error` and the given message
+/// if the middleware is not registered.
+macro_rules! extension_str {
+    ($(#[$meta:meta])* pub struct $name:ident; $missing:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(std::rc::Rc<str>);
+
+        impl $name {
+            /// Returns the value as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::ops::Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl $crate::FromRequest for $name {
+            type Config = ();
+            type Error = $crate::Error;
+            type Future = futures_util::future::Ready<Result<Self, $crate::Error>>;
+
+            fn from_request(
+                req: &$crate::HttpRequest,
+                _: &mut $crate::dev::Payload,
+            ) -> Self::Future {
+                futures_util::future::ready(match req.extensions().get::<$name>() {
+                    Some(val) => Ok(val.clone()),
+                    None => Err($crate::error::ErrorInternalServerError($missing)),
+                })
+            }
+        }
+    };
+}
+
+mod allow_content_type;
+mod body_limit;
+mod cache;
+mod catch_panic;
 mod compat;
 mod condition;
+mod conditional_get;
 mod default_headers;
 mod err_handlers;
 mod logger;
 mod normalize;
+mod rate_limit;
+mod request_id;
+mod security_headers;
+mod timeout;
 
+pub use self::allow_content_type::AllowContentType;
+pub use self::body_limit::BodyLimit;
+pub use self::cache::{Cache, CacheStatus};
+pub use self::catch_panic::{CatchPanic, PanicReport};
 pub use self::compat::Compat;
 pub use self::condition::Condition;
+pub use self::conditional_get::ConditionalGet;
 pub use self::default_headers::DefaultHeaders;
 pub use self::err_handlers::{ErrorHandlerResponse, ErrorHandlers};
-pub use self::logger::Logger;
+pub use self::logger::{LogEntry, LogValue, Logger};
 pub use self::normalize::{NormalizePath, TrailingSlash};
+pub use self::rate_limit::{
+    MemoryRateLimitStore, Quota, RateLimitInfo, RateLimitStore, RateLimiter,
+};
+pub use self::request_id::{ForwardRequestId, ReqId, RequestId};
+pub use self::security_headers::{
+    ContentSecurityPolicy, CspNonce, FrameOptions, PermissionsPolicy, ReferrerPolicy,
+    SecurityHeaders, StrictTransportSecurity,
+};
+pub use self::timeout::Timeout;
 
 #[cfg(feature = "compress")]
 mod compress;
 #[cfg(feature = "compress")]
 pub use self::compress::Compress;
+
+#[cfg(feature = "secure-cookies")]
+mod csrf;
+#[cfg(feature = "secure-cookies")]
+pub use self::csrf::{Csrf, CsrfToken};
+
+/// Encodes bytes as a lowercase hex string.
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(HEX[usize::from(byte >> 4)] as char);
+        hex.push(HEX[usize::from(byte & 0x0f)] as char);
+    }
+    hex
+}
+
+/// Generates `len` random bytes encoded as a hex string.
+pub(crate) fn random_hex(len: usize) -> String {
+    use rand::Rng as _;
+
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill(&mut bytes[..]);
+    hex_encode(&bytes)
+}
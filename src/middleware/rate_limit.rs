@@ -0,0 +1,529 @@
+//! For middleware documentation, see [`RateLimiter`].
+
+use std::{
+    cmp,
+    hash::{BuildHasher, Hash, Hasher},
+    net::SocketAddr,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_service::{Service, Transform};
+use ahash::{AHashMap, RandomState};
+use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    error::Error,
+    http::{
+        header::{HeaderName, HeaderValue, RETRY_AFTER},
+        HeaderMap,
+    },
+    HttpResponse,
+};
+
+const RATELIMIT_LIMIT: &str = "ratelimit-limit";
+const RATELIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATELIMIT_RESET: &str = "ratelimit-reset";
+
+/// Rate limit quota.
+///
+/// A quota allows `limit` requests per `period`. Requests are counted using a token bucket that
+/// holds up to `burst` tokens (equal to `limit` by default) and is refilled at a constant rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    limit: u32,
+    period: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// Constructs a quota allowing `limit` requests per `period`.
+    ///
+    /// Requests are replenished at most once per nanosecond, so higher rates are limited to one
+    /// request per nanosecond.
+    ///
+    /// # Panics
+    /// Panics if `limit` or `period` is zero.
+    pub fn new(limit: u32, period: Duration) -> Self {
+        assert!(limit > 0, "Quota limit must be greater than zero");
+        assert!(
+            period > Duration::from_secs(0),
+            "Quota period must be greater than zero"
+        );
+
+        Quota {
+            limit,
+            period,
+            burst: limit,
+        }
+    }
+
+    /// Constructs a quota allowing `limit` requests per second.
+    pub fn per_second(limit: u32) -> Self {
+        Quota::new(limit, Duration::from_secs(1))
+    }
+
+    /// Constructs a quota allowing `limit` requests per minute.
+    pub fn per_minute(limit: u32) -> Self {
+        Quota::new(limit, Duration::from_secs(60))
+    }
+
+    /// Sets the maximum number of requests allowed in a burst.
+    ///
+    /// # Panics
+    /// Panics if `burst` is zero.
+    pub fn burst(mut self, burst: u32) -> Self {
+        assert!(burst > 0, "Quota burst must be greater than zero");
+        self.burst = burst;
+        self
+    }
+
+    /// Returns the number of requests allowed per period.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Returns the quota period.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the maximum number of requests allowed in a burst.
+    pub fn max_burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Time taken to replenish a single token.
+    ///
+    /// Quotas of more than one request per nanosecond are limited to one request per nanosecond.
+    fn replenish_interval(&self) -> Duration {
+        cmp::max(self.period / self.limit, Duration::from_nanos(1))
+    }
+}
+
+/// Outcome of a rate limit check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+    /// Maximum number of requests allowed in a burst.
+    pub limit: u32,
+    /// Number of requests still allowed right now.
+    pub remaining: u32,
+    /// Time until the quota is fully replenished.
+    pub reset: Duration,
+    /// Time until the next request is allowed, for rejected requests.
+    pub retry_after: Option<Duration>,
+}
+
+/// Storage backend for [`RateLimiter`].
+///
+/// Stores are shared between server workers and must therefore be `Send + Sync`.
+pub trait RateLimitStore: Send + Sync {
+    /// Counts a request against the given key and quota.
+    fn acquire(
+        &self,
+        key: &str,
+        quota: &Quota,
+    ) -> LocalBoxFuture<'static, Result<RateLimitInfo, Error>>;
+}
+
+/// In-memory rate limit store.
+///
+/// Keys are spread over a number of independently locked shards to reduce contention between
+/// workers. Buckets that have fully replenished are removed periodically.
+pub struct MemoryRateLimitStore {
+    shards: Box<[Mutex<Shard>]>,
+    hasher: RandomState,
+}
+
+#[derive(Default)]
+struct Shard {
+    buckets: AHashMap<String, Bucket>,
+    ops: usize,
+}
+
+struct Bucket {
+    /// Time at which the bucket will be full again.
+    full_at: Instant,
+}
+
+const DEFAULT_SHARDS: usize = 32;
+const CLEANUP_INTERVAL: usize = 1024;
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        MemoryRateLimitStore::with_shards(DEFAULT_SHARDS)
+    }
+}
+
+impl MemoryRateLimitStore {
+    /// Constructs an in-memory store with the default number of shards.
+    pub fn new() -> Self {
+        MemoryRateLimitStore::default()
+    }
+
+    /// Constructs an in-memory store with the given number of shards.
+    ///
+    /// # Panics
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "Number of shards must be greater than zero");
+
+        MemoryRateLimitStore {
+            shards: (0..shards).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn acquire_at(&self, key: &str, quota: &Quota, now: Instant) -> RateLimitInfo {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        let idx = hasher.finish() as usize % self.shards.len();
+
+        let mut shard = self.shards[idx].lock().unwrap();
+
+        shard.ops += 1;
+        if shard.ops >= CLEANUP_INTERVAL {
+            shard.ops = 0;
+            shard.buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let interval = quota.replenish_interval();
+        let capacity = interval * quota.burst;
+
+        let bucket = shard
+            .buckets
+            .entry(key.to_owned())
+            .or_insert(Bucket { full_at: now });
+
+        // time the bucket needs to be full again, i.e. the tokens currently missing
+        let missing = bucket.full_at.saturating_duration_since(now);
+
+        if missing + interval > capacity {
+            let retry_after = missing + interval - capacity;
+
+            return RateLimitInfo {
+                allowed: false,
+                limit: quota.burst,
+                remaining: 0,
+                reset: missing,
+                retry_after: Some(retry_after),
+            };
+        }
+
+        let missing = missing + interval;
+        bucket.full_at = now + missing;
+
+        RateLimitInfo {
+            allowed: true,
+            limit: quota.burst,
+            remaining: ((capacity - missing).as_nanos() / interval.as_nanos()) as u32,
+            reset: missing,
+            retry_after: None,
+        }
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    fn acquire(
+        &self,
+        key: &str,
+        quota: &Quota,
+    ) -> LocalBoxFuture<'static, Result<RateLimitInfo, Error>> {
+        ok(self.acquire_at(key, quota, Instant::now())).boxed_local()
+    }
+}
+
+/// Middleware for limiting request rates.
+///
+/// Requests are grouped by a key, which defaults to the IP of the "real IP" remote address (see
+/// [`ConnectionInfo::realip_remote_addr`](crate::dev::ConnectionInfo::realip_remote_addr)).
+/// Requests without a key are not limited. Requests over the [`Quota`] are rejected with
+/// `429 Too Many Requests` and a `Retry-After` header. All responses carry `RateLimit-Limit`,
+/// `RateLimit-Remaining` and `RateLimit-Reset` headers.
+///
+/// Counts are kept in a [`MemoryRateLimitStore`] by default. Each `RateLimiter` constructed with
+/// [`new`](Self::new) has its own store, which is shared by its clones. Since the server's app
+/// factory runs once per worker, create the limiter outside of it so that workers share counts.
+///
+/// Different quotas can be applied to parts of an application by wrapping a `Scope` or
+/// `Resource` with its own limiter.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::{Quota, RateLimiter}, web, App, HttpResponse};
+///
+/// let limiter = RateLimiter::new(Quota::per_second(10).burst(20));
+/// let login_limiter = RateLimiter::new(Quota::per_minute(5));
+///
+/// let app = App::new()
+///     .wrap(limiter.clone())
+///     .service(
+///         web::resource("/login")
+///             .wrap(login_limiter.clone())
+///             .route(web::post().to(|| HttpResponse::Ok())),
+///     );
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    quota: Quota,
+    key: Arc<dyn Fn(&ServiceRequest) -> Option<String> + Send + Sync>,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    /// Constructs a `RateLimiter` middleware with the given quota and a new in-memory store.
+    pub fn new(quota: Quota) -> Self {
+        RateLimiter {
+            quota,
+            key: Arc::new(default_key),
+            store: Arc::new(MemoryRateLimitStore::new()),
+        }
+    }
+
+    /// Sets the function used to derive the rate limit key from a request.
+    ///
+    /// Requests for which the function returns `None` are not limited.
+    pub fn key<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Arc::new(f);
+        self
+    }
+
+    /// Sets the store used to count requests.
+    pub fn store<S>(mut self, store: S) -> Self
+    where
+        S: RateLimitStore + 'static,
+    {
+        self.store = Arc::new(store);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimiterMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimiterMiddleware {
+            service: Rc::new(service),
+            inner: self.clone(),
+        })
+    }
+}
+
+/// Rate limiting middleware service.
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+    inner: RateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let key = match (self.inner.key)(&req) {
+            Some(key) => key,
+            None => return self.service.call(req).boxed_local(),
+        };
+
+        let service = self.service.clone();
+        let acquire = self.inner.store.acquire(&key, &self.inner.quota);
+
+        async move {
+            let info = acquire.await?;
+
+            let mut res = if info.allowed {
+                service.call(req).await?
+            } else {
+                let mut res = HttpResponse::TooManyRequests().finish();
+                let retry_after = info.retry_after.unwrap_or(info.reset);
+                res.headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(ceil_secs(retry_after)));
+
+                req.into_response(res.into_body())
+            };
+
+            insert_headers(res.headers_mut(), &info);
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+/// Uses the "real IP" remote address, without port, as key.
+fn default_key(req: &ServiceRequest) -> Option<String> {
+    let info = req.connection_info();
+    let addr = info.realip_remote_addr()?;
+
+    match addr.parse::<SocketAddr>() {
+        Ok(addr) => Some(addr.ip().to_string()),
+        Err(_) => Some(addr.to_owned()),
+    }
+}
+
+fn insert_headers(headers: &mut HeaderMap, info: &RateLimitInfo) {
+    let values = [
+        (RATELIMIT_LIMIT, u64::from(info.limit)),
+        (RATELIMIT_REMAINING, u64::from(info.remaining)),
+        (RATELIMIT_RESET, ceil_secs(info.reset)),
+    ];
+
+    for &(name, value) in values.iter() {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+fn ceil_secs(dur: Duration) -> u64 {
+    dur.as_secs() + u64::from(dur.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+    };
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryRateLimitStore::with_shards(1);
+        let quota = Quota::per_second(2);
+        let now = Instant::now();
+
+        let info = store.acquire_at("a", &quota, now);
+        assert!(info.allowed);
+        assert_eq!(info.limit, 2);
+        assert_eq!(info.remaining, 1);
+        assert_eq!(info.reset, Duration::from_millis(500));
+
+        let info = store.acquire_at("a", &quota, now);
+        assert!(info.allowed);
+        assert_eq!(info.remaining, 0);
+        assert_eq!(info.reset, Duration::from_secs(1));
+
+        let info = store.acquire_at("a", &quota, now);
+        assert!(!info.allowed);
+        assert_eq!(info.retry_after, Some(Duration::from_millis(500)));
+
+        // other keys are counted separately
+        assert!(store.acquire_at("b", &quota, now).allowed);
+
+        // one token is replenished every 500ms
+        let later = now + Duration::from_millis(500);
+        let info = store.acquire_at("a", &quota, later);
+        assert!(info.allowed);
+        assert_eq!(info.remaining, 0);
+        assert!(!store.acquire_at("a", &quota, later).allowed);
+    }
+
+    #[test]
+    fn test_burst() {
+        let store = MemoryRateLimitStore::new();
+        let quota = Quota::per_minute(1).burst(3);
+        let now = Instant::now();
+
+        for remaining in (0..3).rev() {
+            let info = store.acquire_at("a", &quota, now);
+            assert!(info.allowed);
+            assert_eq!(info.remaining, remaining);
+        }
+
+        let info = store.acquire_at("a", &quota, now);
+        assert!(!info.allowed);
+        assert_eq!(info.retry_after, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_sub_nanosecond_interval() {
+        let store = MemoryRateLimitStore::new();
+        let quota = Quota::new(2_000_000_000, Duration::from_secs(1)).burst(2);
+        let now = Instant::now();
+
+        let info = store.acquire_at("a", &quota, now);
+        assert!(info.allowed);
+        assert_eq!(info.remaining, 1);
+        assert!(store.acquire_at("a", &quota, now).allowed);
+        assert!(!store.acquire_at("a", &quota, now).allowed);
+    }
+
+    #[actix_rt::test]
+    async fn test_middleware() {
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mw = RateLimiter::new(Quota::per_minute(1))
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(RATELIMIT_LIMIT).unwrap(), "1");
+        assert_eq!(res.headers().get(RATELIMIT_REMAINING).unwrap(), "0");
+        assert_eq!(res.headers().get(RATELIMIT_RESET).unwrap(), "60");
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8081".parse().unwrap())
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "60");
+
+        // requests without a key are not limited
+        let req = TestRequest::default().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(RATELIMIT_LIMIT).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_custom_key() {
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mw = RateLimiter::new(Quota::per_minute(1))
+            .key(|req| Some(req.path().to_owned()))
+            .store(MemoryRateLimitStore::with_shards(4))
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/a").to_srv_request();
+        assert_eq!(test::call_service(&mw, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/b").to_srv_request();
+        assert_eq!(test::call_service(&mw, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/a").to_srv_request();
+        assert_eq!(
+            test::call_service(&mw, req).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! For middleware documentation, see [`RequestId`].
+
+use std::{
+    convert::TryFrom,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_http::error::{Error, ErrorInternalServerError};
+use actix_service::{Service, Transform};
+use awc::ClientRequest;
+use futures_util::future::{self, ok, Ready};
+
+use super::hex_encode;
+use crate::{
+    dev::Payload,
+    http::{
+        header::{HeaderName, HeaderValue},
+        Error as HttpError,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    FromRequest, HttpMessage, HttpRequest,
+};
+
+/// Middleware for assigning an ID to each request.
+///
+/// The ID is read from the request header (`X-Request-Id` by default) or, if the header is
+/// missing, produced by a generator that creates random UUIDs by default. The ID is stored in the
+/// request extensions, where handlers can access it using the [`ReqId`] extractor, and is echoed
+/// on the response under the same header name.
+///
+/// [`Logger`](super::Logger) can print the ID using the `%{request-id}x` format token.
+///
+/// Outgoing `awc` requests can carry the same ID using the [`ForwardRequestId`] extractor.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::{Logger, ReqId, RequestId}, web, App};
+///
+/// let app = App::new()
+///     .wrap(Logger::new("%{request-id}x %r %s"))
+///     .wrap(RequestId::new().header("X-Correlation-Id"))
+///     .route("/", web::get().to(|id: ReqId| async move { id.to_string() }));
+/// ```
+#[derive(Clone)]
+pub struct RequestId {
+    header: HeaderName,
+    generator: Rc<dyn Fn() -> String>,
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId {
+            header: HeaderName::from_static("x-request-id"),
+            generator: Rc::new(generate_uuid),
+        }
+    }
+}
+
+impl RequestId {
+    /// Constructs a `RequestId` middleware using the `X-Request-Id` header.
+    pub fn new() -> Self {
+        RequestId::default()
+    }
+
+    /// Sets the name of the header the ID is read from and echoed on.
+    ///
+    /// # Panics
+    /// Panics if `name` is not a valid header name.
+    pub fn header<K>(mut self, name: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        self.header = HeaderName::try_from(name)
+            .map_err(Into::into)
+            .expect("Can not create header name");
+        self
+    }
+
+    /// Sets the function used to generate IDs for requests that do not carry one.
+    ///
+    /// Generated IDs that are not valid header values are not echoed on the response.
+    pub fn generator<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> String + 'static,
+    {
+        self.generator = Rc::new(f);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdMiddleware {
+            service,
+            inner: self.clone(),
+        })
+    }
+}
+
+/// Request ID middleware service.
+pub struct RequestIdMiddleware<S> {
+    service: S,
+    inner: RequestId,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = RequestIdFuture<S, B>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(&self.inner.header)
+            .and_then(|val| val.to_str().ok())
+            .filter(|val| !val.is_empty())
+            .map(ReqId::new)
+            .unwrap_or_else(|| ReqId::new(&(self.inner.generator)()));
+
+        let header = self.inner.header.clone();
+
+        req.extensions_mut().insert(id.clone());
+        req.extensions_mut().insert(ForwardRequestId {
+            header: header.clone(),
+            id: id.clone(),
+        });
+
+        RequestIdFuture {
+            fut: self.service.call(req),
+            header,
+            id,
+            _body: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct RequestIdFuture<S: Service<ServiceRequest>, B> {
+    #[pin]
+    fut: S::Future,
+    header: HeaderName,
+    id: ReqId,
+    _body: PhantomData<B>,
+}
+
+impl<S, B> Future for RequestIdFuture<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures_util::ready!(this.fut.poll(cx))?;
+
+        if !res.headers().contains_key(&*this.header) {
+            if let Ok(val) = HeaderValue::from_str(this.id.as_str()) {
+                res.headers_mut().insert(this.header.clone(), val);
+            }
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+/// Generates a random (version 4) UUID.
+fn generate_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex_encode(&bytes[..4]),
+        hex_encode(&bytes[4..6]),
+        hex_encode(&bytes[6..8]),
+        hex_encode(&bytes[8..10]),
+        hex_encode(&bytes[10..])
+    )
+}
+
+extension_str! {
+    /// Request ID assigned by the [`RequestId`] middleware.
+    ///
+    /// Can be used as an extractor. Extraction fails with a `500 Internal Server Error` if the
+    /// middleware is not registered.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use actix_web::{middleware::ReqId, Responder};
+    ///
+    /// async fn index(id: ReqId) -> impl Responder {
+    ///     format!("request {}", id)
+    /// }
+    /// ```
+    pub struct ReqId;
+    "RequestId middleware is not registered"
+}
+
+impl ReqId {
+    pub(crate) fn new(id: &str) -> Self {
+        ReqId(id.into())
+    }
+}
+
+/// Request ID to forward on outgoing `awc` requests.
+///
+/// Holds the ID assigned by the [`RequestId`] middleware and the name of the header it uses. Can
+/// be used as an extractor. Extraction fails with a `500 Internal Server Error` if the
+/// middleware is not registered.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{middleware::ForwardRequestId, Error, HttpResponse};
+///
+/// async fn index(fwd: ForwardRequestId) -> Result<HttpResponse, Error> {
+///     let req = fwd.apply(awc::Client::new().get("http://localhost:8080/"));
+///     let mut res = req.send().await?;
+///     Ok(HttpResponse::Ok().body(res.body().await?))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForwardRequestId {
+    header: HeaderName,
+    id: ReqId,
+}
+
+impl ForwardRequestId {
+    /// Returns the request ID.
+    pub fn id(&self) -> &ReqId {
+        &self.id
+    }
+
+    /// Adds the request ID to a client request, unless its header is already set.
+    pub fn apply(&self, req: ClientRequest) -> ClientRequest {
+        match HeaderValue::from_str(self.id.as_str()) {
+            Ok(val) => req.insert_header_if_none((self.header.clone(), val)),
+            Err(_) => req,
+        }
+    }
+}
+
+impl FromRequest for ForwardRequestId {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<ForwardRequestId>() {
+            Some(fwd) => ok(fwd.clone()),
+            None => future::err(ErrorInternalServerError(
+                "RequestId middleware is not registered",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn test_generated_id() {
+        let srv = |req: ServiceRequest| {
+            let id = req.extensions().get::<ReqId>().unwrap().clone();
+            ok(req.into_response(HttpResponse::Ok().body(id.to_string())))
+        };
+        let mw = RequestId::new()
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let id = res.headers().get("x-request-id").unwrap().clone();
+        assert_eq!(id.len(), 36);
+        assert_eq!(test::read_body(res).await, id.as_bytes());
+    }
+
+    #[actix_rt::test]
+    async fn test_incoming_id() {
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mw = RequestId::new()
+            .header("x-correlation-id")
+            .generator(|| "generated".to_owned())
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .insert_header(("x-correlation-id", "abc"))
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.headers().get("x-correlation-id").unwrap(), "abc");
+
+        let req = TestRequest::default().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.headers().get("x-correlation-id").unwrap(), "generated");
+    }
+
+    #[actix_rt::test]
+    async fn test_extractor() {
+        let srv = test::init_service(App::new().wrap(RequestId::new()).route(
+            "/",
+            web::get().to(|id: ReqId| async move { id.to_string() }),
+        ))
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header(("x-request-id", "123"))
+            .to_request();
+        let body = test::read_response(&srv, req).await;
+        assert_eq!(body, "123");
+
+        let srv = test::init_service(App::new().route(
+            "/",
+            web::get().to(|id: ReqId| async move { id.to_string() }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_rt::test]
+    async fn test_forward_id() {
+        let srv = test::init_service(App::new().wrap(RequestId::new()).route(
+            "/",
+            web::get().to(|fwd: ForwardRequestId| async move {
+                let req = fwd.apply(awc::Client::new().get("http://localhost/"));
+                let id = req.headers().get("x-request-id").unwrap();
+                id.to_str().unwrap().to_owned()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header(("x-request-id", "123"))
+            .to_request();
+        let body = test::read_response(&srv, req).await;
+        assert_eq!(body, "123");
+    }
+}
@@ -0,0 +1,723 @@
+//! For middleware documentation, see [`SecurityHeaders`].
+
+use std::{
+    fmt::{self, Write as _},
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::{
+    future::{ok, Ready},
+    ready,
+};
+
+use crate::{
+    dev::{Service, Transform},
+    http::{
+        header::{
+            HeaderName, HeaderValue, CONTENT_SECURITY_POLICY,
+            CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY,
+            X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+        },
+        HeaderMap,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpMessage,
+};
+
+/// Middleware for setting security related response headers.
+///
+/// [`new`](Self::new) sets the following headers:
+///
+/// Header | Value
+/// ------ | -----
+/// `Strict-Transport-Security` | `max-age=31536000`
+/// `X-Content-Type-Options` | `nosniff`
+/// `X-Frame-Options` | `DENY`
+/// `Referrer-Policy` | `strict-origin-when-cross-origin`
+///
+/// Use [`empty`](Self::empty) to start without any headers. Headers with the same name that are
+/// already set in a response will *not* be overwritten.
+///
+/// `Strict-Transport-Security` is only sent when the request scheme, as reported by
+/// [`ConnectionInfo::scheme`](crate::dev::ConnectionInfo::scheme), is `https`.
+///
+/// A [`ContentSecurityPolicy`] can include a nonce that is generated for each request. Handlers
+/// can access it using the [`CspNonce`] extractor to mark inline scripts and styles.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::{
+///     middleware::{ContentSecurityPolicy, CspNonce, ReferrerPolicy, SecurityHeaders},
+///     web, App, HttpResponse,
+/// };
+///
+/// async fn index(nonce: CspNonce) -> HttpResponse {
+///     HttpResponse::Ok().body(format!(r#"<script nonce="{}">init()</script>"#, nonce))
+/// }
+///
+/// let app = App::new()
+///     .wrap(
+///         SecurityHeaders::new()
+///             .referrer_policy(ReferrerPolicy::NoReferrer)
+///             .content_security_policy(
+///                 ContentSecurityPolicy::new()
+///                     .directive("default-src", &["'self'"])
+///                     .nonce("script-src"),
+///             ),
+///     )
+///     .route("/", web::get().to(index));
+/// ```
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    headers: HeaderMap,
+    hsts: Option<HeaderValue>,
+    csp: Option<ContentSecurityPolicy>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeaders::new()
+    }
+}
+
+impl SecurityHeaders {
+    /// Constructs a `SecurityHeaders` middleware with recommended defaults.
+    pub fn new() -> Self {
+        SecurityHeaders::empty()
+            .hsts(StrictTransportSecurity::default())
+            .content_type_options(true)
+            .frame_options(FrameOptions::Deny)
+            .referrer_policy(ReferrerPolicy::StrictOriginWhenCrossOrigin)
+    }
+
+    /// Constructs a `SecurityHeaders` middleware that sets no headers.
+    pub fn empty() -> Self {
+        SecurityHeaders {
+            inner: Rc::new(Inner {
+                headers: HeaderMap::new(),
+                hsts: None,
+                csp: None,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+
+    fn set_header(&mut self, name: HeaderName, value: Option<HeaderValue>) {
+        let headers = &mut self.inner_mut().headers;
+
+        match value {
+            Some(value) => {
+                headers.insert(name, value);
+            }
+            None => {
+                headers.remove(name);
+            }
+        }
+    }
+
+    /// Sets the `Strict-Transport-Security` header sent over HTTPS.
+    pub fn hsts(mut self, hsts: StrictTransportSecurity) -> Self {
+        self.inner_mut().hsts = Some(hsts.to_header_value());
+        self
+    }
+
+    /// Removes the `Strict-Transport-Security` header.
+    pub fn no_hsts(mut self) -> Self {
+        self.inner_mut().hsts = None;
+        self
+    }
+
+    /// Sets the `Content-Security-Policy` or `Content-Security-Policy-Report-Only` header.
+    ///
+    /// # Panics
+    /// Panics if the policy is not a valid header value.
+    pub fn content_security_policy(mut self, csp: ContentSecurityPolicy) -> Self {
+        self.set_header(CONTENT_SECURITY_POLICY, None);
+        self.set_header(CONTENT_SECURITY_POLICY_REPORT_ONLY, None);
+
+        if csp.needs_nonce() {
+            // check that the policy renders to a valid header value
+            csp.to_header_value(Some("0"));
+            self.inner_mut().csp = Some(csp);
+        } else {
+            self.set_header(csp.header_name(), Some(csp.to_header_value(None)));
+            self.inner_mut().csp = None;
+        }
+
+        self
+    }
+
+    /// Enables or disables the `X-Content-Type-Options: nosniff` header.
+    pub fn content_type_options(mut self, enable: bool) -> Self {
+        let value = if enable {
+            Some(HeaderValue::from_static("nosniff"))
+        } else {
+            None
+        };
+
+        self.set_header(X_CONTENT_TYPE_OPTIONS, value);
+        self
+    }
+
+    /// Sets the `X-Frame-Options` header.
+    pub fn frame_options(mut self, frame_options: FrameOptions) -> Self {
+        let value = HeaderValue::from_static(frame_options.as_str());
+        self.set_header(X_FRAME_OPTIONS, Some(value));
+        self
+    }
+
+    /// Removes the `X-Frame-Options` header.
+    pub fn no_frame_options(mut self) -> Self {
+        self.set_header(X_FRAME_OPTIONS, None);
+        self
+    }
+
+    /// Sets the `Referrer-Policy` header.
+    pub fn referrer_policy(mut self, policy: ReferrerPolicy) -> Self {
+        let value = HeaderValue::from_static(policy.as_str());
+        self.set_header(REFERRER_POLICY, Some(value));
+        self
+    }
+
+    /// Sets the `Permissions-Policy` header.
+    ///
+    /// # Panics
+    /// Panics if the policy is not a valid header value.
+    pub fn permissions_policy(mut self, policy: PermissionsPolicy) -> Self {
+        let value = HeaderValue::from_str(&policy.to_string())
+            .expect("Invalid Permissions-Policy header value");
+        self.set_header(HeaderName::from_static("permissions-policy"), Some(value));
+        self
+    }
+}
+
+/// `Strict-Transport-Security` header builder.
+///
+/// Defaults to a `max-age` of one year.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrictTransportSecurity {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Default for StrictTransportSecurity {
+    fn default() -> Self {
+        StrictTransportSecurity::new(Duration::from_secs(365 * 24 * 60 * 60))
+    }
+}
+
+impl StrictTransportSecurity {
+    /// Constructs a policy that browsers remember for `max_age`.
+    pub fn new(max_age: Duration) -> Self {
+        StrictTransportSecurity {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Adds the `includeSubDomains` directive.
+    pub fn include_subdomains(mut self) -> Self {
+        self.include_subdomains = true;
+        self
+    }
+
+    /// Adds the `preload` directive.
+    pub fn preload(mut self) -> Self {
+        self.preload = true;
+        self
+    }
+
+    fn to_header_value(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+
+        if self.preload {
+            value.push_str("; preload");
+        }
+
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+/// `Content-Security-Policy` header builder.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::middleware::ContentSecurityPolicy;
+///
+/// ContentSecurityPolicy::new()
+///     .directive("default-src", &["'self'"])
+///     .directive("img-src", &["'self'", "https://images.example.com"])
+///     .directive("upgrade-insecure-requests", &[] as &[&str])
+///     .nonce("script-src");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<Directive>,
+    report_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    name: String,
+    sources: Vec<String>,
+    nonce: bool,
+}
+
+impl ContentSecurityPolicy {
+    /// Constructs an empty policy.
+    pub fn new() -> Self {
+        ContentSecurityPolicy::default()
+    }
+
+    fn directive_mut(&mut self, name: &str) -> &mut Directive {
+        let pos = self.directives.iter().position(|dir| dir.name == name);
+
+        let pos = pos.unwrap_or_else(|| {
+            self.directives.push(Directive {
+                name: name.to_owned(),
+                sources: Vec::new(),
+                nonce: false,
+            });
+            self.directives.len() - 1
+        });
+
+        &mut self.directives[pos]
+    }
+
+    /// Adds sources to the given directive, e.g. `script-src`.
+    ///
+    /// Keyword sources must be quoted, e.g. `'self'`.
+    pub fn directive<I>(mut self, name: &str, sources: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let directive = self.directive_mut(name);
+        directive
+            .sources
+            .extend(sources.into_iter().map(|src| src.as_ref().to_owned()));
+        self
+    }
+
+    /// Adds the per-request nonce as a source to the given directive, e.g. `script-src`.
+    ///
+    /// The nonce is available to handlers through the [`CspNonce`] extractor.
+    pub fn nonce(mut self, name: &str) -> Self {
+        self.directive_mut(name).nonce = true;
+        self
+    }
+
+    /// Sends the policy as `Content-Security-Policy-Report-Only`, so that violations are reported
+    /// but not enforced.
+    pub fn report_only(mut self) -> Self {
+        self.report_only = true;
+        self
+    }
+
+    fn needs_nonce(&self) -> bool {
+        self.directives.iter().any(|dir| dir.nonce)
+    }
+
+    fn header_name(&self) -> HeaderName {
+        if self.report_only {
+            CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            CONTENT_SECURITY_POLICY
+        }
+    }
+
+    fn to_header_value(&self, nonce: Option<&str>) -> HeaderValue {
+        let mut value = String::new();
+
+        for (idx, dir) in self.directives.iter().enumerate() {
+            if idx > 0 {
+                value.push_str("; ");
+            }
+
+            value.push_str(&dir.name);
+
+            for src in &dir.sources {
+                value.push(' ');
+                value.push_str(src);
+            }
+
+            if let (true, Some(nonce)) = (dir.nonce, nonce) {
+                write!(value, " 'nonce-{}'", nonce).unwrap();
+            }
+        }
+
+        HeaderValue::from_str(&value).expect("Invalid Content-Security-Policy header value")
+    }
+}
+
+/// `Referrer-Policy` header values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferrerPolicy {
+    /// `no-referrer`
+    NoReferrer,
+    /// `no-referrer-when-downgrade`
+    NoReferrerWhenDowngrade,
+    /// `origin`
+    Origin,
+    /// `origin-when-cross-origin`
+    OriginWhenCrossOrigin,
+    /// `same-origin`
+    SameOrigin,
+    /// `strict-origin`
+    StrictOrigin,
+    /// `strict-origin-when-cross-origin`
+    StrictOriginWhenCrossOrigin,
+    /// `unsafe-url`
+    UnsafeUrl,
+}
+
+impl ReferrerPolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReferrerPolicy::NoReferrer => "no-referrer",
+            ReferrerPolicy::NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+            ReferrerPolicy::Origin => "origin",
+            ReferrerPolicy::OriginWhenCrossOrigin => "origin-when-cross-origin",
+            ReferrerPolicy::SameOrigin => "same-origin",
+            ReferrerPolicy::StrictOrigin => "strict-origin",
+            ReferrerPolicy::StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+            ReferrerPolicy::UnsafeUrl => "unsafe-url",
+        }
+    }
+}
+
+/// `X-Frame-Options` header values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOptions {
+    /// `DENY`
+    Deny,
+    /// `SAMEORIGIN`
+    SameOrigin,
+}
+
+impl FrameOptions {
+    fn as_str(self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "DENY",
+            FrameOptions::SameOrigin => "SAMEORIGIN",
+        }
+    }
+}
+
+/// `Permissions-Policy` header builder.
+///
+/// # Examples
+/// ```rust
+/// use actix_web::middleware::PermissionsPolicy;
+///
+/// // camera=(), geolocation=(self "https://maps.example.com")
+/// PermissionsPolicy::new()
+///     .feature("camera", &[] as &[&str])
+///     .feature("geolocation", &["self", "https://maps.example.com"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionsPolicy {
+    features: Vec<(String, Vec<String>)>,
+}
+
+impl PermissionsPolicy {
+    /// Constructs an empty policy.
+    pub fn new() -> Self {
+        PermissionsPolicy::default()
+    }
+
+    /// Allows the given feature for an allow-list of origins.
+    ///
+    /// Allow-list entries are `self`, `*` or origins. An empty allow-list disables the feature.
+    pub fn feature<I>(mut self, name: &str, allowlist: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let allowlist = allowlist
+            .into_iter()
+            .map(|origin| origin.as_ref().to_owned())
+            .collect();
+        self.features.push((name.to_owned(), allowlist));
+        self
+    }
+}
+
+impl fmt::Display for PermissionsPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (name, allowlist)) in self.features.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{}=(", name)?;
+
+            for (idx, origin) in allowlist.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(" ")?;
+                }
+
+                match origin.as_str() {
+                    "self" | "*" => f.write_str(origin)?,
+                    _ => write!(f, "\"{}\"", origin)?,
+                }
+            }
+
+            f.write_str(")")?;
+        }
+
+        Ok(())
+    }
+}
+
+extension_str! {
+    /// Extractor for the per-request `Content-Security-Policy` nonce.
+    ///
+    /// Only available when [`SecurityHeaders`] is registered with a [`ContentSecurityPolicy`]
+    /// that uses a [nonce](ContentSecurityPolicy::nonce).
+    pub struct CspNonce;
+    "SecurityHeaders middleware with a CSP nonce is not registered"
+}
+
+impl CspNonce {
+    fn generate() -> Self {
+        CspNonce(super::random_hex(16).into())
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SecurityHeaders
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = SecurityHeadersMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SecurityHeadersMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+/// Security headers middleware service.
+pub struct SecurityHeadersMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for SecurityHeadersMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = SecurityHeadersFuture<S, B>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let https = req.connection_info().scheme() == "https";
+
+        let nonce = if self.inner.csp.is_some() {
+            let nonce = CspNonce::generate();
+            req.extensions_mut().insert(nonce.clone());
+            Some(nonce)
+        } else {
+            None
+        };
+
+        SecurityHeadersFuture {
+            fut: self.service.call(req),
+            inner: self.inner.clone(),
+            https,
+            nonce,
+            _body: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct SecurityHeadersFuture<S: Service<ServiceRequest>, B> {
+    #[pin]
+    fut: S::Future,
+    inner: Rc<Inner>,
+    https: bool,
+    nonce: Option<CspNonce>,
+    _body: PhantomData<B>,
+}
+
+impl<S, B> Future for SecurityHeadersFuture<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = <S::Future as Future>::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.fut.poll(cx))?;
+        let headers = res.headers_mut();
+
+        for (key, value) in this.inner.headers.iter() {
+            if !headers.contains_key(key) {
+                headers.insert(key.clone(), value.clone());
+            }
+        }
+
+        if let (true, Some(hsts)) = (*this.https, &this.inner.hsts) {
+            if !headers.contains_key(STRICT_TRANSPORT_SECURITY) {
+                headers.insert(STRICT_TRANSPORT_SECURITY, hsts.clone());
+            }
+        }
+
+        if let (Some(csp), Some(nonce)) = (&this.inner.csp, this.nonce.take()) {
+            let name = csp.header_name();
+            if !headers.contains_key(&name) {
+                headers.insert(name, csp.to_header_value(Some(nonce.as_str())));
+            }
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn test_defaults() {
+        let mw = SecurityHeaders::new()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(
+            res.headers().get(X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(res.headers().get(X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert_eq!(
+            res.headers().get(REFERRER_POLICY).unwrap(),
+            "strict-origin-when-cross-origin"
+        );
+        assert!(!res.headers().contains_key(STRICT_TRANSPORT_SECURITY));
+
+        let req = TestRequest::default()
+            .insert_header(("x-forwarded-proto", "https"))
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(
+            res.headers().get(STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=31536000"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_builders() {
+        let mw = SecurityHeaders::empty()
+            .hsts(
+                StrictTransportSecurity::new(Duration::from_secs(60))
+                    .include_subdomains()
+                    .preload(),
+            )
+            .frame_options(FrameOptions::SameOrigin)
+            .permissions_policy(
+                PermissionsPolicy::new()
+                    .feature("camera", &[] as &[&str])
+                    .feature("geolocation", &["self", "https://maps.example.com"]),
+            )
+            .content_security_policy(
+                ContentSecurityPolicy::new()
+                    .directive("default-src", &["'self'"])
+                    .directive("upgrade-insecure-requests", &[] as &[&str])
+                    .report_only(),
+            )
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .insert_header(("x-forwarded-proto", "https"))
+            .to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        let headers = res.headers();
+
+        assert!(!headers.contains_key(X_CONTENT_TYPE_OPTIONS));
+        assert!(!headers.contains_key(CONTENT_SECURITY_POLICY));
+        assert_eq!(
+            headers.get(STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=60; includeSubDomains; preload"
+        );
+        assert_eq!(headers.get(X_FRAME_OPTIONS).unwrap(), "SAMEORIGIN");
+        assert_eq!(
+            headers.get("permissions-policy").unwrap(),
+            r#"camera=(), geolocation=(self "https://maps.example.com")"#
+        );
+        assert_eq!(
+            headers.get(CONTENT_SECURITY_POLICY_REPORT_ONLY).unwrap(),
+            "default-src 'self'; upgrade-insecure-requests"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_csp_nonce() {
+        let srv = test::init_service(
+            App::new()
+                .wrap(
+                    SecurityHeaders::empty().content_security_policy(
+                        ContentSecurityPolicy::new()
+                            .directive("script-src", &["'self'"])
+                            .nonce("script-src"),
+                    ),
+                )
+                .route(
+                    "/",
+                    web::get().to(|nonce: CspNonce| HttpResponse::Ok().body(nonce.to_string())),
+                ),
+        )
+        .await;
+
+        let res = test::call_service(&srv, TestRequest::default().to_request()).await;
+        let csp = res.headers().get(CONTENT_SECURITY_POLICY).unwrap().clone();
+        let nonce = test::read_body(res).await;
+        let nonce = std::str::from_utf8(&nonce).unwrap();
+
+        assert_eq!(nonce.len(), 32);
+        assert_eq!(
+            csp.to_str().unwrap(),
+            format!("script-src 'self' 'nonce-{}'", nonce)
+        );
+
+        let res = test::call_service(&srv, TestRequest::default().to_request()).await;
+        let body = test::read_body(res).await;
+        assert_ne!(body, nonce.as_bytes());
+    }
+}
@@ -0,0 +1,324 @@
+//! For middleware documentation, see [`Timeout`].
+
+use std::{
+    future::Future,
+    io,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::{sleep, Sleep};
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures_util::future::{ok, Ready};
+use pin_project::pin_project;
+
+use crate::{
+    dev::{Body, BodySize, MessageBody, ResponseBody},
+    error::{Error, InternalError},
+    http::{HeaderMap, StatusCode},
+    service::{ServiceRequest, ServiceResponse},
+    HttpResponse,
+};
+
+/// Middleware for bounding the time taken to handle a request.
+///
+/// If the wrapped service has not produced a response before the deadline, its future is dropped
+/// and an error is returned instead. By default the error renders as a `503 Service Unavailable`
+/// response; use [`status`](Self::status) or [`error_response`](Self::error_response) to change
+/// it.
+///
+/// The deadline only covers producing the response head. Use
+/// [`body_timeout`](Self::body_timeout) to also bound the time taken to stream the response body;
+/// a body that does not finish in time is aborted and the connection is closed.
+///
+/// # Examples
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{http::StatusCode, middleware::Timeout, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(Timeout::new(Duration::from_secs(30)))
+///     .service(
+///         web::resource("/report")
+///             .wrap(Timeout::new(Duration::from_secs(5)).status(StatusCode::GATEWAY_TIMEOUT))
+///             .to(|| HttpResponse::Ok()),
+///     );
+/// ```
+#[derive(Clone)]
+pub struct Timeout {
+    timeout: Duration,
+    body_timeout: Option<Duration>,
+    error: Rc<dyn Fn() -> Error>,
+}
+
+impl Timeout {
+    /// Constructs a `Timeout` middleware with the given deadline for producing a response.
+    pub fn new(timeout: Duration) -> Self {
+        Timeout {
+            timeout,
+            body_timeout: None,
+            error: Rc::new(|| {
+                InternalError::new("Request timed out", StatusCode::SERVICE_UNAVAILABLE).into()
+            }),
+        }
+    }
+
+    /// Sets the status code of the response sent when the deadline is reached.
+    ///
+    /// Default is `503 Service Unavailable`.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.error = Rc::new(move || InternalError::new("Request timed out", status).into());
+        self
+    }
+
+    /// Sets a function that creates the response sent when the deadline is reached.
+    pub fn error_response<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> HttpResponse + 'static,
+    {
+        self.error =
+            Rc::new(move || InternalError::from_response("Request timed out", f()).into());
+        self
+    }
+
+    /// Bounds the time taken to stream the response body, counted from when the response head is
+    /// produced.
+    ///
+    /// By default, body streaming time is not limited.
+    pub fn body_timeout(mut self, timeout: Duration) -> Self {
+        self.body_timeout = Some(timeout);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Timeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TimeoutMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TimeoutMiddleware {
+            service,
+            inner: self.clone(),
+        })
+    }
+}
+
+/// Timeout middleware service.
+pub struct TimeoutMiddleware<S> {
+    service: S,
+    inner: Timeout,
+}
+
+impl<S, B> Service<ServiceRequest> for TimeoutMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = TimeoutFuture<S, B>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        TimeoutFuture {
+            fut: self.service.call(req),
+            sleep: sleep(self.inner.timeout),
+            body_timeout: self.inner.body_timeout,
+            error: self.inner.error.clone(),
+            _body: PhantomData,
+        }
+    }
+}
+
+#[pin_project]
+pub struct TimeoutFuture<S: Service<ServiceRequest>, B> {
+    #[pin]
+    fut: S::Future,
+    #[pin]
+    sleep: Sleep,
+    body_timeout: Option<Duration>,
+    error: Rc<dyn Fn() -> Error>,
+    _body: PhantomData<B>,
+}
+
+impl<S, B> Future for TimeoutFuture<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(res) = this.fut.poll(cx) {
+            // only wrap the body when it is bounded, so outer middleware can still inspect it
+            let res = match *this.body_timeout {
+                Some(dur) => res.map(|res| {
+                    res.map_body(move |_, body| {
+                        ResponseBody::Other(Body::from_message(TimeoutBody {
+                            body: Box::pin(body),
+                            timer: Box::pin(sleep(dur)),
+                        }))
+                    })
+                }),
+                None => res,
+            };
+
+            return Poll::Ready(res);
+        }
+
+        futures_util::ready!(this.sleep.poll(cx));
+        Poll::Ready(Err((this.error)()))
+    }
+}
+
+/// Response body that is aborted if not fully streamed before a deadline.
+struct TimeoutBody<B> {
+    body: Pin<Box<ResponseBody<B>>>,
+    timer: Pin<Box<Sleep>>,
+}
+
+impl<B: MessageBody> MessageBody for TimeoutBody<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+
+        if this.timer.as_mut().poll(cx).is_ready() {
+            let err = io::Error::new(io::ErrorKind::TimedOut, "Response body timed out");
+            return Poll::Ready(Some(Err(err.into())));
+        }
+
+        this.body.as_mut().poll_next(cx)
+    }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.get_mut().body.as_mut().take_trailers()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+    use futures_util::stream;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        HttpResponse,
+    };
+
+    async fn slow_service(req: ServiceRequest) -> Result<ServiceResponse, Error> {
+        sleep(Duration::from_millis(100)).await;
+        Ok(req.into_response(HttpResponse::Ok().finish()))
+    }
+
+    #[actix_rt::test]
+    async fn test_in_time() {
+        let mw = Timeout::new(Duration::from_millis(500))
+            .new_transform(slow_service.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_timed_out() {
+        let mw = Timeout::new(Duration::from_millis(10))
+            .new_transform(slow_service.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let err = mw.call(req).await.err().unwrap();
+        let res = err.as_response_error().error_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_rt::test]
+    async fn test_custom_error() {
+        let mw = Timeout::new(Duration::from_millis(10))
+            .error_response(|| HttpResponse::GatewayTimeout().body("too slow"))
+            .new_transform(slow_service.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let err = mw.call(req).await.err().unwrap();
+        let res = err.as_response_error().error_response();
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let mw = Timeout::new(Duration::from_millis(10))
+            .status(StatusCode::GATEWAY_TIMEOUT)
+            .new_transform(slow_service.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let err = mw.call(req).await.err().unwrap();
+        let res = err.as_response_error().error_response();
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[actix_rt::test]
+    async fn test_body_passed_through() {
+        let srv = |req: ServiceRequest| {
+            ok::<_, Error>(req.into_response(HttpResponse::Ok().body("hello")))
+        };
+
+        let mw = Timeout::new(Duration::from_millis(500))
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let res = test::call_service(&mw, req).await;
+        assert!(matches!(
+            res.response().body(),
+            ResponseBody::Body(Body::Bytes(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_body_timeout() {
+        let srv = |req: ServiceRequest| async {
+            let body = stream::pending::<Result<Bytes, Error>>();
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().streaming(body)))
+        };
+
+        let mw = Timeout::new(Duration::from_millis(500))
+            .body_timeout(Duration::from_millis(10))
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let mut res = test::call_service(&mw, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut body = res.take_body();
+        let err = futures_util::StreamExt::next(&mut body).await.unwrap();
+        assert!(err.is_err());
+    }
+}
@@ -92,6 +92,14 @@ impl HttpRequest {
         &self.head().headers
     }
 
+    /// Returns trailer fields received after the request payload.
+    ///
+    /// Trailers are only available once the payload has been read to completion, e.g. after
+    /// awaiting a [`Bytes`](bytes::Bytes) extractor or draining [`web::Payload`](crate::web::Payload).
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.head().trailers()
+    }
+
     /// The target path of this Request.
     #[inline]
     pub fn path(&self) -> &str {
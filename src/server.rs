@@ -7,7 +7,8 @@ use std::{
 };
 
 use actix_http::{
-    body::MessageBody, Error, Extensions, HttpService, KeepAlive, Request, Response,
+    body::MessageBody, Error, Extensions, HttpService, HttpServiceBuilder, KeepAlive, Request,
+    Response, ShutdownSignal,
 };
 use actix_server::{Server, ServerBuilder};
 use actix_service::{map_config, IntoServiceFactory, Service, ServiceFactory};
@@ -36,6 +37,55 @@ struct Config {
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_shutdown: u64,
+    shutdown_signal: ShutdownSignal,
+    h1_max_headers: Option<usize>,
+    h1_max_head_size: Option<usize>,
+    h1_max_uri_length: Option<usize>,
+    h2_max_concurrent_streams: Option<u32>,
+    h2_initial_window_size: Option<u32>,
+    h2_initial_connection_window_size: Option<u32>,
+    h2_max_frame_size: Option<u32>,
+    h2_max_header_list_size: Option<u32>,
+}
+
+impl Config {
+    /// Apply configured HTTP/1 limits and HTTP/2 settings to service builder.
+    fn protocol_settings<T, S>(
+        &self,
+        mut svc: HttpServiceBuilder<T, S>,
+    ) -> HttpServiceBuilder<T, S>
+    where
+        S: ServiceFactory<Request, Config = ()>,
+        S::Error: Into<Error> + 'static,
+        S::InitError: fmt::Debug,
+        <S::Service as Service<Request>>::Future: 'static,
+    {
+        if let Some(num) = self.h1_max_headers {
+            svc = svc.h1_max_headers(num);
+        }
+        if let Some(size) = self.h1_max_head_size {
+            svc = svc.h1_max_head_size(size);
+        }
+        if let Some(len) = self.h1_max_uri_length {
+            svc = svc.h1_max_uri_length(len);
+        }
+        if let Some(max) = self.h2_max_concurrent_streams {
+            svc = svc.h2_max_concurrent_streams(max);
+        }
+        if let Some(size) = self.h2_initial_window_size {
+            svc = svc.h2_initial_window_size(size);
+        }
+        if let Some(size) = self.h2_initial_connection_window_size {
+            svc = svc.h2_initial_connection_window_size(size);
+        }
+        if let Some(size) = self.h2_max_frame_size {
+            svc = svc.h2_max_frame_size(size);
+        }
+        if let Some(size) = self.h2_max_header_list_size {
+            svc = svc.h2_max_header_list_size(size);
+        }
+        svc
+    }
 }
 
 /// An HTTP Server.
@@ -95,6 +145,15 @@ where
                 keep_alive: KeepAlive::Timeout(5),
                 client_timeout: 5000,
                 client_shutdown: 5000,
+                shutdown_signal: ShutdownSignal::new(),
+                h1_max_headers: None,
+                h1_max_head_size: None,
+                h1_max_uri_length: None,
+                h2_max_concurrent_streams: None,
+                h2_initial_window_size: None,
+                h2_initial_connection_window_size: None,
+                h2_max_frame_size: None,
+                h2_max_header_list_size: None,
             })),
             backlog: 1024,
             sockets: Vec::new(),
@@ -212,6 +271,95 @@ where
         self
     }
 
+    /// Set the maximum number of headers allowed in an HTTP/1 request.
+    ///
+    /// Requests with more headers are responded to with `431 Request Header Fields Too Large`.
+    ///
+    /// By default 96 headers are allowed.
+    pub fn h1_max_headers(self, num: usize) -> Self {
+        self.config.lock().unwrap().h1_max_headers = Some(num);
+        self
+    }
+
+    /// Set the maximum size (in bytes) of an HTTP/1 request head, including request line and
+    /// headers.
+    ///
+    /// Requests with a larger head are responded to with `431 Request Header Fields Too Large`.
+    ///
+    /// By default the request head is limited to 128KiB.
+    pub fn h1_max_head_size(self, size: usize) -> Self {
+        self.config.lock().unwrap().h1_max_head_size = Some(size);
+        self
+    }
+
+    /// Set the maximum length (in bytes) of an HTTP/1 request URI.
+    ///
+    /// Requests with a longer URI are responded to with `414 URI Too Long`.
+    ///
+    /// By default the URI length is only limited by the maximum request head size.
+    pub fn h1_max_uri_length(self, len: usize) -> Self {
+        self.config.lock().unwrap().h1_max_uri_length = Some(len);
+        self
+    }
+
+    /// Set the maximum number of concurrent HTTP/2 streams a client is allowed to open.
+    ///
+    /// By default the number of concurrent streams is not limited.
+    pub fn h2_max_concurrent_streams(self, max: u32) -> Self {
+        self.config.lock().unwrap().h2_max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Set the initial window size (in octets) for HTTP/2 stream-level flow control for
+    /// received data.
+    ///
+    /// The default value is 65,535 and is good for APIs, but not for big objects.
+    ///
+    /// # Panics
+    /// The size is validated by [`HttpServiceBuilder::h2_initial_window_size`] when the server
+    /// starts, which panics if `size` is larger than 2^31 - 1.
+    pub fn h2_initial_window_size(self, size: u32) -> Self {
+        self.config.lock().unwrap().h2_initial_window_size = Some(size);
+        self
+    }
+
+    /// Set the initial window size (in octets) for HTTP/2 connection-level flow control for
+    /// received data.
+    ///
+    /// The default value is 65,535 and is good for APIs, but not for big objects.
+    ///
+    /// # Panics
+    /// The size is validated by [`HttpServiceBuilder::h2_initial_connection_window_size`] when
+    /// the server starts, which panics if `size` is larger than 2^31 - 1.
+    pub fn h2_initial_connection_window_size(self, size: u32) -> Self {
+        self.config
+            .lock()
+            .unwrap()
+            .h2_initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Set the maximum HTTP/2 frame payload size (in octets) the server is willing to receive.
+    ///
+    /// The default value is 16,384.
+    ///
+    /// # Panics
+    /// The size is validated by [`HttpServiceBuilder::h2_max_frame_size`] when the server starts,
+    /// which panics if `size` is not between 16,384 and 16,777,215.
+    pub fn h2_max_frame_size(self, size: u32) -> Self {
+        self.config.lock().unwrap().h2_max_frame_size = Some(size);
+        self
+    }
+
+    /// Set the maximum size (in octets) of the HTTP/2 header list the server is willing to
+    /// accept.
+    ///
+    /// By default the header list size is limited to 16MiB.
+    pub fn h2_max_header_list_size(self, size: u32) -> Self {
+        self.config.lock().unwrap().h2_max_header_list_size = Some(size);
+        self
+    }
+
     /// Set server host name.
     ///
     /// Host name is used by application router as a hostname for url generation.
@@ -248,6 +396,36 @@ where
         self
     }
 
+    /// Get signal for graceful shutdown of HTTP/2 connections.
+    ///
+    /// Triggering the signal makes HTTP/2 connections send a GOAWAY frame and close once their
+    /// in-flight streams are finished, within the [shutdown timeout](Self::shutdown_timeout).
+    /// The signal is not triggered automatically; trigger it before stopping the server with
+    /// [`Server::stop`] or from the application's own signal handler.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use actix_web::{web, App, HttpResponse, HttpServer};
+    ///
+    /// #[actix_rt::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     let srv = HttpServer::new(|| App::new().route("/", web::get().to(HttpResponse::Ok)))
+    ///         .disable_signals()
+    ///         .bind("127.0.0.1:0")?;
+    ///
+    ///     let shutdown_signal = srv.shutdown_signal();
+    ///     let srv = srv.run();
+    ///
+    ///     // stop gracefully, letting HTTP/2 clients know with a GOAWAY frame
+    ///     shutdown_signal.shutdown();
+    ///     srv.stop(true).await;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        self.config.lock().unwrap().shutdown_signal.clone()
+    }
+
     /// Get addresses of bound sockets.
     pub fn addrs(&self) -> Vec<net::SocketAddr> {
         self.sockets.iter().map(|s| s.addr).collect()
@@ -286,7 +464,9 @@ where
                     let svc = HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
+                        .shutdown_signal(c.shutdown_signal.clone())
                         .local_addr(addr);
+                    let svc = c.protocol_settings(svc);
 
                     let svc = if let Some(handler) = on_connect_fn.clone() {
                         svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext))
@@ -339,7 +519,9 @@ where
                     let svc = HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
-                        .client_disconnect(c.client_shutdown);
+                        .client_disconnect(c.client_shutdown)
+                        .shutdown_signal(c.shutdown_signal.clone());
+                    let svc = c.protocol_settings(svc);
 
                     let svc = if let Some(handler) = on_connect_fn.clone() {
                         svc.on_connect_ext(move |io: &_, ext: _| {
@@ -394,7 +576,9 @@ where
                     let svc = HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
-                        .client_disconnect(c.client_shutdown);
+                        .client_disconnect(c.client_shutdown)
+                        .shutdown_signal(c.shutdown_signal.clone());
+                    let svc = c.protocol_settings(svc);
 
                     let svc = if let Some(handler) = on_connect_fn.clone() {
                         svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext))
@@ -514,7 +698,9 @@ where
             pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None))).and_then({
                 let svc = HttpService::build()
                     .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout);
+                    .client_timeout(c.client_timeout)
+                    .shutdown_signal(c.shutdown_signal.clone());
+                let svc = c.protocol_settings(svc);
 
                 let svc = if let Some(handler) = on_connect_fn.clone() {
                     svc.on_connect_ext(move |io: &_, ext: _| (&*handler)(io as &dyn Any, ext))
@@ -555,12 +741,14 @@ where
                     socket_addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
                 );
-                pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None))).and_then(
-                    HttpService::build()
-                        .keep_alive(c.keep_alive)
-                        .client_timeout(c.client_timeout)
-                        .finish(map_config(factory(), move |_| config.clone())),
-                )
+                let svc = HttpService::build()
+                    .keep_alive(c.keep_alive)
+                    .client_timeout(c.client_timeout)
+                    .shutdown_signal(c.shutdown_signal.clone());
+                let svc = c.protocol_settings(svc);
+
+                pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None)))
+                    .and_then(svc.finish(map_config(factory(), move |_| config.clone())))
             },
         )?;
         Ok(self)
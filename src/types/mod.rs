@@ -8,6 +8,7 @@ mod path;
 pub(crate) mod payload;
 mod query;
 pub(crate) mod readlines;
+mod sse;
 
 pub use self::either::{Either, EitherExtractError};
 pub use self::form::{Form, FormConfig};
@@ -16,3 +17,4 @@ pub use self::path::{Path, PathConfig};
 pub use self::payload::{Payload, PayloadConfig};
 pub use self::query::{Query, QueryConfig};
 pub use self::readlines::Readlines;
+pub use self::sse::{Sse, SseEvent};
@@ -0,0 +1,326 @@
+//! For Server-Sent Events responder documentation, see [`Sse`].
+
+use std::{
+    fmt::Write as _,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::{sleep, Instant, Sleep};
+use bytes::{Bytes, BytesMut};
+use futures_core::{ready, Stream};
+use pin_project::pin_project;
+
+use crate::{
+    dev::BodyEncoding,
+    http::header::{ContentEncoding, CACHE_CONTROL, CONTENT_TYPE},
+    Error, HttpRequest, HttpResponse, Responder,
+};
+
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A single Server-Sent Event.
+///
+/// Events are serialized in the `text/event-stream` format. Multi-line data and comments are split
+/// into one field per line.
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_web::web::SseEvent;
+///
+/// let event = SseEvent::data("{\"status\":\"ok\"}")
+///     .event("status")
+///     .id("42")
+///     .retry(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    comment: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseEvent {
+    /// Create an event carrying the given data.
+    pub fn data(data: impl Into<String>) -> Self {
+        SseEvent {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create an event consisting only of a comment.
+    ///
+    /// Comments are ignored by clients and are mostly useful to keep a connection alive.
+    pub fn comment(comment: impl Into<String>) -> Self {
+        SseEvent {
+            comment: Some(comment.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set the event ID.
+    ///
+    /// Clients send the last seen ID back in the `Last-Event-ID` header when reconnecting.
+    ///
+    /// # Panics
+    /// Panics if `id` contains a line break.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        assert!(
+            !has_line_break(&id),
+            "SSE event ID can not contain line breaks"
+        );
+        self.id = Some(id);
+        self
+    }
+
+    /// Set the event type.
+    ///
+    /// Clients treat events without a type as `message` events.
+    ///
+    /// # Panics
+    /// Panics if `event` contains a line break.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        let event = event.into();
+        assert!(
+            !has_line_break(&event),
+            "SSE event type can not contain line breaks"
+        );
+        self.event = Some(event);
+        self
+    }
+
+    /// Set the reconnection time clients should use if the connection is lost.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn write_to(&self, buf: &mut BytesMut) {
+        if let Some(ref comment) = self.comment {
+            write_lines(buf, "", comment);
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(buf, "retry: {}", retry.as_millis());
+        }
+        if let Some(ref id) = self.id {
+            let _ = writeln!(buf, "id: {}", id);
+        }
+        if let Some(ref event) = self.event {
+            let _ = writeln!(buf, "event: {}", event);
+        }
+        if let Some(ref data) = self.data {
+            write_lines(buf, "data", data);
+        }
+        buf.extend_from_slice(b"\n");
+    }
+}
+
+fn has_line_break(val: &str) -> bool {
+    val.contains(|c| c == '\r' || c == '\n')
+}
+
+fn write_lines(buf: &mut BytesMut, field: &str, val: &str) {
+    for line in val.replace("\r\n", "\n").split(|c| c == '\r' || c == '\n') {
+        let _ = writeln!(buf, "{}: {}", field, line);
+    }
+}
+
+/// Server-Sent Events responder.
+///
+/// Wraps a stream of [`SseEvent`]s and sends them to the client as a `text/event-stream` response.
+/// The response is never compressed and a keep-alive comment is sent whenever the stream has been
+/// idle for the keep-alive interval (15 seconds by default).
+///
+/// ```
+/// use actix_web::{get, web, Error, Responder};
+/// use futures_util::stream;
+///
+/// #[get("/events")]
+/// async fn events() -> impl Responder {
+///     let events = stream::iter(vec![
+///         Ok::<_, Error>(web::SseEvent::data("first")),
+///         Ok(web::SseEvent::data("second").event("update")),
+///     ]);
+///
+///     web::Sse::new(events)
+/// }
+/// ```
+pub struct Sse<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S, E> Sse<S>
+where
+    S: Stream<Item = Result<SseEvent, E>> + 'static,
+    E: Into<Error> + 'static,
+{
+    /// Create a responder from a stream of events.
+    pub fn new(stream: S) -> Self {
+        Sse {
+            stream,
+            keep_alive: Some(DEFAULT_KEEP_ALIVE),
+        }
+    }
+
+    /// Set the interval after which a keep-alive comment is sent on an idle stream.
+    ///
+    /// By default, the interval is 15 seconds.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Disable keep-alive comments.
+    pub fn disable_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+}
+
+impl<S, E> Responder for Sse<S>
+where
+    S: Stream<Item = Result<SseEvent, E>> + 'static,
+    E: Into<Error> + 'static,
+{
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        let body = SseStream {
+            stream: self.stream,
+            keep_alive: self.keep_alive,
+            timer: self.keep_alive.map(|dur| Box::pin(sleep(dur))),
+        };
+
+        HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, "text/event-stream"))
+            .insert_header((CACHE_CONTROL, "no-cache"))
+            .encoding(ContentEncoding::Identity)
+            .streaming(Box::pin(body))
+    }
+}
+
+#[pin_project]
+struct SseStream<S> {
+    #[pin]
+    stream: S,
+    keep_alive: Option<Duration>,
+    timer: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S, E> Stream for SseStream<S>
+where
+    S: Stream<Item = Result<SseEvent, E>>,
+    E: Into<Error>,
+{
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(Ok(event))) => {
+                if let (Some(timer), Some(dur)) = (this.timer, *this.keep_alive) {
+                    timer.as_mut().reset(Instant::now() + dur);
+                }
+
+                let mut buf = BytesMut::new();
+                event.write_to(&mut buf);
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => match (this.timer, *this.keep_alive) {
+                (Some(timer), Some(dur)) => {
+                    ready!(timer.as_mut().poll(cx));
+                    timer.as_mut().reset(Instant::now() + dur);
+                    Poll::Ready(Some(Ok(Bytes::from_static(b": keep-alive\n\n"))))
+                }
+                _ => Poll::Pending,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::StatusCode;
+    use futures_util::{stream, StreamExt as _};
+
+    use super::*;
+    use crate::test::{read_body, TestRequest};
+
+    #[test]
+    fn test_event_format() {
+        let mut buf = BytesMut::new();
+        SseEvent::data("line 1\nline 2\r\nline 3")
+            .id("1")
+            .event("update")
+            .retry(Duration::from_millis(1500))
+            .write_to(&mut buf);
+        assert_eq!(
+            &buf[..],
+            b"retry: 1500\nid: 1\nevent: update\ndata: line 1\ndata: line 2\ndata: line 3\n\n"
+                as &[u8]
+        );
+
+        let mut buf = BytesMut::new();
+        SseEvent::comment("ping").write_to(&mut buf);
+        assert_eq!(&buf[..], b": ping\n\n");
+
+        let mut buf = BytesMut::new();
+        SseEvent::data("").write_to(&mut buf);
+        assert_eq!(&buf[..], b"data: \n\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_event_id_line_break() {
+        let _ = SseEvent::data("data").id("1\n2");
+    }
+
+    #[actix_rt::test]
+    async fn test_responder() {
+        let req = TestRequest::default().to_http_request();
+        let events = stream::iter(vec![
+            Ok::<_, Error>(SseEvent::data("first")),
+            Ok(SseEvent::data("second").event("update").id("2")),
+        ]);
+
+        let res = Sse::new(events).respond_to(&req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(res.headers().get(CACHE_CONTROL).unwrap(), "no-cache");
+        assert_eq!(res.get_encoding(), Some(ContentEncoding::Identity));
+
+        let body = read_body(crate::dev::ServiceResponse::new(req, res)).await;
+        assert_eq!(
+            body,
+            Bytes::from_static(b"data: first\n\nid: 2\nevent: update\ndata: second\n\n")
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_keep_alive() {
+        let req = TestRequest::default().to_http_request();
+        let events = stream::pending::<Result<SseEvent, Error>>();
+
+        let mut res = Sse::new(events)
+            .keep_alive(Duration::from_millis(10))
+            .respond_to(&req);
+
+        let mut body = res.take_body();
+        let chunk = body.next().await;
+        assert_eq!(
+            chunk.unwrap().unwrap(),
+            Bytes::from_static(b": keep-alive\n\n")
+        );
+    }
+}
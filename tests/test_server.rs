@@ -477,6 +477,73 @@ async fn test_body_brotli() {
     assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
 }
 
+#[cfg(feature = "compress-zstd")]
+#[actix_rt::test]
+async fn test_body_zstd() {
+    let srv = test::start_with(test::config().h1(), || {
+        App::new()
+            .wrap(Compress::new(ContentEncoding::Zstd))
+            .service(web::resource("/").route(web::to(move || HttpResponse::Ok().body(STR))))
+    });
+
+    // client request
+    let mut response = srv
+        .get("/")
+        .append_header((ACCEPT_ENCODING, "zstd"))
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+
+    // read response
+    let bytes = response.body().await.unwrap();
+
+    // decode zstd
+    let dec = zstd::stream::decode_all(bytes.as_ref()).unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+
+    // client decompresses transparently
+    let mut response = srv
+        .get("/")
+        .append_header((ACCEPT_ENCODING, "zstd"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[cfg(feature = "compress-zstd")]
+#[actix_rt::test]
+async fn test_body_zstd_streaming() {
+    let srv = test::start_with(test::config().h1(), || {
+        App::new()
+            .wrap(Compress::default())
+            .service(web::resource("/").route(web::to(move || {
+                HttpResponse::Ok()
+                    .streaming(TestBody::new(Bytes::from_static(STR.as_ref()), 24))
+            })))
+    });
+
+    let mut response = srv
+        .get("/")
+        .append_header((ACCEPT_ENCODING, "gzip, zstd"))
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+
+    let bytes = response.body().await.unwrap();
+    let dec = zstd::stream::decode_all(bytes.as_ref()).unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+}
+
 #[actix_rt::test]
 async fn test_encoding() {
     let srv = test::start_with(test::config().h1(), || {
@@ -728,6 +795,64 @@ async fn test_brotli_encoding_large() {
     assert_eq!(bytes, Bytes::from(data));
 }
 
+#[cfg(feature = "compress-zstd")]
+#[actix_rt::test]
+async fn test_zstd_encoding() {
+    let srv = test::start_with(test::config().h1(), || {
+        App::new().service(
+            web::resource("/").route(web::to(move |body: Bytes| HttpResponse::Ok().body(body))),
+        )
+    });
+
+    let enc = zstd::stream::encode_all(STR.as_bytes(), 5).unwrap();
+
+    // client request
+    let request = srv
+        .post("/")
+        .append_header((CONTENT_ENCODING, "zstd"))
+        .send_body(enc);
+    let mut response = request.await.unwrap();
+    assert!(response.status().is_success());
+
+    // read response
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[cfg(feature = "compress-zstd")]
+#[actix_rt::test]
+async fn test_zstd_encoding_large() {
+    let data = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(320_000)
+        .map(char::from)
+        .collect::<String>();
+
+    let srv = test::start_with(test::config().h1(), || {
+        App::new().service(
+            web::resource("/")
+                .app_data(web::PayloadConfig::new(320_000))
+                .route(web::to(move |body: Bytes| {
+                    HttpResponse::Ok().streaming(TestBody::new(body, 10240))
+                })),
+        )
+    });
+
+    let enc = zstd::stream::encode_all(data.as_bytes(), 5).unwrap();
+
+    // client request
+    let request = srv
+        .post("/")
+        .append_header((CONTENT_ENCODING, "zstd"))
+        .send_body(enc);
+    let mut response = request.await.unwrap();
+    assert!(response.status().is_success());
+
+    // read response
+    let bytes = response.body().limit(320_000).await.unwrap();
+    assert_eq!(bytes, Bytes::from(data));
+}
+
 #[cfg(feature = "openssl")]
 #[actix_rt::test]
 async fn test_brotli_encoding_large_openssl() {
@@ -907,3 +1032,40 @@ async fn test_normalize() {
     let response = srv.get("/one/").send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[actix_rt::test]
+async fn test_forward_request_id() {
+    use actix_web::middleware::{ForwardRequestId, RequestId};
+
+    let upstream = test::start(|| {
+        App::new().service(web::resource("/").to(|req: web::HttpRequest| {
+            let id = req.headers().get("x-request-id").unwrap().clone();
+            HttpResponse::Ok().body(id.as_bytes().to_vec())
+        }))
+    });
+    let upstream_url = upstream.url("/");
+
+    let srv = test::start(move || {
+        let upstream_url = upstream_url.clone();
+
+        App::new()
+            .wrap(RequestId::new())
+            .service(web::resource("/").to(move |fwd: ForwardRequestId| {
+                let req = fwd.apply(awc::Client::new().get(&upstream_url));
+
+                async move {
+                    let body = req.send().await.unwrap().body().await.unwrap();
+                    Ok::<_, Error>(HttpResponse::Ok().body(body))
+                }
+            }))
+    });
+
+    let mut res = srv
+        .get("/")
+        .insert_header(("x-request-id", "abc"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "abc");
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"abc"));
+}